@@ -2,7 +2,7 @@
 CREATE TABLE IF NOT EXISTS accounts (
   id INTEGER PRIMARY KEY,
   name TEXT NOT NULL,
-  type TEXT CHECK(type IN ('bank','cash','investment','bucket')) NOT NULL,
+  type TEXT CHECK(type IN ('bank','cash','investment','bucket','credit_card','loan')) NOT NULL,
   opening_balance REAL NOT NULL,
   parent_id INTEGER REFERENCES accounts(id),
   bucket_role TEXT DEFAULT 'none',
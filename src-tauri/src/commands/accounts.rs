@@ -1,7 +1,11 @@
-use rusqlite::{params, Result};
+use rusqlite::{params, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::balances::account_balance_as_of;
+use super::currency::{base_currency, normalize_currency};
+use super::journal::ChangeScope;
+use super::transactions::{remove_transaction, sync_goal_progress};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Account {
@@ -14,10 +18,98 @@ pub struct Account {
     pub bucket_role: String,
     pub is_investment_active: bool,
     pub notes: Option<String>,
+    pub currency: Option<String>, // Buckets default to their parent's, others to the base currency
+    // Credit cards only
+    pub credit_limit: Option<f64>,
+    pub statement_day: Option<i64>,   // Day of month the cycle closes
+    pub due_day: Option<i64>,         // Day of month payment is due
+    pub min_due_percent: Option<f64>, // Minimum due as % of the billed amount (default 5)
+    pub closed_on: Option<String>,    // Set by close_account; closed accounts keep their history
+    pub target_balance: Option<f64>,  // Buckets: fill target for allocation tiers
+    pub balance_floor: Option<f64>,   // Forecast warns when the balance would drop below this
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountMergeResult {
+    pub transactions_moved: i64,
+    pub transfers_removed: i64, // Transfers between the two accounts
+    pub removed_transfer_ids: Vec<i64>,
+    pub investments_moved: i64,
+    pub buckets_moved: i64,
+    pub schedules_moved: i64,
+}
+
+fn validate_card_settings(account: &Account) -> Result<(), String> {
+    if account.account_type != "credit_card" {
+        return Ok(());
+    }
+    for (label, day) in [("Statement day", account.statement_day), ("Due day", account.due_day)] {
+        match day {
+            Some(d) if (1..=31).contains(&d) => {}
+            _ => return Err(format!("{} must be between 1 and 31 for a credit card", label)),
+        }
+    }
+    if account.credit_limit.is_some_and(|l| l < 0.0) {
+        return Err("Credit limit cannot be negative".to_string());
+    }
+    Ok(())
+}
+
+// Allocation tiers address a parent's buckets by role, so each role may be held
+// by only one open bucket per parent. Runs after the write, before commit.
+fn ensure_unique_bucket_roles(conn: &rusqlite::Connection) -> Result<(), String> {
+    let duplicate: Option<(String, String)> = conn.query_row(
+        "SELECT p.name, a.bucket_role
+         FROM accounts a JOIN accounts p ON a.parent_id = p.id
+         WHERE a.closed_on IS NULL AND COALESCE(a.bucket_role, 'none') NOT IN ('none', '')
+         GROUP BY a.parent_id, a.bucket_role
+         HAVING COUNT(*) > 1
+         LIMIT 1",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).optional().map_err(|e| e.to_string())?;
+
+    match duplicate {
+        Some((parent, role)) => Err(format!("{} already has an open bucket with the {} role", parent, role)),
+        None => Ok(()),
+    }
+}
+
+// Resolves the currency to store: explicit code, else the parent's, else the base currency
+fn resolve_account_currency(conn: &rusqlite::Connection, account: &Account) -> Result<String, String> {
+    if let Some(code) = account.currency.as_deref().filter(|c| !c.trim().is_empty()) {
+        return normalize_currency(code);
+    }
+    if let Some(parent_id) = account.parent_id {
+        return conn.query_row("SELECT currency FROM accounts WHERE id = ?1", [parent_id], |r| r.get(0))
+            .map_err(|_| "Parent account not found".to_string());
+    }
+    base_currency(conn)
+}
+
+/// Rejects postings to a closed account dated after its closing date.
+pub(crate) fn ensure_account_open(conn: &rusqlite::Connection, account_id: Option<i64>, date: &str) -> Result<(), String> {
+    let Some(account_id) = account_id else {
+        return Ok(());
+    };
+    let closed: Option<(String, String)> = conn.query_row(
+        "SELECT name, closed_on FROM accounts WHERE id = ?1 AND closed_on IS NOT NULL",
+        [account_id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).optional().map_err(|e| e.to_string())?;
+
+    match closed {
+        Some((name, closed_on)) if date > closed_on.as_str() => {
+            Err(format!("{} was closed on {}; reopen it to add later transactions", name, closed_on))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Lists open accounts; closed ones are included only when asked for (account
+/// management), so pickers stay clean.
 #[tauri::command]
-pub fn get_accounts(db: State<DbConnection>) -> Result<Vec<Account>, String> {
+pub fn get_accounts(db: State<DbConnection>, include_closed: Option<bool>) -> Result<Vec<Account>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
     let mut stmt = conn
@@ -27,22 +119,19 @@ pub fn get_accounts(db: State<DbConnection>) -> Result<Vec<Account>, String> {
                 a.is_investment_active,
                 a.opening_balance + 
                 COALESCE((
-                    SELECT SUM(amount) FROM transactions 
-                    WHERE to_account_id = a.id 
-                    OR to_account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
-                ), 0) -
-                COALESCE((
-                    SELECT SUM(amount) FROM transactions 
-                    WHERE from_account_id = a.id
-                    OR from_account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
-                ), 0) as current_balance
+                    SELECT SUM(net_amount) FROM account_daily_balances 
+                    WHERE account_id = a.id 
+                    OR account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
+                ), 0) as current_balance,
+                a.currency, a.credit_limit, a.statement_day, a.due_day, a.min_due_percent, a.closed_on, a.target_balance, a.balance_floor
             FROM accounts a 
+            WHERE ?1 OR a.closed_on IS NULL
             ORDER BY a.name
         ")
         .map_err(|e| e.to_string())?;
     
     let accounts = stmt
-        .query_map([], |row| {
+        .query_map([include_closed.unwrap_or(false)], |row| {
             Ok(Account {
                 id: Some(row.get(0)?),
                 name: row.get(1)?,
@@ -53,6 +142,14 @@ pub fn get_accounts(db: State<DbConnection>) -> Result<Vec<Account>, String> {
                 bucket_role: row.get(6)?,
                 is_investment_active: row.get(7)?,
                 current_balance: Some(row.get(8)?),
+                currency: row.get(9)?,
+                credit_limit: row.get(10)?,
+                statement_day: row.get(11)?,
+                due_day: row.get(12)?,
+                min_due_percent: row.get(13)?,
+                closed_on: row.get(14)?,
+                target_balance: row.get(15)?,
+                balance_floor: row.get(16)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -67,10 +164,15 @@ pub fn create_account(
     db: State<DbConnection>,
     account: Account,
 ) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_account")?;
+    validate_card_settings(&account)?;
+    let currency = resolve_account_currency(&tx, &account)?;
     
-    conn.execute(
-        "INSERT INTO accounts (name, type, opening_balance, notes, parent_id, bucket_role, is_investment_active) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    tx.execute(
+        "INSERT INTO accounts (name, type, opening_balance, notes, parent_id, bucket_role, is_investment_active, currency, credit_limit, statement_day, due_day, min_due_percent, target_balance, balance_floor)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
         params![
             account.name,
             account.account_type,
@@ -79,11 +181,22 @@ pub fn create_account(
             account.parent_id,
             account.bucket_role,
             account.is_investment_active,
+            currency,
+            account.credit_limit,
+            account.statement_day,
+            account.due_day,
+            account.min_due_percent,
+            account.target_balance,
+            account.balance_floor,
         ],
     )
     .map_err(|e| e.to_string())?;
-    
-    Ok(conn.last_insert_rowid())
+    let id = tx.last_insert_rowid();
+    ensure_unique_bucket_roles(&tx)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
 }
 
 #[tauri::command]
@@ -91,12 +204,31 @@ pub fn update_account(
     db: State<DbConnection>,
     account: Account,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_account")?;
     
     let id = account.id.ok_or("Account ID is required")?;
+    validate_card_settings(&account)?;
+
+    // Stored amounts are in the account's currency, so it is fixed once transactions exist
+    let currency = match account.currency.as_deref().filter(|c| !c.trim().is_empty()) {
+        Some(code) => normalize_currency(code)?,
+        None => tx.query_row("SELECT currency FROM accounts WHERE id = ?1", [id], |r| r.get(0))
+            .map_err(|_| "Account not found".to_string())?,
+    };
+    let (old_currency, tx_count): (String, i64) = tx.query_row(
+        "SELECT currency, (SELECT COUNT(*) FROM transactions WHERE from_account_id = ?1 OR to_account_id = ?1) FROM accounts WHERE id = ?1",
+        [id],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).map_err(|_| "Account not found".to_string())?;
+    if currency != old_currency && tx_count > 0 {
+        return Err(format!("Cannot change currency. There are {} transactions linked to this account.", tx_count));
+    }
     
-    conn.execute(
-        "UPDATE accounts SET name = ?1, type = ?2, opening_balance = ?3, notes = ?4, parent_id = ?5, bucket_role = ?6, is_investment_active = ?7 WHERE id = ?8",
+    tx.execute(
+        "UPDATE accounts SET name = ?1, type = ?2, opening_balance = ?3, notes = ?4, parent_id = ?5, bucket_role = ?6, is_investment_active = ?7, currency = ?8,
+         credit_limit = ?9, statement_day = ?10, due_day = ?11, min_due_percent = ?12, target_balance = ?13, balance_floor = ?14 WHERE id = ?15",
         params![
             account.name,
             account.account_type,
@@ -105,11 +237,21 @@ pub fn update_account(
             account.parent_id,
             account.bucket_role,
             account.is_investment_active,
+            currency,
+            account.credit_limit,
+            account.statement_day,
+            account.due_day,
+            account.min_due_percent,
+            account.target_balance,
+            account.balance_floor,
             id,
         ],
     )
     .map_err(|e| e.to_string())?;
-    
+    ensure_unique_bucket_roles(&tx)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -118,10 +260,12 @@ pub fn delete_account(
     db: State<DbConnection>,
     id: i64,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_account")?;
     
     // Safety Check: Check if any transactions are linked to this account
-    let tx_count: i64 = conn.query_row(
+    let tx_count: i64 = tx.query_row(
         "SELECT COUNT(*) FROM transactions WHERE from_account_id = ?1 OR to_account_id = ?1",
         rusqlite::params![id],
         |row| row.get(0),
@@ -132,7 +276,7 @@ pub fn delete_account(
     }
     
     // Also check if any investments are linked to this account
-    let inv_count: i64 = conn.query_row(
+    let inv_count: i64 = tx.query_row(
         "SELECT COUNT(*) FROM investments WHERE account_id = ?1",
         rusqlite::params![id],
         |row| row.get(0),
@@ -142,11 +286,246 @@ pub fn delete_account(
         return Err(format!("Cannot delete account. There are {} investments linked to it.", inv_count));
     }
     
-    conn.execute(
+    tx.execute(
+        "DELETE FROM card_statements WHERE account_id = ?1",
+        rusqlite::params![id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.execute(
+        "DELETE FROM balance_assertions WHERE account_id = ?1",
+        rusqlite::params![id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.execute(
+        "DELETE FROM loan_payments WHERE loan_id IN (SELECT id FROM loans WHERE account_id = ?1)",
+        rusqlite::params![id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.execute(
+        "DELETE FROM loans WHERE account_id = ?1",
+        rusqlite::params![id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.execute(
         "DELETE FROM accounts WHERE id = ?1",
         rusqlite::params![id],
     )
     .map_err(|e| e.to_string())?;
-    
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
+
+/// Closes an account (and its buckets) as of `closed_on`. The balance must be
+/// zero on that date and nothing may be posted after it. Closed accounts drop
+/// out of pickers and the dashboard but stay in reports.
+#[tauri::command]
+pub fn close_account(db: State<DbConnection>, id: i64, closed_on: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "close_account")?;
+
+    chrono::NaiveDate::parse_from_str(&closed_on, "%Y-%m-%d").map_err(|_| "Closing date must be YYYY-MM-DD".to_string())?;
+
+    let mut stmt = conn.prepare("SELECT id FROM accounts WHERE id = ?1 OR parent_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([id], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    if !ids.contains(&id) {
+        return Err("Account not found".to_string());
+    }
+
+    let mut balance = 0.0;
+    for account_id in &ids {
+        balance += account_balance_as_of(&conn, *account_id, Some(&closed_on))?;
+    }
+    if balance.abs() >= 0.005 {
+        return Err(format!("Balance on {} is {:.2}; move it out before closing", closed_on, balance));
+    }
+
+    for account_id in &ids {
+        let later: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM transactions WHERE (from_account_id = ?1 OR to_account_id = ?1) AND date > ?2",
+            params![account_id, closed_on],
+            |r| r.get(0),
+        ).map_err(|e| e.to_string())?;
+        if later > 0 {
+            return Err(format!("There are {} transactions after {}", later, closed_on));
+        }
+
+        let schedules: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM scheduled_transactions WHERE is_active = 1 AND (from_account_id = ?1 OR to_account_id = ?1)",
+            [account_id],
+            |r| r.get(0),
+        ).map_err(|e| e.to_string())?;
+        if schedules > 0 {
+            return Err(format!("There are {} active scheduled transactions on this account", schedules));
+        }
+    }
+
+    conn.execute(
+        "UPDATE accounts SET closed_on = ?1 WHERE id = ?2 OR parent_id = ?2",
+        params![closed_on, id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn reopen_account(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "reopen_account")?;
+
+    tx.execute(
+        "UPDATE accounts SET closed_on = NULL WHERE id = ?1 OR parent_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    ensure_unique_bucket_roles(&tx)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Moves everything that points at `source_id` (transactions, investments,
+/// buckets, goals, scheduled transactions, rules and the opening balance) to
+/// `target_id` and deletes the source, all in one database transaction.
+/// Transfers between the two accounts are deleted and reported by id.
+#[tauri::command]
+pub fn merge_accounts(db: State<DbConnection>, source_id: i64, target_id: i64) -> Result<AccountMergeResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    if source_id == target_id {
+        return Err("Choose two different accounts to merge".to_string());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "merge_accounts")?;
+
+    let load = |id: i64| -> Result<(String, String, Option<i64>, Option<String>), String> {
+        tx.query_row(
+            "SELECT type, currency, parent_id, closed_on FROM accounts WHERE id = ?1",
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        ).map_err(|_| format!("Account {} not found", id))
+    };
+    let (source_type, source_currency, _, _) = load(source_id)?;
+    let (target_type, target_currency, target_parent, target_closed) = load(target_id)?;
+
+    // Cards, loans, investments and buckets carry type-specific data, so
+    // only bank and cash accounts may merge across types
+    let interchangeable = |t: &str| t == "bank" || t == "cash";
+    if source_type != target_type && !(interchangeable(&source_type) && interchangeable(&target_type)) {
+        return Err(format!("Cannot merge a {} account into a {} account", source_type, target_type));
+    }
+
+    // Amounts are stored in the account's currency, so they only carry over 1:1
+    if source_currency != target_currency {
+        return Err(format!("Cannot merge a {} account into a {} account", source_currency, target_currency));
+    }
+    if target_closed.is_some() {
+        return Err("Reopen the target account before merging into it".to_string());
+    }
+    if target_parent == Some(source_id) {
+        return Err("Cannot merge an account into one of its own buckets".to_string());
+    }
+    let source_buckets: i64 = tx.query_row("SELECT COUNT(*) FROM accounts WHERE parent_id = ?1", [source_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    if source_buckets > 0 && target_parent.is_some() {
+        return Err("The source has buckets, so the target must be a top-level account".to_string());
+    }
+    let reconciled: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE (from_account_id = ?1 OR to_account_id = ?1) AND cleared_status = 'reconciled'",
+        [source_id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+    if reconciled > 0 {
+        return Err(format!("{} transactions are reconciled on the source account; un-reconcile them first", reconciled));
+    }
+    let has_loan: i64 = tx.query_row("SELECT COUNT(*) FROM loans WHERE account_id IN (?1, ?2)", params![source_id, target_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    if has_loan > 0 {
+        return Err("Loan accounts carry their own terms and cannot be merged".to_string());
+    }
+
+    // Transfers between the two accounts would become transfers to itself
+    let mut stmt = tx.prepare(
+        "SELECT id FROM transactions WHERE (from_account_id = ?1 AND to_account_id = ?2) OR (from_account_id = ?2 AND to_account_id = ?1)"
+    ).map_err(|e| e.to_string())?;
+    let internal = stmt
+        .query_map(params![source_id, target_id], |r| r.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+    let mut goals = Vec::new();
+    for id in &internal {
+        if let Some(goal_id) = remove_transaction(&tx, *id)? {
+            goals.push(goal_id);
+        }
+    }
+
+    let moved_from = tx.execute("UPDATE transactions SET from_account_id = ?2 WHERE from_account_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    let moved_to = tx.execute("UPDATE transactions SET to_account_id = ?2 WHERE to_account_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    let investments_moved = tx.execute("UPDATE investments SET account_id = ?2 WHERE account_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    let buckets_moved = tx.execute("UPDATE accounts SET parent_id = ?2 WHERE parent_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    let schedules_from = tx.execute("UPDATE scheduled_transactions SET from_account_id = ?2 WHERE from_account_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    let schedules_to = tx.execute("UPDATE scheduled_transactions SET to_account_id = ?2 WHERE to_account_id = ?1", params![source_id, target_id])
+        .map_err(|e| e.to_string())?;
+    for sql in [
+        "UPDATE goals SET bucket_id = ?2 WHERE bucket_id = ?1",
+        "UPDATE categorization_rules SET account_id = ?2 WHERE account_id = ?1",
+        "UPDATE accounts SET opening_balance = opening_balance + (SELECT opening_balance FROM accounts WHERE id = ?1) WHERE id = ?2",
+    ] {
+        tx.execute(sql, params![source_id, target_id]).map_err(|e| e.to_string())?;
+    }
+    for sql in [
+        "DELETE FROM account_statements WHERE account_id = ?1",
+        "DELETE FROM card_statements WHERE account_id = ?1",
+        "DELETE FROM balance_assertions WHERE account_id = ?1",
+        "DELETE FROM accounts WHERE id = ?1",
+    ] {
+        tx.execute(sql, [source_id]).map_err(|e| e.to_string())?;
+    }
+
+    let mut stmt = tx.prepare("SELECT id FROM goals WHERE bucket_id = ?1").map_err(|e| e.to_string())?;
+    goals.extend(
+        stmt.query_map([target_id], |r| r.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?,
+    );
+    drop(stmt);
+    ensure_unique_bucket_roles(&tx)?;
+    goals.sort_unstable();
+    goals.dedup();
+    for goal_id in goals {
+        let _ = sync_goal_progress(&tx, goal_id);
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(AccountMergeResult {
+        transactions_moved: (moved_from + moved_to) as i64,
+        transfers_removed: internal.len() as i64,
+        removed_transfer_ids: internal,
+        investments_moved: investments_moved as i64,
+        buckets_moved: buckets_moved as i64,
+        schedules_moved: (schedules_from + schedules_to) as i64,
+    })
+}
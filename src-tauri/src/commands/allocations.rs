@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::account_balance_as_of;
+use super::goals::load_allocation_rules;
+use super::rules::apply_categorization_rules;
+use super::search::refresh_search_index;
+use super::transactions::{remove_transaction, Transaction};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationLine {
+    pub bucket_id: i64,
+    pub bucket_name: String,
+    pub bucket_role: String,
+    pub amount: f64,
+    pub transaction_id: Option<i64>, // The recorded transfer; None in previews
+}
+
+/// The transfers one income makes into its account's buckets. Previews carry
+/// no ids; a recorded batch is tied to its source transaction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationBatch {
+    pub id: Option<i64>,
+    pub source_transaction_id: Option<i64>,
+    pub account_id: i64,
+    pub date: String,
+    pub tier_id: Option<i64>,
+    pub tier_name: Option<String>,
+    pub income_amount: f64,
+    pub allocated: f64,
+    pub remainder: f64, // Stays in the parent account
+    pub lines: Vec<AllocationLine>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Works out which tier applies and what each bucket receives. Transfers of
+// `replacing` (a batch about to be redone) are left out of the bucket balances.
+fn plan_allocation(
+    conn: &rusqlite::Connection,
+    account_id: i64,
+    category_id: i64,
+    amount: f64,
+    date: &str,
+    replacing: Option<i64>,
+) -> Result<Option<AllocationBatch>, String> {
+    let (default_target, trigger_cat, enabled): (f64, Option<i64>, i32) = conn.query_row(
+        "SELECT emergency_target, trigger_category_id, is_enabled FROM bucket_allocation_settings WHERE id = 1",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+    ).unwrap_or((100000.0, None, 0));
+
+    if enabled == 0 || trigger_cat != Some(category_id) {
+        return Ok(None);
+    }
+
+    // Open buckets under this parent account, by role. Accounts allow one open
+    // bucket per role; data from before that rule is refused rather than guessed at.
+    let mut stmt = conn.prepare(
+        "SELECT id, name, bucket_role, target_balance FROM accounts WHERE parent_id = ?1 AND closed_on IS NULL AND bucket_role != 'none'"
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([account_id], |r| Ok((r.get::<_, String>(2)?, (r.get(0)?, r.get(1)?, r.get(3)?))))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<(String, (i64, String, Option<f64>))>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut buckets = HashMap::new();
+    for (role, bucket) in rows {
+        if let Some((_, other, _)) = buckets.insert(role.clone(), bucket) {
+            return Err(format!("Buckets {} and {} share the {} role; give each a distinct role", other, buckets[&role].1, role));
+        }
+    }
+    if buckets.is_empty() {
+        return Ok(None);
+    }
+
+    // Fill as it stood on the income's date, so back-dated entries and later
+    // re-allocations see what was in the bucket at the time
+    let bucket_balance = |bucket_id: i64| -> Result<f64, String> {
+        let replaced: f64 = conn.query_row(
+            "SELECT COALESCE(SUM(COALESCE(to_amount, amount)), 0) FROM transactions WHERE allocation_batch_id = ?1 AND to_account_id = ?2 AND date <= ?3",
+            params![replacing, bucket_id, date],
+            |r| r.get(0),
+        ).map_err(|e| e.to_string())?;
+        Ok(account_balance_as_of(conn, bucket_id, Some(date))? - replaced)
+    };
+
+    // First tier whose key bucket is below its fill level (unkeyed tiers always match)
+    let mut chosen = None;
+    for rule in load_allocation_rules(conn)? {
+        let matches = match rule.key_role.as_deref() {
+            None => true,
+            Some(role) => match buckets.get(role) {
+                Some((bucket_id, _, target)) => {
+                    let target = target.filter(|t| *t > 0.0).unwrap_or(default_target);
+                    target > 0.0 && bucket_balance(*bucket_id)? < target * rule.fill_below.unwrap_or(0.0)
+                }
+                None => false,
+            },
+        };
+        if matches {
+            chosen = Some(rule);
+            break;
+        }
+    }
+    let Some(rule) = chosen else {
+        return Ok(None);
+    };
+
+    // Shares without a matching bucket stay in the parent with the remainder
+    let mut lines = Vec::new();
+    for share in &rule.shares {
+        let Some((bucket_id, bucket_name, _)) = buckets.get(&share.bucket_role) else {
+            continue;
+        };
+        let line_amount = round2(amount * share.pc);
+        if line_amount > 0.0 {
+            lines.push(AllocationLine {
+                bucket_id: *bucket_id,
+                bucket_name: bucket_name.clone(),
+                bucket_role: share.bucket_role.clone(),
+                amount: line_amount,
+                transaction_id: None,
+            });
+        }
+    }
+
+    let allocated = round2(lines.iter().map(|l| l.amount).sum());
+    Ok(Some(AllocationBatch {
+        id: None,
+        source_transaction_id: None,
+        account_id,
+        date: date.to_string(),
+        tier_id: rule.id,
+        tier_name: rule.name.clone(),
+        income_amount: amount,
+        allocated,
+        remainder: round2(amount - allocated),
+        lines,
+    }))
+}
+
+/// Runs auto-allocation for a saved income and records the resulting
+/// transfers as a batch tied to it. Returns the batch id, if anything was
+/// allocated. The batch is written whole or not at all.
+pub(crate) fn allocate_transaction(conn: &rusqlite::Connection, source_id: i64) -> Result<Option<i64>, String> {
+    conn.execute_batch("SAVEPOINT allocate_transaction").map_err(|e| e.to_string())?;
+    let result = write_allocation(conn, source_id);
+    let finish = match result {
+        Ok(_) => "RELEASE allocate_transaction",
+        Err(_) => "ROLLBACK TO allocate_transaction; RELEASE allocate_transaction",
+    };
+    conn.execute_batch(finish).map_err(|e| e.to_string())?;
+    result
+}
+
+fn write_allocation(conn: &rusqlite::Connection, source_id: i64) -> Result<Option<i64>, String> {
+    let (direction, account_id, category_id, amount, date): (String, Option<i64>, i64, f64, String) = conn.query_row(
+        "SELECT direction, to_account_id, category_id, COALESCE(to_amount, amount), date FROM transactions WHERE id = ?1",
+        [source_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+    ).map_err(|e| e.to_string())?;
+
+    let Some(account_id) = account_id.filter(|_| direction == "income") else {
+        return Ok(None);
+    };
+    let plan = match plan_allocation(conn, account_id, category_id, amount, &date, None)? {
+        Some(plan) if !plan.lines.is_empty() => plan,
+        _ => return Ok(None),
+    };
+
+    conn.execute(
+        "INSERT INTO allocation_batches (source_transaction_id, tier_id) VALUES (?1, ?2)",
+        params![source_id, plan.tier_id],
+    ).map_err(|e| e.to_string())?;
+    let batch_id = conn.last_insert_rowid();
+
+    for line in &plan.lines {
+        conn.execute(
+            "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, notes, currency, allocation_batch_id)
+             VALUES (?1, ?2, 'transfer', ?3, ?4, ?5, ?6, (SELECT currency FROM accounts WHERE id = ?3), ?7)",
+            params![date, line.amount, account_id, line.bucket_id, category_id, format!("Auto-Allocation: {}", line.bucket_name), batch_id],
+        ).map_err(|e| e.to_string())?;
+        refresh_search_index(conn, conn.last_insert_rowid())?;
+    }
+
+    Ok(Some(batch_id))
+}
+
+/// Deletes the allocation batch of a source transaction along with its
+/// transfers. Fails if any of the transfers has been reconciled.
+pub(crate) fn reverse_allocation(conn: &rusqlite::Connection, source_id: i64) -> Result<(), String> {
+    let batch_id: Option<i64> = conn.query_row(
+        "SELECT id FROM allocation_batches WHERE source_transaction_id = ?1",
+        [source_id],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    let Some(batch_id) = batch_id else {
+        return Ok(());
+    };
+
+    let mut stmt = conn.prepare("SELECT id FROM transactions WHERE allocation_batch_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let transfer_ids = stmt
+        .query_map([batch_id], |r| r.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for id in transfer_ids {
+        remove_transaction(conn, id)?;
+    }
+
+    conn.execute("DELETE FROM allocation_batches WHERE id = ?1", [batch_id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Redoes the allocation of an edited transaction so its transfers follow
+/// the new amount, date, account and category. The old batch is removed
+/// first, so its transfers don't count toward the fill the new plan sees.
+pub(crate) fn reallocate_transaction(conn: &rusqlite::Connection, source_id: i64) -> Result<(), String> {
+    reverse_allocation(conn, source_id)?;
+    allocate_transaction(conn, source_id)?;
+    Ok(())
+}
+
+/// What saving this transaction would allocate, without writing anything.
+/// For an existing transaction its current batch is left out of the bucket
+/// balances, as it would be replaced.
+#[tauri::command]
+pub fn preview_allocation(db: State<DbConnection>, transaction: Transaction) -> Result<Option<AllocationBatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut transaction = transaction;
+    if transaction.id.is_none() && transaction.category_id == 0 {
+        apply_categorization_rules(&conn, &mut transaction, &mut Vec::new())?;
+    }
+    let Some(account_id) = transaction.to_account_id.filter(|_| transaction.direction == "income") else {
+        return Ok(None);
+    };
+
+    let replacing: Option<i64> = match transaction.id {
+        Some(id) => conn.query_row("SELECT id FROM allocation_batches WHERE source_transaction_id = ?1", [id], |r| r.get(0))
+            .optional().map_err(|e| e.to_string())?,
+        None => None,
+    };
+
+    let amount = transaction.to_amount.unwrap_or(transaction.amount);
+    plan_allocation(&conn, account_id, transaction.category_id, amount, &transaction.date, replacing)
+}
+
+/// The recorded batch for an income, or for the batch an allocation transfer
+/// belongs to.
+#[tauri::command]
+pub fn get_allocation_batch(db: State<DbConnection>, transaction_id: i64) -> Result<Option<AllocationBatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let batch = conn.query_row(
+        "SELECT b.id, b.source_transaction_id, t.to_account_id, t.date, b.tier_id, r.name, COALESCE(t.to_amount, t.amount)
+         FROM allocation_batches b
+         JOIN transactions t ON b.source_transaction_id = t.id
+         LEFT JOIN allocation_tiers r ON b.tier_id = r.id
+         WHERE b.source_transaction_id = ?1 OR b.id = (SELECT allocation_batch_id FROM transactions WHERE id = ?1)",
+        [transaction_id],
+        |r| Ok((
+            r.get::<_, i64>(0)?, r.get::<_, i64>(1)?, r.get::<_, i64>(2)?, r.get::<_, String>(3)?,
+            r.get::<_, Option<i64>>(4)?, r.get::<_, Option<String>>(5)?, r.get::<_, f64>(6)?,
+        )),
+    ).optional().map_err(|e| e.to_string())?;
+    let Some((id, source_id, account_id, date, tier_id, tier_name, income_amount)) = batch else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare("
+        SELECT a.id, a.name, a.bucket_role, t.amount, t.id
+        FROM transactions t
+        JOIN accounts a ON t.to_account_id = a.id
+        WHERE t.allocation_batch_id = ?1
+        ORDER BY t.id
+    ").map_err(|e| e.to_string())?;
+    let lines = stmt.query_map([id], |r| {
+        Ok(AllocationLine {
+            bucket_id: r.get(0)?,
+            bucket_name: r.get(1)?,
+            bucket_role: r.get(2)?,
+            amount: r.get(3)?,
+            transaction_id: Some(r.get(4)?),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let allocated = round2(lines.iter().map(|l| l.amount).sum());
+    Ok(Some(AllocationBatch {
+        id: Some(id),
+        source_transaction_id: Some(source_id),
+        account_id,
+        date,
+        tier_id,
+        tier_name,
+        income_amount,
+        allocated,
+        remainder: round2(income_amount - allocated),
+        lines,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_tier(conn: &rusqlite::Connection, source_id: i64) -> i64 {
+        conn.query_row(
+            "SELECT t.tier FROM allocation_batches b JOIN allocation_tiers t ON b.tier_id = t.id WHERE b.source_transaction_id = ?1",
+            [source_id],
+            |r| r.get(0),
+        ).unwrap()
+    }
+
+    #[test]
+    fn fill_is_read_on_the_income_date_without_its_own_batch() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        // Default tiers: 1 while emergency is below 50% of target, 2 below 100%
+        conn.execute_batch(
+            "INSERT INTO categories (id, name, kind) VALUES (1, 'Salary', 'income'), (2, 'Savings', 'transfer');
+             INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 0);
+             INSERT INTO accounts (id, name, type, opening_balance, parent_id, bucket_role, target_balance) VALUES
+                (2, 'Emergency', 'bucket', 0, 1, 'emergency', 1000), (3, 'Assets', 'bucket', 0, 1, 'asset', NULL);
+             UPDATE bucket_allocation_settings SET trigger_category_id = 1, is_enabled = 1 WHERE id = 1;
+             INSERT INTO transactions (id, date, amount, direction, from_account_id, to_account_id, category_id)
+                VALUES (1, '2026-06-01', 1000, 'transfer', 1, 2, 2),
+                       (2, '2026-03-01', 1000, 'income', NULL, 1, 1);",
+        ).unwrap();
+
+        // The bucket was only filled in June, so a March income sees it empty
+        allocate_transaction(&conn, 2).unwrap();
+        assert_eq!(batch_tier(&conn, 2), 1);
+
+        // Redoing it ignores the 700 its own batch moved into the bucket
+        reallocate_transaction(&conn, 2).unwrap();
+        assert_eq!(batch_tier(&conn, 2), 1);
+    }
+}
@@ -0,0 +1,197 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::{rolled_up_balance_as_of, statement_rows};
+use super::journal::ChangeScope;
+use super::transactions::{map_transaction_row, TransactionWithDetails, TRANSACTION_DETAILS_SELECT};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceAssertion {
+    pub id: Option<i64>,
+    pub account_id: i64,
+    pub date: String,
+    pub expected_balance: f64, // In the account's currency, at the end of `date`
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceAssertionCheck {
+    pub id: i64,
+    pub account_id: i64,
+    pub account_name: String,
+    pub date: String,
+    pub expected_balance: f64,
+    pub actual_balance: f64,
+    pub gap: f64, // actual - expected
+    pub passed: bool,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssertionDivergence {
+    pub assertion_id: i64,
+    pub account_id: i64,
+    pub gap: f64,
+    pub after_date: Option<String>, // Last assertion before the gap appeared; None = from the start
+    pub on_or_before_date: String,  // First assertion showing this gap
+    pub transactions: Vec<TransactionWithDetails>, // Postings in that window
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Assertions are checked on read against the maintained daily balances, so
+// every edit is reflected immediately. Like a bank statement, an asserted
+// balance covers the account's buckets too.
+fn check_assertions(conn: &rusqlite::Connection, account_id: Option<i64>) -> Result<Vec<BalanceAssertionCheck>, String> {
+    let mut stmt = conn.prepare("
+        SELECT b.id, b.account_id, a.name, b.date, b.expected_balance, b.notes
+        FROM balance_assertions b
+        JOIN accounts a ON b.account_id = a.id
+        WHERE (?1 IS NULL OR b.account_id = ?1)
+        ORDER BY a.name, b.date
+    ").map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([account_id], |r| {
+        Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?, r.get::<_, String>(2)?, r.get::<_, String>(3)?, r.get::<_, f64>(4)?, r.get::<_, Option<String>>(5)?))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut checks = Vec::new();
+    for (id, account_id, account_name, date, expected_balance, notes) in rows {
+        let actual_balance = round2(rolled_up_balance_as_of(conn, account_id, Some(&date))?);
+        let gap = round2(actual_balance - expected_balance);
+        checks.push(BalanceAssertionCheck {
+            id,
+            account_id,
+            account_name,
+            date,
+            expected_balance,
+            actual_balance,
+            gap,
+            passed: gap.abs() < 0.005,
+            notes,
+        });
+    }
+
+    Ok(checks)
+}
+
+#[tauri::command]
+pub fn create_balance_assertion(db: State<DbConnection>, assertion: BalanceAssertion) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_balance_assertion")?;
+
+    chrono::NaiveDate::parse_from_str(&assertion.date, "%Y-%m-%d").map_err(|_| "Date must be YYYY-MM-DD".to_string())?;
+
+    // One assertion per account and day; recording it again replaces the amount
+    conn.execute(
+        "INSERT INTO balance_assertions (account_id, date, expected_balance, notes) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(account_id, date) DO UPDATE SET expected_balance = excluded.expected_balance, notes = excluded.notes",
+        params![assertion.account_id, assertion.date, assertion.expected_balance, assertion.notes],
+    ).map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        "SELECT id FROM balance_assertions WHERE account_id = ?1 AND date = ?2",
+        params![assertion.account_id, assertion.date],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_balance_assertion(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_balance_assertion")?;
+    conn.execute("DELETE FROM balance_assertions WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Every assertion with the computed balance and the gap. `failing_only`
+/// narrows the list to the ones that do not hold.
+#[tauri::command]
+pub fn get_balance_assertions(
+    db: State<DbConnection>,
+    account_id: Option<i64>,
+    failing_only: Option<bool>,
+) -> Result<Vec<BalanceAssertionCheck>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let checks = check_assertions(&conn, account_id)?;
+    if failing_only.unwrap_or(false) {
+        return Ok(checks.into_iter().filter(|c| !c.passed).collect());
+    }
+    Ok(checks)
+}
+
+/// Narrows down where a failing assertion's gap was introduced. Walking back
+/// through the account's earlier assertions, the gap entered after the latest
+/// one with a different gap (usually the last passing one) and on or before
+/// the earliest one that already shows it.
+#[tauri::command]
+pub fn locate_assertion_divergence(db: State<DbConnection>, assertion_id: i64) -> Result<AssertionDivergence, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let account_id: i64 = conn.query_row("SELECT account_id FROM balance_assertions WHERE id = ?1", [assertion_id], |r| r.get(0))
+        .map_err(|_| "Assertion not found".to_string())?;
+
+    let checks = check_assertions(&conn, Some(account_id))?;
+    let position = checks.iter().position(|c| c.id == assertion_id).ok_or("Assertion not found")?;
+    let target = &checks[position];
+    if target.passed {
+        return Err("This assertion holds; there is no divergence to locate".to_string());
+    }
+
+    let mut first = position;
+    while first > 0 && (checks[first - 1].gap - target.gap).abs() < 0.005 {
+        first -= 1;
+    }
+    let after_date = if first > 0 { Some(checks[first - 1].date.clone()) } else { None };
+    let on_or_before_date = checks[first].date.clone();
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE {} AND (?2 IS NULL OR t.date > ?2) AND t.date <= ?3 ORDER BY t.date, t.id",
+        TRANSACTION_DETAILS_SELECT,
+        statement_rows("t."),
+    )).map_err(|e| e.to_string())?;
+    let transactions = stmt
+        .query_map(params![account_id, after_date, on_or_before_date], map_transaction_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(AssertionDivergence {
+        assertion_id,
+        account_id,
+        gap: target.gap,
+        after_date,
+        on_or_before_date,
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assertion_on_a_parent_counts_its_buckets() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 1000);
+             INSERT INTO accounts (id, name, type, opening_balance, parent_id, bucket_role) VALUES (2, 'Emergency', 'bucket', 0, 1, 'emergency');
+             INSERT INTO categories (id, name, kind) VALUES (1, 'Misc', 'expense');
+             INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id)
+             VALUES ('2026-03-02', 400, 'transfer', 1, 2, 1),
+                    ('2026-03-05', 40, 'expense', 2, NULL, 1);
+             INSERT INTO balance_assertions (account_id, date, expected_balance) VALUES (1, '2026-03-03', 1000), (1, '2026-03-06', 1000);",
+        ).unwrap();
+
+        let checks = check_assertions(&conn, Some(1)).unwrap();
+        assert!(checks[0].passed);
+        assert_eq!((checks[1].actual_balance, checks[1].gap), (960.0, -40.0));
+    }
+}
@@ -0,0 +1,329 @@
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use crate::db::{get_app_data_dir, DbConnection};
+use super::journal::ChangeScope;
+
+// Attachment rows are journaled like any other data. Stored files outlive
+// their rows so an undo can bring an attachment back; only
+// cleanup_orphan_attachments removes files, once neither a row nor the
+// journal refers to them.
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub file_hash: String,
+    pub original_name: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub transaction_id: Option<i64>,
+    pub invoice_id: Option<i64>,
+    pub investment_lot_id: Option<i64>,
+    pub created_at: String,
+}
+
+fn attachments_dir() -> PathBuf {
+    get_app_data_dir().join("attachments")
+}
+
+// Content-addressed layout relative to the attachments dir: ab/abcdef....pdf
+// Stored relative so the data folder can be moved or restored from backup.
+fn relative_path(hash: &str, extension: &str) -> String {
+    let file_name = if extension.is_empty() { hash.to_string() } else { format!("{}.{}", hash, extension) };
+    format!("{}/{}", &hash[..2], file_name)
+}
+
+fn file_extension(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+// Magic bytes first (renamed files are common with bank downloads), then the extension
+fn detect_mime_type(data: &[u8], extension: &str) -> String {
+    let sniffed = if data.starts_with(b"%PDF") {
+        Some("application/pdf")
+    } else if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    };
+
+    let mime = sniffed.unwrap_or(match extension {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    });
+    mime.to_string()
+}
+
+fn load_attachment(conn: &rusqlite::Connection, id: i64) -> Result<(Attachment, PathBuf), String> {
+    conn.query_row(
+        "SELECT a.id, a.file_hash, a.original_name, f.mime_type, f.size_bytes, a.transaction_id, a.invoice_id,
+                a.investment_lot_id, a.created_at, f.stored_path
+         FROM attachments a
+         JOIN attachment_files f ON a.file_hash = f.hash
+         WHERE a.id = ?1",
+        [id],
+        |row| Ok((map_attachment_row(row)?, attachments_dir().join(row.get::<_, String>(9)?))),
+    ).map_err(|_| "Attachment not found".to_string())
+}
+
+fn map_attachment_row(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        file_hash: row.get(1)?,
+        original_name: row.get(2)?,
+        mime_type: row.get(3)?,
+        size_bytes: row.get(4)?,
+        transaction_id: row.get(5)?,
+        invoice_id: row.get(6)?,
+        investment_lot_id: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// Copies a file into the app data directory and links it to exactly one
+/// transaction, invoice or investment lot. Identical content is stored once.
+#[tauri::command]
+pub fn add_attachment(
+    db: State<DbConnection>,
+    source_path: String,
+    transaction_id: Option<i64>,
+    invoice_id: Option<i64>,
+    investment_lot_id: Option<i64>,
+) -> Result<Attachment, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let links = [transaction_id, invoice_id, investment_lot_id].iter().filter(|l| l.is_some()).count();
+    if links != 1 {
+        return Err("Link the attachment to exactly one transaction, invoice or investment lot".to_string());
+    }
+    // Checked before anything is written so a bad link leaves no file behind
+    for (table, label, id) in [
+        ("transactions", "Transaction", transaction_id),
+        ("invoices", "Invoice", invoice_id),
+        ("investment_lots", "Investment lot", investment_lot_id),
+    ] {
+        let Some(id) = id else { continue };
+        let exists: bool = conn.query_row(&format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table), [id], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("{} {} not found", label, id));
+        }
+    }
+
+    let data = fs::read(&source_path).map_err(|e| format!("Could not read {}: {}", source_path, e))?;
+    let original_name = Path::new(&source_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "attachment".to_string());
+    let extension = file_extension(&original_name);
+    let hash = format!("{:x}", Sha256::digest(&data));
+
+    let existing: Option<String> = conn.query_row(
+        "SELECT stored_path FROM attachment_files WHERE hash = ?1",
+        [&hash],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+
+    // Write the file before the row so a failed copy never leaves a dangling reference
+    let relative = existing.clone().unwrap_or_else(|| relative_path(&hash, &extension));
+    let path = attachments_dir().join(&relative);
+    let written = !path.exists();
+    if written {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, &data).map_err(|e| e.to_string())?;
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "add_attachment")?;
+    let inserted = (|| -> Result<i64, String> {
+        if existing.is_none() {
+            tx.execute(
+                "INSERT INTO attachment_files (hash, stored_path, mime_type, size_bytes) VALUES (?1, ?2, ?3, ?4)",
+                params![hash, relative, detect_mime_type(&data, &extension), data.len() as i64],
+            ).map_err(|e| e.to_string())?;
+        }
+        tx.execute(
+            "INSERT INTO attachments (file_hash, original_name, transaction_id, invoice_id, investment_lot_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![hash, original_name, transaction_id, invoice_id, investment_lot_id],
+        ).map_err(|e| e.to_string())?;
+        Ok(tx.last_insert_rowid())
+    })();
+    drop(change);
+    let id = match inserted.and_then(|id| tx.commit().map(|_| id).map_err(|e| e.to_string())) {
+        Ok(id) => id,
+        Err(e) => {
+            // Nothing references a file this call created
+            if written {
+                let _ = fs::remove_file(&path);
+            }
+            return Err(e);
+        }
+    };
+
+    Ok(load_attachment(&conn, id)?.0)
+}
+
+#[tauri::command]
+pub fn get_attachments(
+    db: State<DbConnection>,
+    transaction_id: Option<i64>,
+    invoice_id: Option<i64>,
+    investment_lot_id: Option<i64>,
+) -> Result<Vec<Attachment>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT a.id, a.file_hash, a.original_name, f.mime_type, f.size_bytes, a.transaction_id, a.invoice_id,
+               a.investment_lot_id, a.created_at
+        FROM attachments a
+        JOIN attachment_files f ON a.file_hash = f.hash
+        WHERE (?1 IS NULL OR a.transaction_id = ?1)
+        AND (?2 IS NULL OR a.invoice_id = ?2)
+        AND (?3 IS NULL OR a.investment_lot_id = ?3)
+        ORDER BY a.created_at DESC, a.id DESC
+    ").map_err(|e| e.to_string())?;
+
+    let attachments = stmt
+        .query_map(params![transaction_id, invoice_id, investment_lot_id], map_attachment_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(attachments)
+}
+
+/// Opens the stored copy with the system's default viewer and returns its path.
+#[tauri::command]
+pub fn open_attachment(db: State<DbConnection>, id: i64) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let (_, path) = load_attachment(&conn, id)?;
+
+    if !path.exists() {
+        return Err(format!("Attachment file is missing: {}", path.display()));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::process::Command;
+        Command::new("cmd")
+            .args(["/C", "start", ""])
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command;
+        Command::new("open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::process::Command;
+        Command::new("xdg-open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Removes the link, and the file record once nothing else references it.
+/// The stored file stays on disk so the deletion can be undone.
+#[tauri::command]
+pub fn delete_attachment(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_attachment")?;
+    let (attachment, _) = load_attachment(&tx, id)?;
+
+    tx.execute("DELETE FROM attachments WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM attachment_files WHERE hash = ?1 AND NOT EXISTS (SELECT 1 FROM attachments WHERE file_hash = ?1)",
+        [&attachment.file_hash],
+    ).map_err(|e| e.to_string())?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes attachments whose transaction, invoice or lot has since been
+/// deleted (the links are set to NULL) and file records nothing references,
+/// then removes stored files that no row or journal entry refers to. Returns
+/// the number of attachments removed.
+#[tauri::command]
+pub fn cleanup_orphan_attachments(db: State<DbConnection>) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "cleanup_orphan_attachments")?;
+
+    let removed = tx.execute(
+        "DELETE FROM attachments WHERE transaction_id IS NULL AND invoice_id IS NULL AND investment_lot_id IS NULL",
+        [],
+    ).map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM attachment_files WHERE NOT EXISTS (SELECT 1 FROM attachments a WHERE a.file_hash = attachment_files.hash)",
+        [],
+    ).map_err(|e| e.to_string())?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // Layout is <first two hash chars>/<hash>[.ext]
+    let mut stored = Vec::new();
+    if let Ok(dirs) = fs::read_dir(attachments_dir()) {
+        for dir in dirs.flatten() {
+            if let Ok(files) = fs::read_dir(dir.path()) {
+                stored.extend(files.flatten().map(|f| f.path()).filter(|p| p.is_file()));
+            }
+        }
+    }
+    for path in stored {
+        let Some(hash) = path.file_stem().and_then(|h| h.to_str()) else { continue };
+        let referenced: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM attachment_files WHERE hash = ?1)
+                 OR EXISTS (SELECT 1 FROM change_journal_entries WHERE entity = 'attachment_files'
+                            AND json_extract(COALESCE(before_json, after_json), '$.hash') = ?1)",
+            [hash],
+            |r| r.get(0),
+        ).map_err(|e| e.to_string())?;
+        if !referenced {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(removed as i64)
+}
@@ -0,0 +1,210 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceMismatch {
+    pub account_id: i64,
+    pub account_name: String,
+    pub date: String,
+    pub stored_amount: f64,
+    pub expected_amount: f64,
+    pub stored_base_amount: f64,
+    pub expected_base_amount: f64,
+}
+
+// Recomputes the stored day rows matching `filter` (a condition on account_id
+// and date) from account_flows
+fn refresh_sql(filter: &str) -> String {
+    format!(
+        "DELETE FROM account_daily_balances WHERE {f};
+         INSERT INTO account_daily_balances (account_id, date, net_amount, net_base_amount)
+         SELECT account_id, date, SUM(amount), COALESCE(SUM(base_amount), 0)
+         FROM account_flows WHERE {f}
+         GROUP BY account_id, date;",
+        f = filter
+    )
+}
+
+// Accounts whose base amounts depend on a rate for either currency of the pair.
+// Base-currency amounts always convert at 1, so those accounts are left alone.
+const FX_AFFECTED: &str = "account_id IN (
+    SELECT id FROM accounts WHERE currency IN ({r}.from_currency, {r}.to_currency)
+        AND currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+    UNION SELECT from_account_id FROM transactions WHERE currency IN ({r}.from_currency, {r}.to_currency)
+        AND currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+    UNION SELECT to_account_id FROM transactions WHERE currency IN ({r}.from_currency, {r}.to_currency)
+        AND currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+)";
+
+/// Installs the per-connection triggers that keep `account_daily_balances` in
+/// step with every transaction, rate and base-currency change, and fills the
+/// table when it has never been built.
+pub(crate) fn install_balance_triggers(conn: &rusqlite::Connection) -> Result<()> {
+    let tx_filter = |row: &str| format!("(account_id IN ({r}.from_account_id, {r}.to_account_id) AND date = {r}.date)", r = row);
+    let fx_filter = |row: &str| FX_AFFECTED.replace("{r}", row);
+
+    conn.execute_batch(&format!(
+        "CREATE TEMP TRIGGER IF NOT EXISTS balances_transactions_insert AFTER INSERT ON main.transactions
+         BEGIN {insert} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_transactions_update
+         AFTER UPDATE OF date, amount, direction, from_account_id, to_account_id, currency, to_amount ON main.transactions
+         BEGIN {update} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_transactions_delete AFTER DELETE ON main.transactions
+         BEGIN {delete} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_fx_rates_insert AFTER INSERT ON main.fx_rates
+         BEGIN {fx_insert} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_fx_rates_update AFTER UPDATE ON main.fx_rates
+         BEGIN {fx_update} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_fx_rates_delete AFTER DELETE ON main.fx_rates
+         BEGIN {fx_delete} END;
+         CREATE TEMP TRIGGER IF NOT EXISTS balances_fx_settings_update AFTER UPDATE OF base_currency ON main.fx_settings
+         BEGIN {all} END;",
+        insert = refresh_sql(&tx_filter("NEW")),
+        update = refresh_sql(&format!("({} OR {})", tx_filter("OLD"), tx_filter("NEW"))),
+        delete = refresh_sql(&tx_filter("OLD")),
+        fx_insert = refresh_sql(&fx_filter("NEW")),
+        fx_update = refresh_sql(&format!("({} OR {})", fx_filter("OLD"), fx_filter("NEW"))),
+        fx_delete = refresh_sql(&fx_filter("OLD")),
+        all = refresh_sql("1 = 1"),
+    ))?;
+
+    let (stored, transactions): (i64, i64) = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM account_daily_balances), (SELECT COUNT(*) FROM transactions)",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    if stored == 0 && transactions > 0 {
+        conn.execute_batch(&refresh_sql("1 = 1"))?;
+    }
+
+    Ok(())
+}
+
+// Rate triggers refresh the affected accounts once per row, which a bulk
+// import repeats for every line. Imports drop them, write the rates, then
+// rebuild the foreign-currency accounts once and reinstall the triggers.
+pub(crate) fn suspend_fx_rate_triggers(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS temp.balances_fx_rates_insert;
+         DROP TRIGGER IF EXISTS temp.balances_fx_rates_update;
+         DROP TRIGGER IF EXISTS temp.balances_fx_rates_delete;",
+    )
+}
+
+pub(crate) fn resume_fx_rate_triggers(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute_batch(&refresh_sql(
+        "account_id IN (
+            SELECT id FROM accounts WHERE currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+            UNION SELECT from_account_id FROM transactions WHERE currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+            UNION SELECT to_account_id FROM transactions WHERE currency <> (SELECT base_currency FROM fx_settings WHERE id = 1)
+        )",
+    ))?;
+    install_balance_triggers(conn)
+}
+
+/// Balance in the account's own currency at the end of `date` (all time when
+/// None): opening balance plus the stored daily net flows.
+pub(crate) fn account_balance_as_of(conn: &rusqlite::Connection, account_id: i64, date: Option<&str>) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT a.opening_balance + COALESCE((
+            SELECT SUM(b.net_amount) FROM account_daily_balances b
+            WHERE b.account_id = a.id AND (?2 IS NULL OR b.date <= ?2)
+         ), 0)
+         FROM accounts a WHERE a.id = ?1",
+        params![account_id, date],
+        |r| r.get(0),
+    ).map_err(|_| format!("Account {} not found", account_id))
+}
+
+// An account and its buckets. Bucket money sits in the parent's real account,
+// so the bank only ever sees their combined balance. Expects the account id as ?1.
+pub(crate) const ACCOUNT_WITH_BUCKETS: &str = "(SELECT id FROM accounts WHERE id = ?1 OR parent_id = ?1)";
+
+// Rows the bank statement shows: money moving in or out of the account and
+// its buckets. Moves between the parent and its buckets stay inside the real
+// account and never appear. Expects the account id as ?1.
+pub(crate) fn statement_rows(alias: &str) -> String {
+    format!(
+        "({a}from_account_id IN {f} OR {a}to_account_id IN {f}) AND NOT (IFNULL({a}from_account_id IN {f}, 0) AND IFNULL({a}to_account_id IN {f}, 0))",
+        a = alias,
+        f = ACCOUNT_WITH_BUCKETS
+    )
+}
+
+/// Balance of an account together with its buckets at the end of `date` (all
+/// time when None), which is what the bank reports for it.
+pub(crate) fn rolled_up_balance_as_of(conn: &rusqlite::Connection, account_id: i64, date: Option<&str>) -> Result<f64, String> {
+    conn.query_row(
+        &format!(
+            "SELECT (SELECT SUM(opening_balance) FROM accounts WHERE id IN {f}) + COALESCE((
+                SELECT SUM(b.net_amount) FROM account_daily_balances b
+                WHERE b.account_id IN {f} AND (?2 IS NULL OR b.date <= ?2)
+             ), 0)
+             FROM accounts WHERE id = ?1",
+            f = ACCOUNT_WITH_BUCKETS
+        ),
+        params![account_id, date],
+        |r| r.get(0),
+    ).map_err(|_| format!("Account {} not found", account_id))
+}
+
+/// Recomputes the daily balance table from the transactions. Returns the
+/// number of account-days stored.
+#[tauri::command]
+pub fn rebuild_account_balances(db: State<DbConnection>) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch(&refresh_sql("1 = 1")).map_err(|e| e.to_string())?;
+    let rows: i64 = tx.query_row("SELECT COUNT(*) FROM account_daily_balances", [], |r| r.get(0))
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Compares the stored daily balances against a fresh computation and lists
+/// every account-day that differs. Empty when the table is in sync.
+#[tauri::command]
+pub fn verify_account_balances(db: State<DbConnection>) -> Result<Vec<BalanceMismatch>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        WITH expected AS (
+            SELECT account_id, date, SUM(amount) AS net_amount, COALESCE(SUM(base_amount), 0) AS net_base_amount
+            FROM account_flows GROUP BY account_id, date
+        ),
+        compared AS (
+            SELECT e.account_id, e.date, COALESCE(b.net_amount, 0) AS stored, e.net_amount AS expected,
+                   COALESCE(b.net_base_amount, 0) AS stored_base, e.net_base_amount AS expected_base
+            FROM expected e
+            LEFT JOIN account_daily_balances b ON b.account_id = e.account_id AND b.date = e.date
+            UNION ALL
+            SELECT b.account_id, b.date, b.net_amount, 0, b.net_base_amount, 0
+            FROM account_daily_balances b
+            WHERE NOT EXISTS (SELECT 1 FROM expected e WHERE e.account_id = b.account_id AND e.date = b.date)
+        )
+        SELECT c.account_id, COALESCE(a.name, ''), c.date, c.stored, c.expected, c.stored_base, c.expected_base
+        FROM compared c
+        LEFT JOIN accounts a ON c.account_id = a.id
+        WHERE ABS(c.stored - c.expected) > 0.005 OR ABS(c.stored_base - c.expected_base) > 0.005
+        ORDER BY c.account_id, c.date
+    ").map_err(|e| e.to_string())?;
+
+    let mismatches = stmt.query_map([], |row| {
+        Ok(BalanceMismatch {
+            account_id: row.get(0)?,
+            account_name: row.get(1)?,
+            date: row.get(2)?,
+            stored_amount: row.get(3)?,
+            expected_amount: row.get(4)?,
+            stored_base_amount: row.get(5)?,
+            expected_base_amount: row.get(6)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(mismatches)
+}
@@ -2,6 +2,10 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::credit_cards::day_in_month;
+use super::currency::resolve_transaction_currency;
+use super::journal::ChangeScope;
+use super::search::refresh_search_index;
 use chrono::{Datelike, Duration, Local, Months, NaiveDate};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,8 +33,90 @@ pub struct CategoryBreakdown {
     pub amount: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: Option<i64>,
+    pub month: String, // YYYY-MM
+    pub category_id: i64,
+    pub budgeted_amount: f64,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryBudgetSummary {
+    pub category_id: i64,
+    pub category_name: String,
+    pub budgeted: f64,
+    pub spent: f64,
+    pub remaining: f64,
+    pub percent_used: Option<f64>, // None when nothing is budgeted
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetSettings {
+    pub salary_date: u32,
+    pub period_mode: String, // calendar, salary (starts on salary_date), custom (starts on period_start_day)
+    pub period_start_day: Option<u32>,
+}
+
+/// The dates a budget month covers. A period is labelled by the month it
+/// starts in: with salary on the 25th, "2026-03" runs 2026-03-25 to 2026-04-24.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetPeriod {
+    pub month: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthlyIncome {
+    pub id: Option<i64>,
+    pub month: String, // YYYY-MM, the budget period's label
+    pub expected_income: f64,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncomeVariance {
+    pub month: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub expected: Option<f64>,
+    pub actual: f64,
+    pub variance: Option<f64>,         // actual - expected
+    pub variance_percent: Option<f64>,
+    pub closed: bool,                  // The period has ended
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetMove {
+    pub id: Option<i64>,
+    pub month: String, // YYYY-MM
+    pub from_category_id: i64,
+    pub to_category_id: i64,
+    pub amount: f64,
+    pub reason: String,
+    pub created_at: Option<String>,
+}
+
+/// One category envelope for a month: what came over from last month, this
+/// month's budget and moves, the spending against it and what is left.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetEnvelope {
+    pub category_id: i64,
+    pub category_name: String,
+    pub rollover_mode: String,
+    pub carried_in: f64,
+    pub budgeted: f64,
+    pub moved: f64, // Net of moves in and out this month
+    pub spent: f64,
+    pub available: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BudgetSummary {
+    pub period_start: String,
+    pub period_end: String,
     pub realized_income: f64,
     pub realized_expenses: f64,
     pub realized_investments: f64,
@@ -39,11 +125,83 @@ pub struct BudgetSummary {
     pub expected_recurring_income: f64,
     pub expected_recurring_investments: f64,
     pub expected_recurring_buckets: f64,
+    pub expected_income: Option<f64>, // Planned income recorded for this month
     pub safe_to_spend: f64,
     pub breakdown_income: Vec<CategoryBreakdown>,
     pub breakdown_expenses: Vec<CategoryBreakdown>,
     pub breakdown_investments: Vec<CategoryBreakdown>,
     pub breakdown_buckets: Vec<CategoryBreakdown>,
+    pub total_budgeted: f64,
+    pub category_budgets: Vec<CategoryBudgetSummary>, // Budgeted categories and any other with spending
+}
+
+// Expense lines that count against the budget: not investments, not moves into
+// buckets or investment accounts, and only categories included in the budget
+const BUDGET_EXPENSE_FILTER: &str = "t.direction = 'expense'
+         AND t.investment_id IS NULL
+         AND (t.to_account_id IS NULL OR t.to_account_id NOT IN (SELECT id FROM accounts WHERE type IN ('bucket', 'investment')))
+         AND c.include_in_budget = 1";
+
+fn check_month(month: &str) -> Result<(), String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| "Month must be YYYY-MM".to_string())
+}
+
+// Income counted by the budget: income lines in categories included in the budget
+fn realized_income_between(conn: &rusqlite::Connection, start_date: &str, end_date: &str) -> f64 {
+    conn.query_row(
+        "SELECT COALESCE(SUM(t.base_amount), 0) FROM transaction_lines t
+         JOIN categories c ON t.category_id = c.id
+         WHERE t.direction = 'income' AND c.include_in_budget = 1 
+         AND t.date >= ?1 AND t.date <= ?2",
+        params![start_date, end_date],
+        |row| row.get(0)
+    ).unwrap_or(0.0)
+}
+
+// Day of the month budget periods start on, from the period settings
+fn period_start_day(conn: &rusqlite::Connection) -> Result<u32, String> {
+    let (mode, salary_date, custom_day): (String, u32, Option<u32>) = conn.query_row(
+        "SELECT COALESCE(period_mode, 'calendar'), salary_date, period_start_day FROM budget_settings WHERE id = 1",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    ).unwrap_or(("calendar".to_string(), 1, None));
+
+    let day = match mode.as_str() {
+        "salary" => salary_date,
+        "custom" => custom_day.unwrap_or(1),
+        _ => 1,
+    };
+    Ok(day.clamp(1, 31))
+}
+
+fn period_bounds(month: &str, start_day: u32) -> Result<(NaiveDate, NaiveDate), String> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").map_err(|_| "Month must be YYYY-MM".to_string())?;
+    let next = first + Months::new(1);
+    let start = day_in_month(first.year(), first.month(), start_day);
+    let end = day_in_month(next.year(), next.month(), start_day) - Duration::days(1);
+    Ok((start, end))
+}
+
+// Label of the period a date falls in
+fn period_label(date: NaiveDate, start_day: u32) -> String {
+    if date >= day_in_month(date.year(), date.month(), start_day) {
+        date.format("%Y-%m").to_string()
+    } else {
+        (date - Months::new(1)).format("%Y-%m").to_string()
+    }
+}
+
+/// Start and end dates (inclusive) of a budget month under the current
+/// period settings.
+pub(crate) fn budget_period(conn: &rusqlite::Connection, month: &str) -> Result<BudgetPeriod, String> {
+    let (start, end) = period_bounds(month, period_start_day(conn)?)?;
+    Ok(BudgetPeriod {
+        month: month.to_string(),
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+    })
 }
 
 #[tauri::command]
@@ -88,6 +246,7 @@ pub fn get_scheduled_transactions(db: State<DbConnection>) -> Result<Vec<Schedul
 #[tauri::command]
 pub fn create_scheduled_transaction(db: State<DbConnection>, payload: ScheduledTransaction) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_scheduled_transaction")?;
     let is_act_int = if payload.is_active { 1 } else { 0 };
     
     conn.execute(
@@ -109,6 +268,7 @@ pub fn create_scheduled_transaction(db: State<DbConnection>, payload: ScheduledT
 #[tauri::command]
 pub fn update_scheduled_transaction(db: State<DbConnection>, payload: ScheduledTransaction) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_scheduled_transaction")?;
     let id = payload.id.ok_or("ID missing")?;
     let is_act_int = if payload.is_active { 1 } else { 0 };
     
@@ -132,11 +292,12 @@ pub fn update_scheduled_transaction(db: State<DbConnection>, payload: ScheduledT
 #[tauri::command]
 pub fn delete_scheduled_transaction(db: State<DbConnection>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_scheduled_transaction")?;
     conn.execute("DELETE FROM scheduled_transactions WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
     Ok(())
 }
 
-fn calculate_next_date(current_date_str: &str, frequency: &str, interval: i32) -> Result<String, String> {
+pub(crate) fn calculate_next_date(current_date_str: &str, frequency: &str, interval: i32) -> Result<String, String> {
     let mut d = NaiveDate::parse_from_str(current_date_str, "%Y-%m-%d")
         .map_err(|_| "Invalid date format".to_string())?;
 
@@ -203,6 +364,7 @@ pub fn process_pending_schedules(db: State<DbConnection>) -> Result<i32, String>
     }
     
     let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "process_pending_schedules")?;
     let mut processed_count = 0;
     
     for (id, _name, amount, tx_type, freq, interval, run_date, from_acc, to_acc, cat_id, inv_id, notes, created_at_date) in due_items {
@@ -221,12 +383,15 @@ pub fn process_pending_schedules(db: State<DbConnection>) -> Result<i32, String>
             let tx_notes = format!("Auto-logged [{}]: {}", tx_type, notes.unwrap_or_default());
             let cat_val = cat_id.unwrap_or(1); // Default fallback category
 
+            let resolved = resolve_transaction_currency(&tx, &run_date, amount, from_acc, to_acc, None, None)?;
+
             // Insert Transaction
             tx.execute(
-                "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, investment_id, notes)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                params![run_date, amount, mapped_dir, from_acc, to_acc, cat_val, inv_id, tx_notes]
+                "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, investment_id, notes, currency, to_amount)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![run_date, amount, mapped_dir, from_acc, to_acc, cat_val, inv_id, tx_notes, resolved.currency, resolved.to_amount]
             ).map_err(|e| e.to_string())?;
+            refresh_search_index(&tx, tx.last_insert_rowid())?;
         }
 
         // Calculate next date and aggressively fast-forward it if the schedule was deeply historical
@@ -244,6 +409,7 @@ pub fn process_pending_schedules(db: State<DbConnection>) -> Result<i32, String>
         processed_count += 1;
     }
     
+    drop(change);
     tx.commit().map_err(|e| e.to_string())?;
     
     Ok(processed_count)
@@ -253,36 +419,29 @@ pub fn process_pending_schedules(db: State<DbConnection>) -> Result<i32, String>
 pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result<BudgetSummary, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
-    // We expect year_month as format "YYYY-MM"
-    let start_date = format!("{}-01", year_month);
-    let end_date = format!("{}-31", year_month); // works enough for sqlite strftime <= comparison
+    // We expect year_month as format "YYYY-MM"; the period may run across two calendar months
+    let period = budget_period(&conn, &year_month)?;
+    let (start_date, end_date) = (period.start_date, period.end_date);
     
+    // Category filters read transaction_lines so split parents count per child line
     // 1. Realized Income (from transactions direction income, only included categories)
-    let realized_income: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(t.amount), 0) FROM transactions t
-         JOIN categories c ON t.category_id = c.id
-         WHERE t.direction = 'income' AND c.include_in_budget = 1 
-         AND t.date >= ?1 AND t.date <= ?2",
-        params![start_date, end_date],
-        |row| row.get(0)
-    ).unwrap_or(0.0);
+    let realized_income = realized_income_between(&conn, &start_date, &end_date);
     
     // 2. Realized Expenses (only included categories)
     let realized_expenses: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(t.amount), 0) FROM transactions t
-         JOIN categories c ON t.category_id = c.id
-         WHERE t.direction = 'expense'
-         AND t.investment_id IS NULL
-         AND (t.to_account_id IS NULL OR t.to_account_id NOT IN (SELECT id FROM accounts WHERE type IN ('bucket', 'investment')))
-         AND c.include_in_budget = 1 
-         AND t.date >= ?1 AND t.date <= ?2",
+        &format!(
+            "SELECT COALESCE(SUM(t.base_amount), 0) FROM transaction_lines t
+             JOIN categories c ON t.category_id = c.id
+             WHERE {} AND t.date >= ?1 AND t.date <= ?2",
+            BUDGET_EXPENSE_FILTER
+        ),
         params![start_date, end_date],
         |row| row.get(0)
     ).unwrap_or(0.0);
 
     // 2b. Realized Investments (Exclude PF)
     let realized_investments: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(t.amount), 0) FROM transactions t
+        "SELECT COALESCE(SUM(t.base_amount), 0) FROM transaction_lines t
          JOIN categories c ON t.category_id = c.id
          LEFT JOIN investments i ON t.investment_id = i.id
          WHERE t.direction IN ('expense', 'transfer')
@@ -297,7 +456,7 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
 
     // 2c. Realized Buckets
     let realized_buckets: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(t.amount), 0) FROM transactions t
+        "SELECT COALESCE(SUM(t.base_amount), 0) FROM transaction_lines t
          JOIN categories c ON t.category_id = c.id
          WHERE t.direction = 'transfer'
          AND t.to_account_id IN (SELECT id FROM accounts WHERE type = 'bucket')
@@ -317,8 +476,9 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
     let mut expected_recurring_buckets = 0.0;
     let mut expected_recurring_income = 0.0;
 
-    let dt_target = chrono::NaiveDate::parse_from_str(&format!("{}-01", year_month), "%Y-%m-%d").unwrap_or_else(|_| chrono::Local::now().date_naive());
-    let target_month_num = dt_target.month();
+    let period_first = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    let period_last = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    let period_days = ((period_last - period_first).num_days() + 1) as f64;
 
     let mut sched_stmt = conn.prepare("
         SELECT s.amount, s.type, s.frequency, s.frequency_interval, s.next_run_date, s.investment_id, s.to_account_id, i.type
@@ -347,23 +507,26 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
                 if t == "pf" { continue; }
             }
 
-            // Second check: If the target month is BEFORE the schedule even starts, don't include it
-            if let Ok(run_dt) = chrono::NaiveDate::parse_from_str(&next_run, "%Y-%m-%d") {
-                // If run_dt's month is after target month, and it's in the same or future year, skip.
-                if run_dt.year() > dt_target.year() || (run_dt.year() == dt_target.year() && run_dt.month() > target_month_num) {
-                    continue;
-                }
+            // Second check: If the period ends BEFORE the schedule even starts, don't include it
+            let run_dt = NaiveDate::parse_from_str(&next_run, "%Y-%m-%d").ok();
+            if run_dt.is_some_and(|d| d > period_last) {
+                continue;
             }
 
+            // Weights follow the period's length, which need not be a calendar month
             let mut monthly_weight = 0.0;
             match freq.as_str() {
-                "daily" => monthly_weight = amt * 30.0,
-                "weekly" => monthly_weight = amt * 4.33,
+                "daily" => monthly_weight = amt * period_days,
+                "weekly" => monthly_weight = amt * period_days / 7.0,
                 "monthly" => monthly_weight = amt,
                 "yearly" => {
-                    // Check if run month aligns with this target month
-                    if let Ok(run_dt) = chrono::NaiveDate::parse_from_str(&next_run, "%Y-%m-%d") {
-                        if run_dt.month() == target_month_num {
+                    // Check if the yearly run date (clamped to month end) falls inside this period
+                    if let Some(run_dt) = run_dt {
+                        let in_period = [period_first.year(), period_last.year()].iter().any(|y| {
+                            let date = day_in_month(*y, run_dt.month(), run_dt.day());
+                            date >= period_first && date <= period_last
+                        });
+                        if in_period {
                             monthly_weight = amt;
                         }
                     }
@@ -400,16 +563,29 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
         }
     }
     
-    let safe_to_spend = (realized_income + expected_recurring_income) - (realized_expenses + expected_recurring_expenses + realized_investments + expected_recurring_investments + realized_buckets + expected_recurring_buckets);
+    // A month that has not closed counts on its planned income when one is recorded;
+    // money already received beyond the plan still counts
+    let expected_income: Option<f64> = conn.query_row(
+        "SELECT expected_income FROM monthly_income WHERE month = ?1",
+        [&year_month],
+        |r| r.get(0),
+    ).ok();
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let income_basis = match expected_income {
+        Some(expected) if end_date >= today => expected.max(realized_income),
+        _ => realized_income + expected_recurring_income,
+    };
+
+    let safe_to_spend = income_basis - (realized_expenses + expected_recurring_expenses + realized_investments + expected_recurring_investments + realized_buckets + expected_recurring_buckets);
 
     // CATEGORY BREAKDOWNS (REALIZED)
     let mut breakdown_income = Vec::new();
     let mut stmt = conn.prepare("
-        SELECT c.name, COALESCE(SUM(t.amount), 0)
-        FROM transactions t
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0)
+        FROM transaction_lines t
         JOIN categories c ON t.category_id = c.id
         WHERE t.direction = 'income' AND c.include_in_budget = 1 AND t.date >= ?1 AND t.date <= ?2
-        GROUP BY c.name ORDER BY SUM(t.amount) DESC
+        GROUP BY c.name ORDER BY SUM(t.base_amount) DESC
     ").map_err(|e| e.to_string())?;
     let mut rows = stmt.query(params![start_date, end_date]).map_err(|e| e.to_string())?;
     while let Some(row) = rows.next().map_err(|e| e.to_string())? {
@@ -420,15 +596,13 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
     }
 
     let mut breakdown_expenses = Vec::new();
-    let mut stmt = conn.prepare("
-        SELECT c.name, COALESCE(SUM(t.amount), 0)
-        FROM transactions t
+    let mut stmt = conn.prepare(&format!("
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0)
+        FROM transaction_lines t
         JOIN categories c ON t.category_id = c.id
-        WHERE t.direction = 'expense' AND t.investment_id IS NULL
-        AND (t.to_account_id IS NULL OR t.to_account_id NOT IN (SELECT id FROM accounts WHERE type IN ('bucket', 'investment')))
-        AND c.include_in_budget = 1 AND t.date >= ?1 AND t.date <= ?2
-        GROUP BY c.name ORDER BY SUM(t.amount) DESC
-    ").map_err(|e| e.to_string())?;
+        WHERE {} AND t.date >= ?1 AND t.date <= ?2
+        GROUP BY c.name ORDER BY SUM(t.base_amount) DESC
+    ", BUDGET_EXPENSE_FILTER)).map_err(|e| e.to_string())?;
     let mut rows = stmt.query(params![start_date, end_date]).map_err(|e| e.to_string())?;
     while let Some(row) = rows.next().map_err(|e| e.to_string())? {
         breakdown_expenses.push(CategoryBreakdown { 
@@ -439,8 +613,8 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
 
     let mut breakdown_investments = Vec::new();
     let mut stmt = conn.prepare("
-        SELECT c.name, COALESCE(SUM(t.amount), 0)
-        FROM transactions t
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0)
+        FROM transaction_lines t
         JOIN categories c ON t.category_id = c.id
         LEFT JOIN investments i ON t.investment_id = i.id
         WHERE t.direction IN ('expense', 'transfer')
@@ -448,7 +622,7 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
         AND (i.type IS NULL OR i.type != 'pf')
         AND t.from_account_id IN (SELECT id FROM accounts WHERE type IN ('bank', 'cash'))
         AND c.include_in_budget = 1 AND t.date >= ?1 AND t.date <= ?2
-        GROUP BY c.name ORDER BY SUM(t.amount) DESC
+        GROUP BY c.name ORDER BY SUM(t.base_amount) DESC
     ").map_err(|e| e.to_string())?;
     let mut rows = stmt.query(params![start_date, end_date]).map_err(|e| e.to_string())?;
     while let Some(row) = rows.next().map_err(|e| e.to_string())? {
@@ -460,13 +634,13 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
 
     let mut breakdown_buckets = Vec::new();
     let mut stmt = conn.prepare("
-        SELECT c.name, COALESCE(SUM(t.amount), 0)
-        FROM transactions t
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0)
+        FROM transaction_lines t
         JOIN categories c ON t.category_id = c.id
         WHERE t.direction = 'transfer' AND t.to_account_id IN (SELECT id FROM accounts WHERE type = 'bucket')
         AND t.from_account_id IN (SELECT id FROM accounts WHERE type IN ('bank', 'cash'))
         AND c.include_in_budget = 1 AND t.date >= ?1 AND t.date <= ?2
-        GROUP BY c.name ORDER BY SUM(t.amount) DESC
+        GROUP BY c.name ORDER BY SUM(t.base_amount) DESC
     ").map_err(|e| e.to_string())?;
     let mut rows = stmt.query(params![start_date, end_date]).map_err(|e| e.to_string())?;
     while let Some(row) = rows.next().map_err(|e| e.to_string())? {
@@ -476,7 +650,46 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
         });
     }
 
+    // BUDGET VS ACTUAL per category, spending read with the expense filters above
+    let mut stmt = conn.prepare(&format!("
+        WITH spent AS (
+            SELECT t.category_id, SUM(t.base_amount) AS amount
+            FROM transaction_lines t
+            JOIN categories c ON t.category_id = c.id
+            WHERE {} AND t.date >= ?1 AND t.date <= ?2
+            GROUP BY t.category_id
+        ),
+        budgeted AS (
+            SELECT category_id, budgeted_amount AS amount FROM budgets WHERE month = ?3
+        )
+        SELECT c.id, c.name, COALESCE(b.amount, 0), COALESCE(s.amount, 0)
+        FROM categories c
+        LEFT JOIN budgeted b ON b.category_id = c.id
+        LEFT JOIN spent s ON s.category_id = c.id
+        WHERE b.category_id IS NOT NULL OR s.category_id IS NOT NULL
+        ORDER BY COALESCE(b.amount, 0) DESC, COALESCE(s.amount, 0) DESC, c.name
+    ", BUDGET_EXPENSE_FILTER)).map_err(|e| e.to_string())?;
+    let category_budgets = stmt.query_map(params![start_date, end_date, year_month], |row| {
+        let budgeted: f64 = row.get(2)?;
+        let spent: f64 = row.get(3)?;
+        let spent = (spent * 100.0).round() / 100.0;
+        Ok(CategoryBudgetSummary {
+            category_id: row.get(0)?,
+            category_name: row.get(1)?,
+            budgeted,
+            spent,
+            remaining: ((budgeted - spent) * 100.0).round() / 100.0,
+            percent_used: if budgeted > 0.0 { Some((spent / budgeted * 1000.0).round() / 10.0) } else { None },
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    let total_budgeted = category_budgets.iter().map(|b| b.budgeted).sum();
+
     Ok(BudgetSummary {
+        period_start: start_date,
+        period_end: end_date,
         realized_income,
         realized_expenses,
         realized_investments,
@@ -485,10 +698,464 @@ pub fn get_monthly_budget(db: State<DbConnection>, year_month: String) -> Result
         expected_recurring_income,
         expected_recurring_investments,
         expected_recurring_buckets,
+        expected_income,
         safe_to_spend,
         breakdown_income,
         breakdown_expenses,
         breakdown_investments,
         breakdown_buckets,
+        total_budgeted,
+        category_budgets,
     })
 }
+
+#[tauri::command]
+pub fn get_category_budgets(db: State<DbConnection>, month: String) -> Result<Vec<Budget>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("SELECT id, month, category_id, budgeted_amount, notes FROM budgets WHERE month = ?1 ORDER BY category_id")
+        .map_err(|e| e.to_string())?;
+    let budgets = stmt.query_map([month], |row| {
+        Ok(Budget {
+            id: Some(row.get(0)?),
+            month: row.get(1)?,
+            category_id: row.get(2)?,
+            budgeted_amount: row.get(3)?,
+            notes: row.get(4)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(budgets)
+}
+
+/// Sets a category's budget for one month, replacing any existing amount.
+#[tauri::command]
+pub fn set_category_budget(db: State<DbConnection>, budget: Budget) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_category_budget")?;
+    check_month(&budget.month)?;
+    if budget.budgeted_amount < 0.0 {
+        return Err("Budgeted amount cannot be negative".to_string());
+    }
+
+    tx.execute(
+        "INSERT INTO budgets (month, category_id, budgeted_amount, notes) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(month, category_id) DO UPDATE SET budgeted_amount = excluded.budgeted_amount, notes = excluded.notes",
+        params![budget.month, budget.category_id, budget.budgeted_amount, budget.notes],
+    ).map_err(|e| e.to_string())?;
+
+    let id = tx.query_row(
+        "SELECT id FROM budgets WHERE month = ?1 AND category_id = ?2",
+        params![budget.month, budget.category_id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// Copies one month's category budgets into another. Categories already
+/// budgeted in the target month are kept unless `overwrite` is set. Returns
+/// the number of budgets written.
+#[tauri::command]
+pub fn copy_budgets_forward(db: State<DbConnection>, from_month: String, to_month: String, overwrite: Option<bool>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "copy_budgets_forward")?;
+    check_month(&from_month)?;
+    check_month(&to_month)?;
+    if from_month == to_month {
+        return Err("Pick a different month to copy into".to_string());
+    }
+
+    let conflict = if overwrite.unwrap_or(false) {
+        "DO UPDATE SET budgeted_amount = excluded.budgeted_amount, notes = excluded.notes"
+    } else {
+        "DO NOTHING"
+    };
+    conn.execute(
+        &format!(
+            "INSERT INTO budgets (month, category_id, budgeted_amount, notes)
+             SELECT ?2, category_id, budgeted_amount, notes FROM budgets WHERE month = ?1
+             ON CONFLICT(month, category_id) {}",
+            conflict
+        ),
+        params![from_month, to_month],
+    ).map_err(|e| e.to_string())
+}
+
+/// Removes a month's budgets, or only one category's when given. Returns the
+/// number removed.
+#[tauri::command]
+pub fn clear_category_budgets(db: State<DbConnection>, month: String, category_id: Option<i64>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "clear_category_budgets")?;
+    conn.execute(
+        "DELETE FROM budgets WHERE month = ?1 AND (?2 IS NULL OR category_id = ?2)",
+        params![month, category_id],
+    ).map_err(|e| e.to_string())
+}
+
+/// Envelope balances for every budgeted category in `month`. Each envelope is
+/// a running balance from the category's first budgeted month: what is left
+/// at a month's end carries into the next according to the category's
+/// rollover mode (none, carry_positive, carry_both).
+#[tauri::command]
+pub fn get_budget_envelopes(db: State<DbConnection>, month: String) -> Result<Vec<BudgetEnvelope>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let start_day = period_start_day(&conn)?;
+    let (_, period_end) = period_bounds(&month, start_day)?;
+    let end_date = period_end.format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn.prepare("
+        SELECT c.id, c.name, COALESCE(c.rollover_mode, 'none'), MIN(e.month)
+        FROM categories c
+        JOIN (
+            SELECT category_id, month FROM budgets
+            UNION ALL SELECT from_category_id, month FROM budget_moves
+            UNION ALL SELECT to_category_id, month FROM budget_moves
+        ) e ON e.category_id = c.id
+        WHERE e.month <= ?1
+        GROUP BY c.id
+        ORDER BY c.name
+    ").map_err(|e| e.to_string())?;
+    let categories = stmt
+        .query_map([&month], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?, r.get::<_, String>(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Per category and month amounts, keyed (category_id, YYYY-MM)
+    let load = |sql: &str, params: &[&dyn rusqlite::ToSql]| -> Result<std::collections::HashMap<(i64, String), f64>, String> {
+        let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params, |r| Ok(((r.get::<_, i64>(0)?, r.get::<_, String>(1)?), r.get::<_, f64>(2)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(rows)
+    };
+    let budgeted = load("SELECT category_id, month, budgeted_amount FROM budgets WHERE month <= ?1", &[&month])?;
+    let moved = load(
+        "SELECT category_id, month, SUM(amount) FROM (
+             SELECT to_category_id AS category_id, month, amount FROM budget_moves WHERE month <= ?1
+             UNION ALL SELECT from_category_id, month, -amount FROM budget_moves WHERE month <= ?1
+         ) GROUP BY category_id, month",
+        &[&month],
+    )?;
+
+    // Spending is grouped by day, then folded into the budget period each day falls in
+    let mut spent: std::collections::HashMap<(i64, String), f64> = std::collections::HashMap::new();
+    for ((category_id, date), amount) in load(
+        &format!(
+            "SELECT t.category_id, substr(t.date, 1, 10), SUM(t.base_amount)
+             FROM transaction_lines t
+             JOIN categories c ON t.category_id = c.id
+             WHERE {} AND t.date <= ?1
+             GROUP BY t.category_id, substr(t.date, 1, 10)",
+            BUDGET_EXPENSE_FILTER
+        ),
+        &[&end_date],
+    )? {
+        let day = NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+        *spent.entry((category_id, period_label(day, start_day))).or_insert(0.0) += amount;
+    }
+
+    let round = |v: f64| (v * 100.0).round() / 100.0;
+    let target = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").map_err(|e| e.to_string())?;
+    let mut envelopes = Vec::new();
+    for (category_id, category_name, rollover_mode, first_month) in categories {
+        let mut current = NaiveDate::parse_from_str(&format!("{}-01", first_month), "%Y-%m-%d").map_err(|e| e.to_string())?;
+        let mut carried_in = 0.0;
+        loop {
+            let key = (category_id, current.format("%Y-%m").to_string());
+            let month_budget = budgeted.get(&key).copied().unwrap_or(0.0);
+            let month_moved = moved.get(&key).copied().unwrap_or(0.0);
+            let month_spent = spent.get(&key).copied().unwrap_or(0.0);
+            let available = carried_in + month_budget + month_moved - month_spent;
+
+            if current >= target {
+                envelopes.push(BudgetEnvelope {
+                    category_id,
+                    category_name,
+                    rollover_mode,
+                    carried_in: round(carried_in),
+                    budgeted: round(month_budget),
+                    moved: round(month_moved),
+                    spent: round(month_spent),
+                    available: round(available),
+                });
+                break;
+            }
+
+            carried_in = match rollover_mode.as_str() {
+                "carry_positive" => available.max(0.0),
+                "carry_both" => available,
+                _ => 0.0,
+            };
+            current = current + Months::new(1);
+        }
+    }
+
+    Ok(envelopes)
+}
+
+/// Moves budget from one category envelope to another within a month. The
+/// move is recorded with its reason and shows in both envelopes.
+#[tauri::command]
+pub fn move_budget_between_categories(db: State<DbConnection>, budget_move: BudgetMove) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "move_budget_between_categories")?;
+    check_month(&budget_move.month)?;
+    if budget_move.amount <= 0.0 {
+        return Err("Amount must be positive".to_string());
+    }
+    if budget_move.from_category_id == budget_move.to_category_id {
+        return Err("Pick two different categories".to_string());
+    }
+    if budget_move.reason.trim().is_empty() {
+        return Err("A reason is required".to_string());
+    }
+
+    conn.execute(
+        "INSERT INTO budget_moves (month, from_category_id, to_category_id, amount, reason) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![budget_move.month, budget_move.from_category_id, budget_move.to_category_id, budget_move.amount, budget_move.reason.trim()],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+#[tauri::command]
+pub fn get_budget_moves(db: State<DbConnection>, month: String) -> Result<Vec<BudgetMove>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT id, month, from_category_id, to_category_id, amount, reason, created_at
+        FROM budget_moves WHERE month = ?1 ORDER BY created_at, id
+    ").map_err(|e| e.to_string())?;
+    let moves = stmt.query_map([month], |row| {
+        Ok(BudgetMove {
+            id: Some(row.get(0)?),
+            month: row.get(1)?,
+            from_category_id: row.get(2)?,
+            to_category_id: row.get(3)?,
+            amount: row.get(4)?,
+            reason: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(moves)
+}
+
+#[tauri::command]
+pub fn delete_budget_move(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_budget_move")?;
+    conn.execute("DELETE FROM budget_moves WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_budget_settings(db: State<DbConnection>) -> Result<BudgetSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let settings = conn.query_row(
+        "SELECT salary_date, COALESCE(period_mode, 'calendar'), period_start_day FROM budget_settings WHERE id = 1",
+        [],
+        |r| Ok(BudgetSettings { salary_date: r.get(0)?, period_mode: r.get(1)?, period_start_day: r.get(2)? }),
+    ).unwrap_or(BudgetSettings { salary_date: 1, period_mode: "calendar".to_string(), period_start_day: None });
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn update_budget_settings(db: State<DbConnection>, settings: BudgetSettings) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_budget_settings")?;
+
+    if !["calendar", "salary", "custom"].contains(&settings.period_mode.as_str()) {
+        return Err(format!("Unknown period mode: {}", settings.period_mode));
+    }
+    if !(1..=31).contains(&settings.salary_date) || settings.period_start_day.is_some_and(|d| !(1..=31).contains(&d)) {
+        return Err("Days must be between 1 and 31".to_string());
+    }
+    if settings.period_mode == "custom" && settings.period_start_day.is_none() {
+        return Err("Pick the day custom periods start on".to_string());
+    }
+
+    conn.execute(
+        "INSERT INTO budget_settings (id, salary_date, period_mode, period_start_day) VALUES (1, ?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET salary_date = excluded.salary_date, period_mode = excluded.period_mode, period_start_day = excluded.period_start_day",
+        params![settings.salary_date, settings.period_mode, settings.period_start_day],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The date range a budget month covers under the current settings.
+#[tauri::command]
+pub fn get_budget_period(db: State<DbConnection>, month: String) -> Result<BudgetPeriod, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    budget_period(&conn, &month)
+}
+
+/// Expected income records, for one year ("YYYY") or all of them.
+#[tauri::command]
+pub fn get_monthly_incomes(db: State<DbConnection>, year: Option<String>) -> Result<Vec<MonthlyIncome>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT id, month, expected_income, notes FROM monthly_income
+        WHERE (?1 IS NULL OR substr(month, 1, 4) = ?1)
+        ORDER BY month
+    ").map_err(|e| e.to_string())?;
+    let incomes = stmt.query_map([year], |row| {
+        Ok(MonthlyIncome {
+            id: Some(row.get(0)?),
+            month: row.get(1)?,
+            expected_income: row.get(2)?,
+            notes: row.get(3)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(incomes)
+}
+
+/// Records the income expected in a month, replacing any earlier figure.
+#[tauri::command]
+pub fn set_monthly_income(db: State<DbConnection>, income: MonthlyIncome) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "set_monthly_income")?;
+    check_month(&income.month)?;
+    if income.expected_income < 0.0 {
+        return Err("Expected income cannot be negative".to_string());
+    }
+
+    conn.execute(
+        "INSERT INTO monthly_income (month, expected_income, notes) VALUES (?1, ?2, ?3)
+         ON CONFLICT(month) DO UPDATE SET expected_income = excluded.expected_income, notes = excluded.notes",
+        params![income.month, income.expected_income, income.notes],
+    ).map_err(|e| e.to_string())?;
+
+    conn.query_row("SELECT id FROM monthly_income WHERE month = ?1", [&income.month], |r| r.get(0))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_monthly_income(db: State<DbConnection>, month: String) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_monthly_income")?;
+    conn.execute("DELETE FROM monthly_income WHERE month = ?1", [month]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Expected against realized income for each month of `year` ("YYYY"), using
+/// the configured budget periods.
+#[tauri::command]
+pub fn get_income_variance(db: State<DbConnection>, year: String) -> Result<Vec<IncomeVariance>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let start_day = period_start_day(&conn)?;
+
+    let mut report = Vec::new();
+    for m in 1..=12 {
+        let month = format!("{}-{:02}", year, m);
+        let (start, end) = period_bounds(&month, start_day)?;
+        let period_start = start.format("%Y-%m-%d").to_string();
+        let period_end = end.format("%Y-%m-%d").to_string();
+
+        let expected: Option<f64> = conn.query_row(
+            "SELECT expected_income FROM monthly_income WHERE month = ?1",
+            [&month],
+            |r| r.get(0),
+        ).ok();
+        let actual = (realized_income_between(&conn, &period_start, &period_end) * 100.0).round() / 100.0;
+        let variance = expected.map(|e| ((actual - e) * 100.0).round() / 100.0);
+        let variance_percent = expected
+            .filter(|e| *e > 0.0)
+            .map(|e| ((actual - e) / e * 1000.0).round() / 10.0);
+
+        report.push(IncomeVariance {
+            month,
+            closed: period_end < today,
+            period_start,
+            period_end,
+            expected,
+            actual,
+            variance,
+            variance_percent,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn calendar_periods_follow_months() {
+        assert_eq!(period_bounds("2024-02", 1).unwrap(), (date(2024, 2, 1), date(2024, 2, 29)));
+        assert_eq!(period_bounds("2024-12", 1).unwrap(), (date(2024, 12, 1), date(2024, 12, 31)));
+        assert_eq!(period_label(date(2024, 2, 29), 1), "2024-02");
+        assert_eq!(period_label(date(2024, 3, 1), 1), "2024-03");
+        assert!(period_bounds("2024-13", 1).is_err());
+    }
+
+    #[test]
+    fn salary_day_31_clamps_through_february() {
+        // Leap year: February's period starts on the 29th
+        assert_eq!(period_bounds("2024-01", 31).unwrap(), (date(2024, 1, 31), date(2024, 2, 28)));
+        assert_eq!(period_bounds("2024-02", 31).unwrap(), (date(2024, 2, 29), date(2024, 3, 30)));
+        assert_eq!(period_label(date(2024, 2, 28), 31), "2024-01");
+        assert_eq!(period_label(date(2024, 2, 29), 31), "2024-02");
+        // Common year: on the 28th
+        assert_eq!(period_bounds("2023-01", 31).unwrap(), (date(2023, 1, 31), date(2023, 2, 27)));
+        assert_eq!(period_bounds("2023-02", 31).unwrap(), (date(2023, 2, 28), date(2023, 3, 30)));
+        assert_eq!(period_label(date(2023, 2, 27), 31), "2023-01");
+        assert_eq!(period_label(date(2023, 2, 28), 31), "2023-02");
+        assert_eq!(period_label(date(2023, 3, 31), 31), "2023-03");
+    }
+
+    #[test]
+    fn custom_day_crosses_the_year() {
+        assert_eq!(period_bounds("2024-12", 15).unwrap(), (date(2024, 12, 15), date(2025, 1, 14)));
+        assert_eq!(period_label(date(2025, 1, 14), 15), "2024-12");
+        assert_eq!(period_label(date(2025, 1, 15), 15), "2025-01");
+        assert_eq!(period_label(date(2024, 12, 14), 15), "2024-11");
+    }
+
+    #[test]
+    fn labels_agree_with_bounds() {
+        for start_day in [1, 15, 28, 29, 30, 31] {
+            let mut previous_end: Option<NaiveDate> = None;
+            for month in 0..24 {
+                let label = format!("{}-{:02}", 2023 + month / 12, month % 12 + 1);
+                let (start, end) = period_bounds(&label, start_day).unwrap();
+                // Periods tile the calendar without gaps or overlaps
+                if let Some(prev) = previous_end {
+                    assert_eq!(start, prev + Duration::days(1), "{} day {}", label, start_day);
+                }
+                let mut day = start;
+                while day <= end {
+                    assert_eq!(period_label(day, start_day), label, "{} day {}", day, start_day);
+                    day += Duration::days(1);
+                }
+                previous_end = Some(end);
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tauri::State;
+use crate::db::DbConnection;
+use super::allocations::reallocate_transaction;
+use super::journal::ChangeScope;
+use super::reconcile::ensure_not_reconciled;
+use super::search::refresh_search_index;
+use super::transactions::{apply_transaction_filters, remove_transaction, sync_goal_progress, TransactionFilters};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkTransactionChanges {
+    pub category_id: Option<i64>,
+    pub client_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub goal_id: Option<i64>,
+    pub clear_client: Option<bool>,
+    pub clear_project: Option<bool>,
+    pub clear_goal: Option<bool>,
+    pub add_tag_ids: Option<Vec<i64>>,
+    pub remove_tag_ids: Option<Vec<i64>>,
+    pub shift_days: Option<i64>, // Negative moves dates back
+    pub delete: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkEditResult {
+    pub affected: usize,
+    pub transaction_ids: Vec<i64>,
+    pub resynced_goal_ids: Vec<i64>,
+}
+
+// Resolves the target set: explicit ids win, otherwise every row matching the
+// filters (pagination fields are ignored).
+fn resolve_targets(conn: &rusqlite::Connection, ids: Option<Vec<i64>>, filters: Option<&TransactionFilters>) -> Result<Vec<i64>, String> {
+    let mut sql = String::from("SELECT t.id FROM transactions t WHERE 1=1");
+    let mut params_vec: Vec<rusqlite::types::Value> = vec![];
+
+    match (ids, filters) {
+        (Some(ids), _) => {
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            sql.push_str(&format!(" AND t.id IN ({})", vec!["?"; ids.len()].join(", ")));
+            params_vec.extend(ids.into_iter().map(rusqlite::types::Value::from));
+        }
+        (None, Some(f)) => apply_transaction_filters(&mut sql, f, &mut params_vec),
+        (None, None) => return Err("Provide transaction ids or a filter".to_string()),
+    }
+    sql.push_str(" ORDER BY t.id");
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map(rusqlite::params_from_iter(params_vec), |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Applies one set of changes to many transactions in a single SQLite
+/// transaction; any failure leaves every row untouched. Split parents keep
+/// their per-line categories, clients and projects.
+#[tauri::command]
+pub fn bulk_edit_transactions(
+    db: State<DbConnection>,
+    ids: Option<Vec<i64>>,
+    filters: Option<TransactionFilters>,
+    changes: BulkTransactionChanges,
+) -> Result<BulkEditResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "bulk_edit_transactions")?;
+
+    let targets = resolve_targets(&tx, ids, filters.as_ref())?;
+    let mut goal_ids = BTreeSet::new();
+
+    for &id in &targets {
+        ensure_not_reconciled(&tx, id)?;
+        let old_goal_id: Option<Option<i64>> = tx.query_row(
+            "SELECT goal_id FROM transactions WHERE id = ?1",
+            [id],
+            |row| row.get(0)
+        ).optional().map_err(|e| e.to_string())?;
+        // Allocation transfers are already gone when their income was handled first
+        let Some(old_goal_id) = old_goal_id else {
+            continue;
+        };
+        goal_ids.extend(old_goal_id);
+
+        if changes.delete.unwrap_or(false) {
+            remove_transaction(&tx, id)?;
+            continue;
+        }
+
+        if let Some(cat) = changes.category_id {
+            tx.execute("UPDATE transactions SET category_id = ?1 WHERE id = ?2", params![cat, id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        if changes.clear_client.unwrap_or(false) {
+            tx.execute("UPDATE transactions SET client_id = NULL WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+        } else if let Some(client) = changes.client_id {
+            tx.execute("UPDATE transactions SET client_id = ?1 WHERE id = ?2", params![client, id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        if changes.clear_project.unwrap_or(false) {
+            tx.execute("UPDATE transactions SET project_id = NULL WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+        } else if let Some(project) = changes.project_id {
+            tx.execute("UPDATE transactions SET project_id = ?1 WHERE id = ?2", params![project, id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        if changes.clear_goal.unwrap_or(false) {
+            tx.execute("UPDATE transactions SET goal_id = NULL WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+        } else if let Some(goal) = changes.goal_id {
+            tx.execute("UPDATE transactions SET goal_id = ?1 WHERE id = ?2", params![goal, id])
+                .map_err(|e| e.to_string())?;
+            goal_ids.insert(goal);
+        }
+
+        if let Some(days) = changes.shift_days.filter(|d| *d != 0) {
+            tx.execute(
+                "UPDATE transactions SET date = date(date, ?1) WHERE id = ?2",
+                params![format!("{:+} days", days), id],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        // Category and date feed auto-allocation, so an income's batch is redone
+        if changes.category_id.is_some() || changes.shift_days.is_some_and(|d| d != 0) {
+            reallocate_transaction(&tx, id)?;
+        }
+
+        for tag_id in changes.add_tag_ids.iter().flatten() {
+            tx.execute(
+                "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+                params![id, tag_id],
+            ).map_err(|e| e.to_string())?;
+        }
+        for tag_id in changes.remove_tag_ids.iter().flatten() {
+            tx.execute(
+                "DELETE FROM transaction_tags WHERE transaction_id = ?1 AND tag_id = ?2",
+                params![id, tag_id],
+            ).map_err(|e| e.to_string())?;
+        }
+
+        refresh_search_index(&tx, id)?;
+    }
+
+    for gid in &goal_ids {
+        sync_goal_progress(&tx, *gid)?;
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(BulkEditResult {
+        affected: targets.len(),
+        transaction_ids: targets,
+        resynced_goal_ids: goal_ids.into_iter().collect(),
+    })
+}
@@ -2,6 +2,7 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Category {
@@ -12,6 +13,16 @@ pub struct Category {
     pub is_investment: Option<bool>,
     pub include_in_budget: Option<bool>,
     pub include_in_income_breakdown: Option<bool>,
+    pub rollover_mode: Option<String>, // none, carry_positive, carry_both
+}
+
+const ROLLOVER_MODES: &[&str] = &["none", "carry_positive", "carry_both"];
+
+fn check_rollover_mode(mode: Option<&str>) -> Result<(), String> {
+    match mode {
+        Some(m) if !ROLLOVER_MODES.contains(&m) => Err(format!("Unknown rollover mode: {}", m)),
+        _ => Ok(()),
+    }
 }
 
 #[tauri::command]
@@ -19,7 +30,7 @@ pub fn get_categories(db: State<DbConnection>) -> Result<Vec<Category>, String>
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
     let mut stmt = conn
-        .prepare("SELECT id, name, kind, notes, COALESCE(is_investment, 0), COALESCE(include_in_budget, 1), COALESCE(include_in_income_breakdown, 0) FROM categories ORDER BY kind, name")
+        .prepare("SELECT id, name, kind, notes, COALESCE(is_investment, 0), COALESCE(include_in_budget, 1), COALESCE(include_in_income_breakdown, 0), rollover_mode FROM categories ORDER BY kind, name")
         .map_err(|e| e.to_string())?;
     
     let categories = stmt
@@ -32,6 +43,7 @@ pub fn get_categories(db: State<DbConnection>) -> Result<Vec<Category>, String>
                 is_investment: Some(row.get::<_, i32>(4)? == 1),
                 include_in_budget: Some(row.get::<_, i32>(5)? == 1),
                 include_in_income_breakdown: Some(row.get::<_, i32>(6)? == 1),
+                rollover_mode: row.get(7)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -47,16 +59,19 @@ pub fn create_category(
     category: Category,
 ) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_category")?;
+    check_rollover_mode(category.rollover_mode.as_deref())?;
     
     conn.execute(
-        "INSERT INTO categories (name, kind, notes, is_investment, include_in_budget, include_in_income_breakdown) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO categories (name, kind, notes, is_investment, include_in_budget, include_in_income_breakdown, rollover_mode) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         params![
             category.name, 
             category.kind, 
             category.notes, 
             category.is_investment.unwrap_or(false) as i32, 
             category.include_in_budget.unwrap_or(true) as i32,
-            category.include_in_income_breakdown.unwrap_or(false) as i32
+            category.include_in_income_breakdown.unwrap_or(false) as i32,
+            category.rollover_mode.as_deref().unwrap_or("none")
         ],
     )
     .map_err(|e| e.to_string())?;
@@ -70,11 +85,15 @@ pub fn update_category(
     category: Category,
 ) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_category")?;
     
     let id = category.id.ok_or("Category ID is required")?;
+    check_rollover_mode(category.rollover_mode.as_deref())?;
     
+    // A missing rollover mode keeps the current one
     conn.execute(
-        "UPDATE categories SET name = ?1, kind = ?2, notes = ?3, is_investment = ?4, include_in_budget = ?5, include_in_income_breakdown = ?6 WHERE id = ?7",
+        "UPDATE categories SET name = ?1, kind = ?2, notes = ?3, is_investment = ?4, include_in_budget = ?5, include_in_income_breakdown = ?6,
+         rollover_mode = COALESCE(?7, rollover_mode) WHERE id = ?8",
         params![
             category.name, 
             category.kind, 
@@ -82,6 +101,7 @@ pub fn update_category(
             category.is_investment.unwrap_or(false) as i32, 
             category.include_in_budget.unwrap_or(true) as i32,
             category.include_in_income_breakdown.unwrap_or(false) as i32,
+            category.rollover_mode,
             id
         ],
     )
@@ -2,6 +2,7 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Client {
@@ -51,6 +52,7 @@ pub fn create_client(
     client: Client,
 ) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_client")?;
     
     conn.execute(
         "INSERT INTO clients (name, notes, status, business_name, address, contact_number, email, gst) 
@@ -77,6 +79,7 @@ pub fn update_client(
     client: Client,
 ) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_client")?;
     
     let id = client.id.ok_or("Client ID is required")?;
     
@@ -0,0 +1,266 @@
+use chrono::{Datelike, Duration, Local, Months, NaiveDate};
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::account_balance_as_of;
+use super::journal::ChangeScope;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CardStatement {
+    pub id: i64,
+    pub account_id: i64,
+    pub account_name: String,
+    pub period_start: String,
+    pub period_end: String, // Statement date
+    pub due_date: String,
+    pub billed_amount: f64, // Outstanding on the statement date
+    pub minimum_due: f64,
+    pub paid_amount: f64,   // Payments and credits after the statement date, up to the next one
+    pub remaining: f64,
+    pub status: String,     // paid, minimum_paid, due, overdue
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditCardSummary {
+    pub account_id: i64,
+    pub name: String,
+    pub currency: String,
+    pub credit_limit: Option<f64>,
+    pub outstanding: f64,
+    pub available_credit: Option<f64>,
+    pub utilization_percent: Option<f64>,
+}
+
+// Day `day` of the given month, clamped to the month's last day (31 -> 28/29/30)
+pub(crate) fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+    let last = (first + Months::new(1)) - Duration::days(1);
+    first + Duration::days((day.min(last.day()) - 1) as i64)
+}
+
+fn statement_on_or_before(date: NaiveDate, statement_day: u32) -> NaiveDate {
+    let candidate = day_in_month(date.year(), date.month(), statement_day);
+    if candidate <= date {
+        candidate
+    } else {
+        let prev = date - Months::new(1);
+        day_in_month(prev.year(), prev.month(), statement_day)
+    }
+}
+
+fn next_statement(statement: NaiveDate, statement_day: u32) -> NaiveDate {
+    let next = day_in_month(statement.year(), statement.month(), 1) + Months::new(1);
+    day_in_month(next.year(), next.month(), statement_day)
+}
+
+// Due in the same month when the due day comes after the statement day, else the next month
+fn due_date_for(statement: NaiveDate, statement_day: u32, due_day: u32) -> NaiveDate {
+    let month = if due_day > statement_day {
+        statement
+    } else {
+        day_in_month(statement.year(), statement.month(), 1) + Months::new(1)
+    };
+    day_in_month(month.year(), month.month(), due_day)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&value[..value.len().min(10)], "%Y-%m-%d").map_err(|e| e.to_string())
+}
+
+// Creates or refreshes one statement per closed cycle, from the card's first
+// activity up to the last statement date before today.
+fn generate_for_card(conn: &rusqlite::Connection, account_id: i64, today: NaiveDate) -> Result<usize, String> {
+    let (statement_day, due_day, min_due_percent, created_at, first_tx): (Option<u32>, Option<u32>, Option<f64>, String, Option<String>) = conn.query_row(
+        "SELECT statement_day, due_day, min_due_percent, created_at,
+                (SELECT MIN(date) FROM transactions WHERE from_account_id = a.id OR to_account_id = a.id)
+         FROM accounts a WHERE id = ?1 AND type = 'credit_card'",
+        [account_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+    ).map_err(|_| format!("Account {} is not a credit card", account_id))?;
+
+    let (statement_day, due_day) = match (statement_day, due_day) {
+        (Some(s), Some(d)) => (s, d),
+        _ => return Err("Set the statement day and due day on the card first".to_string()),
+    };
+    let min_due_percent = min_due_percent.unwrap_or(5.0);
+
+    let mut start = parse_date(&created_at)?;
+    if let Some(first) = first_tx {
+        start = start.min(parse_date(&first)?);
+    }
+    let last_closed = statement_on_or_before(today - Duration::days(1), statement_day);
+
+    let mut period_end = statement_on_or_before(start, statement_day);
+    if period_end < start {
+        period_end = next_statement(period_end, statement_day);
+    }
+
+    let mut generated = 0;
+    while period_end <= last_closed {
+        let period_start = statement_on_or_before(period_end - Duration::days(1), statement_day) + Duration::days(1);
+        let end = period_end.format("%Y-%m-%d").to_string();
+        let billed = (-account_balance_as_of(conn, account_id, Some(&end))?).max(0.0);
+        let billed = (billed * 100.0).round() / 100.0;
+        let minimum_due = (billed * min_due_percent / 100.0 * 100.0).round() / 100.0;
+
+        conn.execute(
+            "INSERT INTO card_statements (account_id, period_start, period_end, due_date, billed_amount, minimum_due)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(account_id, period_end) DO UPDATE SET
+                period_start = excluded.period_start, due_date = excluded.due_date,
+                billed_amount = excluded.billed_amount, minimum_due = excluded.minimum_due",
+            params![
+                account_id,
+                period_start.format("%Y-%m-%d").to_string(),
+                end,
+                due_date_for(period_end, statement_day, due_day).format("%Y-%m-%d").to_string(),
+                billed,
+                minimum_due,
+            ],
+        ).map_err(|e| e.to_string())?;
+
+        generated += 1;
+        period_end = next_statement(period_end, statement_day);
+    }
+
+    Ok(generated)
+}
+
+pub(crate) fn load_card_statements(conn: &rusqlite::Connection, account_id: Option<i64>, open_only: bool) -> Result<Vec<CardStatement>, String> {
+    let today = Local::now().format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn.prepare("
+        SELECT s.id, s.account_id, a.name, s.period_start, s.period_end, s.due_date, s.billed_amount, s.minimum_due,
+               COALESCE((
+                   SELECT SUM(f.amount) FROM account_flows f
+                   WHERE f.account_id = s.account_id AND f.amount > 0 AND f.date > s.period_end
+                   AND f.date <= COALESCE((SELECT MIN(n.period_end) FROM card_statements n WHERE n.account_id = s.account_id AND n.period_end > s.period_end), '9999-12-31')
+               ), 0) as paid
+        FROM card_statements s
+        JOIN accounts a ON s.account_id = a.id
+        WHERE (?1 IS NULL OR s.account_id = ?1)
+        ORDER BY s.period_end DESC, a.name
+    ").map_err(|e| e.to_string())?;
+
+    let statements = stmt.query_map([account_id], |row| {
+        let billed: f64 = row.get(6)?;
+        let minimum_due: f64 = row.get(7)?;
+        let due_date: String = row.get(5)?;
+        let paid: f64 = row.get(8)?;
+        let paid = (paid * 100.0).round() / 100.0;
+
+        let status = if paid >= billed - 0.005 {
+            "paid"
+        } else if paid < minimum_due - 0.005 && due_date < today {
+            "overdue"
+        } else if paid >= minimum_due - 0.005 && minimum_due > 0.0 {
+            "minimum_paid"
+        } else {
+            "due"
+        };
+
+        Ok(CardStatement {
+            id: row.get(0)?,
+            account_id: row.get(1)?,
+            account_name: row.get(2)?,
+            period_start: row.get(3)?,
+            period_end: row.get(4)?,
+            due_date,
+            billed_amount: billed,
+            minimum_due,
+            paid_amount: paid,
+            remaining: ((billed - paid).max(0.0) * 100.0).round() / 100.0,
+            status: status.to_string(),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    if !open_only {
+        return Ok(statements);
+    }
+
+    // Only the latest statement per card is still payable; older unpaid amounts roll into it
+    let mut seen = std::collections::HashSet::new();
+    Ok(statements
+        .into_iter()
+        .filter(|s| seen.insert(s.account_id) && s.status != "paid")
+        .collect())
+}
+
+pub(crate) fn credit_card_summaries(conn: &rusqlite::Connection) -> Result<Vec<CreditCardSummary>, String> {
+    let today = Local::now().format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn.prepare("SELECT id, name, currency, credit_limit FROM accounts WHERE type = 'credit_card' AND closed_on IS NULL ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let cards = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?, r.get::<_, Option<f64>>(3)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut summaries = Vec::new();
+    for (account_id, name, currency, credit_limit) in cards {
+        let outstanding = (-account_balance_as_of(conn, account_id, Some(&today))?).max(0.0);
+        let outstanding = (outstanding * 100.0).round() / 100.0;
+        let limit = credit_limit.filter(|l| *l > 0.0);
+        summaries.push(CreditCardSummary {
+            account_id,
+            name,
+            currency,
+            credit_limit,
+            outstanding,
+            available_credit: limit.map(|l| ((l - outstanding) * 100.0).round() / 100.0),
+            utilization_percent: limit.map(|l| (outstanding / l * 1000.0).round() / 10.0),
+        });
+    }
+
+    Ok(summaries)
+}
+
+/// Builds statements (billed amount, minimum due, due date) for every closed
+/// billing cycle. Safe to re-run: existing cycles are refreshed after edits.
+#[tauri::command]
+pub fn generate_card_statements(db: State<DbConnection>, account_id: Option<i64>) -> Result<usize, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "generate_card_statements")?;
+    let today = Local::now().date_naive();
+
+    let ids: Vec<i64> = match account_id {
+        Some(id) => vec![id],
+        None => {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM accounts WHERE type = 'credit_card' AND statement_day IS NOT NULL AND due_day IS NOT NULL AND closed_on IS NULL"
+            ).map_err(|e| e.to_string())?;
+            let ids = stmt
+                .query_map([], |r| r.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<i64>, _>>()
+                .map_err(|e| e.to_string())?;
+            ids
+        }
+    };
+
+    let mut generated = 0;
+    for id in ids {
+        generated += generate_for_card(&tx, id, today)?;
+    }
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(generated)
+}
+
+#[tauri::command]
+pub fn get_card_statements(db: State<DbConnection>, account_id: Option<i64>) -> Result<Vec<CardStatement>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_card_statements(&conn, account_id, false)
+}
+
+#[tauri::command]
+pub fn get_credit_card_summaries(db: State<DbConnection>) -> Result<Vec<CreditCardSummary>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    credit_card_summaries(&conn)
+}
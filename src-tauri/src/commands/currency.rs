@@ -0,0 +1,508 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::{resume_fx_rate_triggers, suspend_fx_rate_triggers};
+use super::import::{parse_csv_records, parse_import_amount, parse_import_date};
+use super::journal::ChangeScope;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxRate {
+    pub id: Option<i64>,
+    pub date: String,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64, // 1 from_currency = rate to_currency
+    pub source: Option<String>, // manual, csv
+}
+
+#[derive(Debug, Serialize)]
+pub struct FxRateImportResult {
+    pub imported: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FxGainLoss {
+    pub transaction_id: i64,
+    pub date: String,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub amount: f64,
+    pub to_amount: f64,
+    pub cost_basis: f64, // Average cost of the units sold, in base currency
+    pub proceeds: f64,   // Value received, in base currency
+    pub gain: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FxGainReport {
+    pub base_currency: String,
+    pub items: Vec<FxGainLoss>,
+    pub total_gain: f64,
+}
+
+/// SQL expression for the rate converting `currency` into the base currency on
+/// `date`: the latest rate on or before the date, else the earliest one after it.
+/// NULL when the currency has no rate at all.
+pub(crate) fn rate_to_base_sql(currency: &str, date: &str) -> String {
+    format!(
+        "(CASE WHEN {c} = (SELECT base_currency FROM fx_settings WHERE id = 1) THEN 1.0
+          ELSE COALESCE(
+            (SELECT r.rate FROM fx_rates_to_base r WHERE r.currency = {c} AND r.date <= {d} ORDER BY r.date DESC LIMIT 1),
+            (SELECT r.rate FROM fx_rates_to_base r WHERE r.currency = {c} ORDER BY r.date ASC LIMIT 1)
+          ) END)",
+        c = currency,
+        d = date
+    )
+}
+
+/// (Re)creates the conversion views. Recreated on every start so column changes
+/// reach existing databases.
+///
+/// - `transaction_amounts`: transactions with `base_amount` (the debited leg) and
+///   `to_base_amount` (the credited leg, which differs for cross-currency transfers)
+/// - `account_flows`: one signed row per account side, in the account's own
+///   currency (`amount`) and in base currency (`base_amount`)
+/// - `transaction_lines`: category-level view where a split parent is replaced by
+///   its child lines; balances read `account_flows` so the parent counts once
+pub(crate) fn create_currency_views(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        "DROP VIEW IF EXISTS transaction_lines;
+         DROP VIEW IF EXISTS account_flows;
+         DROP VIEW IF EXISTS transaction_amounts;
+         DROP VIEW IF EXISTS fx_rates_to_base;
+
+         CREATE VIEW fx_rates_to_base AS
+         SELECT r.date, r.from_currency AS currency, r.rate
+         FROM fx_rates r JOIN fx_settings s ON s.id = 1 AND r.to_currency = s.base_currency
+         UNION ALL
+         SELECT r.date, r.to_currency AS currency, 1.0 / r.rate
+         FROM fx_rates r JOIN fx_settings s ON s.id = 1 AND r.from_currency = s.base_currency
+         WHERE r.rate > 0;
+
+         CREATE VIEW transaction_amounts AS
+         SELECT x.*,
+                x.amount * x.fx_rate AS base_amount,
+                CASE WHEN x.to_amount IS NULL THEN x.amount * x.fx_rate ELSE x.to_amount * x.to_fx_rate END AS to_base_amount
+         FROM (
+             SELECT t.*, {from_rate} AS fx_rate, {to_rate} AS to_fx_rate
+             FROM transactions t
+             LEFT JOIN accounts dest ON t.to_account_id = dest.id
+         ) x;
+
+         CREATE VIEW account_flows AS
+         SELECT id AS transaction_id, date, to_account_id AS account_id,
+                COALESCE(to_amount, amount) AS amount, to_base_amount AS base_amount
+         FROM transaction_amounts WHERE to_account_id IS NOT NULL
+         UNION ALL
+         SELECT id, date, from_account_id, -amount, -base_amount
+         FROM transaction_amounts WHERE from_account_id IS NOT NULL;
+
+         CREATE VIEW transaction_lines AS
+         SELECT t.id, NULL AS split_id, t.date, t.amount, t.base_amount, t.currency, t.direction, t.from_account_id, t.to_account_id,
+                t.category_id, t.client_id, t.project_id, t.investment_id, t.goal_id, t.notes
+         FROM transaction_amounts t
+         WHERE NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)
+         UNION ALL
+         SELECT t.id, s.id AS split_id, t.date, s.amount, s.amount * t.fx_rate, t.currency, t.direction, t.from_account_id, t.to_account_id,
+                s.category_id, s.client_id, s.project_id, t.investment_id, t.goal_id, COALESCE(s.notes, t.notes)
+         FROM transaction_splits s
+         JOIN transaction_amounts t ON s.transaction_id = t.id;",
+        from_rate = rate_to_base_sql("t.currency", "t.date"),
+        to_rate = rate_to_base_sql("COALESCE(dest.currency, t.currency)", "t.date"),
+    ))
+}
+
+pub(crate) fn base_currency(conn: &rusqlite::Connection) -> Result<String, String> {
+    conn.query_row("SELECT base_currency FROM fx_settings WHERE id = 1", [], |r| r.get(0))
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn account_currency(conn: &rusqlite::Connection, account_id: i64) -> Result<String, String> {
+    conn.query_row("SELECT currency FROM accounts WHERE id = ?1", [account_id], |r| r.get(0))
+        .map_err(|_| format!("Account {} not found", account_id))
+}
+
+/// Rate converting one unit of `from` into `to` on `date`, crossed through the
+/// base currency when there is no direct pair.
+pub(crate) fn convert_rate(conn: &rusqlite::Connection, from: &str, to: &str, date: &str) -> Result<f64, String> {
+    if from == to {
+        return Ok(1.0);
+    }
+    let sql = format!("SELECT {}, {}", rate_to_base_sql("?1", "?3"), rate_to_base_sql("?2", "?3"));
+    let (from_rate, to_rate): (Option<f64>, Option<f64>) = conn
+        .query_row(&sql, params![from, to, date], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?;
+
+    match (from_rate, to_rate) {
+        (Some(f), Some(t)) if t > 0.0 => Ok(f / t),
+        _ => Err(format!("No FX rate for {} to {} around {}; add one first", from, to, date)),
+    }
+}
+
+pub(crate) fn normalize_currency(code: &str) -> Result<String, String> {
+    let code = code.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(format!("'{}' is not a three-letter currency code", code));
+    }
+    Ok(code)
+}
+
+#[tauri::command]
+pub fn get_base_currency(db: State<DbConnection>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    base_currency(&conn)
+}
+
+/// Changes the reporting currency. Stored amounts are untouched; every total is
+/// converted on read, so rates to the new base must exist.
+#[tauri::command]
+pub fn set_base_currency(db: State<DbConnection>, currency: String) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_base_currency")?;
+    let currency = normalize_currency(&currency)?;
+
+    tx.execute("UPDATE fx_settings SET base_currency = ?1 WHERE id = 1", [currency])
+        .map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_fx_rates(db: State<DbConnection>, currency: Option<String>) -> Result<Vec<FxRate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let currency = currency.map(|c| c.trim().to_ascii_uppercase());
+
+    let mut stmt = conn.prepare("
+        SELECT id, date, from_currency, to_currency, rate, source
+        FROM fx_rates
+        WHERE (?1 IS NULL OR from_currency = ?1 OR to_currency = ?1)
+        ORDER BY date DESC, from_currency, to_currency
+    ").map_err(|e| e.to_string())?;
+
+    let rates = stmt.query_map([currency], |row| {
+        Ok(FxRate {
+            id: Some(row.get(0)?),
+            date: row.get(1)?,
+            from_currency: row.get(2)?,
+            to_currency: row.get(3)?,
+            rate: row.get(4)?,
+            source: row.get(5)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(rates)
+}
+
+fn upsert_fx_rate(conn: &rusqlite::Connection, date: &str, from: &str, to: &str, rate: f64, source: &str) -> Result<i64, String> {
+    let from = normalize_currency(from)?;
+    let to = normalize_currency(to)?;
+    if from == to {
+        return Err("A rate needs two different currencies".to_string());
+    }
+    if rate.is_nan() || rate <= 0.0 {
+        return Err(format!("Rate for {}/{} must be positive", from, to));
+    }
+
+    conn.execute(
+        "INSERT INTO fx_rates (date, from_currency, to_currency, rate, source) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(date, from_currency, to_currency) DO UPDATE SET rate = excluded.rate, source = excluded.source",
+        params![date, from, to, rate, source],
+    ).map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        "SELECT id FROM fx_rates WHERE date = ?1 AND from_currency = ?2 AND to_currency = ?3",
+        params![date, from, to],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())
+}
+
+/// Adds a rate, replacing any existing one for the same pair and date.
+#[tauri::command]
+pub fn save_fx_rate(db: State<DbConnection>, rate: FxRate) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "save_fx_rate")?;
+
+    let date = parse_import_date(&rate.date, None).ok_or_else(|| format!("Invalid date '{}'", rate.date))?;
+    if let Some(id) = rate.id {
+        tx.execute("DELETE FROM fx_rates WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    }
+    let id = upsert_fx_rate(&tx, &date, &rate.from_currency, &rate.to_currency, rate.rate, rate.source.as_deref().unwrap_or("manual"))?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn delete_fx_rate(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_fx_rate")?;
+
+    conn.execute("DELETE FROM fx_rates WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Imports `date,from,to,rate` lines (header row optional). Bad lines are
+/// reported and skipped; the rest are upserted.
+#[tauri::command]
+pub fn import_fx_rates_csv(db: State<DbConnection>, content: String) -> Result<FxRateImportResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "import_fx_rates_csv")?;
+    suspend_fx_rate_triggers(&tx).map_err(|e| e.to_string())?;
+
+    let mut imported = 0;
+    let mut errors = Vec::new();
+
+    for (line, record) in parse_csv_records(&content, ',') {
+        let cells: Vec<&str> = record.iter().map(|c| c.trim()).collect();
+        if cells.iter().all(|c| c.is_empty()) {
+            continue;
+        }
+        if cells.len() < 4 {
+            errors.push(format!("Line {}: expected date, from, to, rate", line));
+            continue;
+        }
+        let date = match parse_import_date(cells[0], None) {
+            Some(d) => d,
+            None => {
+                // Header row
+                if line == 1 {
+                    continue;
+                }
+                errors.push(format!("Line {}: invalid date '{}'", line, cells[0]));
+                continue;
+            }
+        };
+        let rate = match parse_import_amount(cells[3]) {
+            Some(r) => r,
+            None => {
+                errors.push(format!("Line {}: invalid rate '{}'", line, cells[3]));
+                continue;
+            }
+        };
+        match upsert_fx_rate(&tx, &date, cells[1], cells[2], rate, "csv") {
+            Ok(_) => imported += 1,
+            Err(e) => errors.push(format!("Line {}: {}", line, e)),
+        }
+    }
+
+    resume_fx_rate_triggers(&tx).map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(FxRateImportResult { imported, errors })
+}
+
+/// Currencies in use on accounts or transactions that have no rate to the base
+/// currency; their amounts are left out of converted totals.
+#[tauri::command]
+pub fn get_missing_fx_currencies(db: State<DbConnection>) -> Result<Vec<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT currency FROM (SELECT currency FROM accounts UNION SELECT currency FROM transactions)
+        WHERE currency != (SELECT base_currency FROM fx_settings WHERE id = 1)
+        AND currency NOT IN (SELECT currency FROM fx_rates_to_base)
+        ORDER BY currency
+    ").map_err(|e| e.to_string())?;
+
+    let currencies = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(currencies)
+}
+
+/// Realized FX gain/loss on transfers that convert one currency into another.
+/// Each foreign currency is an average-cost pool (opening balances, income and
+/// conversions in add to it; spending and conversions out draw it down); a
+/// conversion out realizes `proceeds - average cost` in base currency.
+#[tauri::command]
+pub fn get_fx_gain_loss(
+    db: State<DbConnection>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<FxGainReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let base = base_currency(&conn)?;
+
+    // (units, cost in base) per foreign currency
+    let mut pools: HashMap<String, (f64, f64)> = HashMap::new();
+
+    let mut opening_stmt = conn.prepare(&format!(
+        "SELECT currency, opening_balance, opening_balance * {}
+         FROM accounts a WHERE currency != ?1 AND opening_balance != 0",
+        rate_to_base_sql("a.currency", "date(a.created_at)")
+    )).map_err(|e| e.to_string())?;
+    let openings = opening_stmt
+        .query_map([&base], |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?, r.get::<_, Option<f64>>(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (currency, units, cost) in openings {
+        let cost = cost.ok_or_else(|| format!("No FX rate for {}; add one first", currency))?;
+        let pool = pools.entry(currency).or_insert((0.0, 0.0));
+        pool.0 += units;
+        pool.1 += cost;
+    }
+
+    let mut stmt = conn.prepare("
+        SELECT t.id, t.date, t.direction, t.amount, COALESCE(t.to_amount, t.amount),
+               fa.currency, ta.currency, t.base_amount, t.to_base_amount
+        FROM transaction_amounts t
+        LEFT JOIN accounts fa ON t.from_account_id = fa.id
+        LEFT JOIN accounts ta ON t.to_account_id = ta.id
+        WHERE (?1 IS NULL OR t.date <= ?1)
+        ORDER BY t.date, t.id
+    ").map_err(|e| e.to_string())?;
+
+    type FlowRow = (i64, String, String, f64, f64, Option<String>, Option<String>, Option<f64>, Option<f64>);
+    let rows = stmt
+        .query_map([&end_date], |r| Ok((
+            r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?,
+            r.get(5)?, r.get(6)?, r.get(7)?, r.get(8)?,
+        )))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<FlowRow>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut items = Vec::new();
+
+    for (id, date, direction, amount, to_amount, from_currency, to_currency, base_amount, to_base_amount) in rows {
+        // Moves within one currency don't change its pool
+        if from_currency.is_some() && from_currency == to_currency {
+            continue;
+        }
+        let missing = || format!("No FX rate for transaction {} on {}; add one first", id, date);
+
+        if let Some(from) = from_currency.as_ref().filter(|c| **c != base) {
+            let base_amount = base_amount.ok_or_else(missing)?;
+            let pool = pools.entry(from.clone()).or_insert((0.0, 0.0));
+            // Drawing past what the pool holds costs the excess at the day's rate
+            let cost_basis = if pool.0 >= amount {
+                pool.1 / pool.0 * amount
+            } else if pool.0 > 0.0 {
+                pool.1 + base_amount / amount * (amount - pool.0)
+            } else {
+                base_amount
+            };
+            pool.0 -= amount;
+            pool.1 = if pool.0 > 0.0 { pool.1 - cost_basis } else { 0.0 };
+
+            if direction == "transfer" && to_currency.is_some() {
+                let proceeds = to_base_amount.ok_or_else(missing)?;
+                let in_range = match &start_date {
+                    Some(start) => date >= *start,
+                    None => true,
+                };
+                if in_range {
+                    items.push(FxGainLoss {
+                        transaction_id: id,
+                        date: date.clone(),
+                        from_currency: from.clone(),
+                        to_currency: to_currency.clone().unwrap_or_default(),
+                        amount,
+                        to_amount,
+                        cost_basis: (cost_basis * 100.0).round() / 100.0,
+                        proceeds: (proceeds * 100.0).round() / 100.0,
+                        gain: ((proceeds - cost_basis) * 100.0).round() / 100.0,
+                    });
+                }
+            }
+        }
+
+        if let Some(to) = to_currency.as_ref().filter(|c| **c != base) {
+            // Bought with base currency: the cost is what was paid
+            let cost = if from_currency.as_deref() == Some(base.as_str()) {
+                amount
+            } else {
+                to_base_amount.ok_or_else(missing)?
+            };
+            let pool = pools.entry(to.clone()).or_insert((0.0, 0.0));
+            pool.0 += to_amount;
+            pool.1 += cost;
+        }
+    }
+
+    let total_gain = (items.iter().map(|i| i.gain).sum::<f64>() * 100.0).round() / 100.0;
+
+    Ok(FxGainReport {
+        base_currency: base,
+        items,
+        total_gain,
+    })
+}
+
+/// How a transaction is stored: `amount` in the paying account's currency
+/// and, for transfers into another currency, the credited `to_amount`.
+/// Amounts entered in a foreign currency keep that amount and the rate used.
+#[derive(Debug)]
+pub(crate) struct ResolvedCurrency {
+    pub currency: String,
+    pub amount: f64,
+    pub to_amount: Option<f64>,
+    pub original_currency: Option<String>,
+    pub original_amount: Option<f64>,
+    pub original_rate: Option<f64>, // Account currency per unit of the original
+}
+
+/// Resolves the stored currency and amounts for a transaction: the amount is
+/// kept in the paying account's currency (the receiving one for income),
+/// converted at the day's rate when given in another currency, and transfers
+/// into another currency carry `to_amount`, converted when not given.
+pub(crate) fn resolve_transaction_currency(
+    conn: &rusqlite::Connection,
+    date: &str,
+    amount: f64,
+    from_account_id: Option<i64>,
+    to_account_id: Option<i64>,
+    currency: Option<&str>,
+    to_amount: Option<f64>,
+) -> Result<ResolvedCurrency, String> {
+    let from_currency = from_account_id.map(|id| account_currency(conn, id)).transpose()?;
+    let to_currency = to_account_id.map(|id| account_currency(conn, id)).transpose()?;
+
+    let resolved = match from_currency.clone().or_else(|| to_currency.clone()) {
+        Some(c) => c,
+        None => base_currency(conn)?,
+    };
+
+    let mut original_currency = None;
+    let mut original_amount = None;
+    let mut original_rate = None;
+    let mut amount = amount;
+    if let Some(given) = currency.filter(|c| !c.trim().is_empty()) {
+        let given = normalize_currency(given)?;
+        if given != resolved {
+            let rate = convert_rate(conn, &given, &resolved, date)?;
+            original_amount = Some(amount);
+            original_rate = Some(rate);
+            original_currency = Some(given);
+            amount = (amount * rate * 100.0).round() / 100.0;
+        }
+    }
+
+    let to_amount = match (from_currency, to_currency) {
+        (Some(from), Some(to)) if from != to => Some(match to_amount {
+            Some(v) => v,
+            None => {
+                let rate = convert_rate(conn, &from, &to, date)?;
+                (amount * rate * 100.0).round() / 100.0
+            }
+        }),
+        _ => None,
+    };
+
+    Ok(ResolvedCurrency { currency: resolved, amount, to_amount, original_currency, original_amount, original_rate })
+}
@@ -2,13 +2,17 @@ use rusqlite::Result;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::credit_cards::{credit_card_summaries, load_card_statements, CardStatement, CreditCardSummary};
+use super::currency::{base_currency, rate_to_base_sql};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AccountBalance {
     pub id: i64,
     pub name: String,
     pub account_type: String,
-    pub balance: f64,
+    pub currency: String,
+    pub balance: f64,      // In the account's currency
+    pub base_balance: f64, // Converted to the base currency
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,10 +33,13 @@ pub struct DashboardGoal {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardData {
+    pub base_currency: String, // Every total below is in this currency
     pub total_balance: f64,
     pub bank_balance: f64,
     pub cash_balance: f64,
     pub investment_balance: f64,
+    pub credit_card_balance: f64, // Negative while card dues are outstanding
+    pub loan_balance: f64,        // Negative: outstanding principal on loan accounts
     pub individual_accounts: Vec<AccountBalance>,
     pub current_month_income: f64,
     pub current_month_expense: f64,
@@ -41,6 +48,8 @@ pub struct DashboardData {
     pub completed_goals_count: i64,
     pub goals: Vec<DashboardGoal>,
     pub breakdowns: std::collections::HashMap<String, Vec<CategoryBreakdown>>,
+    pub credit_cards: Vec<CreditCardSummary>,
+    pub upcoming_card_dues: Vec<CardStatement>,
 }
 
 #[tauri::command]
@@ -51,25 +60,31 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
     let mut bank_balance = 0.0;
     let mut cash_balance = 0.0;
     let mut investment_balance = 0.0;
+    let mut credit_card_balance = 0.0;
+    let mut loan_balance = 0.0;
     let mut individual_accounts = Vec::new();
     
     // Get all accounts with their dynamic balances (recursive for parents)
-    let mut accounts_stmt = conn.prepare("
+    // Daily net flows are in each account's own currency (native) and converted at the
+    // transaction-date rate (base); opening balances convert at the opening date
+    let mut accounts_stmt = conn.prepare(&format!("
         SELECT 
-            id, name, type, opening_balance, parent_id,
+            id, name, type, opening_balance, parent_id, currency,
+            opening_balance * COALESCE({}, 0) as base_opening,
             (
-                SELECT COALESCE(SUM(amount), 0) FROM transactions 
-                WHERE to_account_id = a.id 
-                OR to_account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
-            ) as incoming,
+                SELECT COALESCE(SUM(net_amount), 0) FROM account_daily_balances 
+                WHERE account_id = a.id 
+                OR account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
+            ) as net_flow,
             (
-                SELECT COALESCE(SUM(amount), 0) FROM transactions 
-                WHERE from_account_id = a.id 
-                OR from_account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
-            ) as outgoing
+                SELECT COALESCE(SUM(net_base_amount), 0) FROM account_daily_balances 
+                WHERE account_id = a.id 
+                OR account_id IN (SELECT id FROM accounts WHERE parent_id = a.id)
+            ) as base_net_flow
         FROM accounts a
+        WHERE closed_on IS NULL
         ORDER BY name
-    ").map_err(|e| e.to_string())?;
+    ", rate_to_base_sql("a.currency", "date(a.created_at)"))).map_err(|e| e.to_string())?;
     
     let accounts_data = accounts_stmt.query_map([], |row| {
         let id: i64 = row.get(0)?;
@@ -77,22 +92,27 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
         let account_type: String = row.get(2)?;
         let opening_balance: f64 = row.get(3)?;
         let parent_id: Option<i64> = row.get(4)?;
-        let incoming: f64 = row.get(5)?;
-        let outgoing: f64 = row.get(6)?;
+        let currency: String = row.get(5)?;
+        let base_opening: f64 = row.get(6)?;
+        let net_flow: f64 = row.get(7)?;
+        let base_net_flow: f64 = row.get(8)?;
         
-        let current_balance = opening_balance + incoming - outgoing;
+        let current_balance = opening_balance + net_flow;
+        let base_balance = base_opening + base_net_flow;
         
-        Ok((id, name, account_type, current_balance, parent_id))
+        Ok((id, name, account_type, currency, current_balance, base_balance, parent_id))
     }).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, rusqlite::Error>>().map_err(|e| e.to_string())?;
     
-    for (account_id, account_name, account_type, current_balance, parent_id) in accounts_data {
+    for (account_id, account_name, account_type, currency, current_balance, base_balance, parent_id) in accounts_data {
         let account_type_lower = account_type.to_lowercase();
         
         individual_accounts.push(AccountBalance {
             id: account_id,
             name: account_name,
             account_type: account_type.clone(),
+            currency,
             balance: current_balance,
+            base_balance,
         });
 
         // Don't add buckets to total balances (they are already included in parents)
@@ -102,9 +122,11 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
         }
 
         match account_type_lower.as_str() {
-            "bank" => bank_balance += current_balance,
-            "cash" => cash_balance += current_balance,
-            "investment" => investment_balance += current_balance,
+            "bank" => bank_balance += base_balance,
+            "cash" => cash_balance += base_balance,
+            "investment" => investment_balance += base_balance,
+            "credit_card" => credit_card_balance += base_balance,
+            "loan" => loan_balance += base_balance,
             "bucket" => {}, // Buckets are already handled if they have parents
             _ => {}
         }
@@ -112,7 +134,7 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
     
     // Get current month stats
     let current_month_income: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(amount), 0) FROM transactions 
+        "SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts 
          WHERE direction = 'income' 
          AND strftime('%Y-%m', date) = strftime('%Y-%m', 'now', 'localtime')",
         [],
@@ -120,14 +142,14 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
     ).unwrap_or(0.0);
     
     let current_month_expense: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(amount), 0) FROM transactions 
+        "SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts 
          WHERE direction = 'expense' 
          AND strftime('%Y-%m', date) = strftime('%Y-%m', 'now', 'localtime')",
         [],
         |row| row.get(0)
     ).unwrap_or(0.0);
     
-    let total_balance = bank_balance + cash_balance + investment_balance;
+    let total_balance = bank_balance + cash_balance + investment_balance + credit_card_balance + loan_balance;
     
     // Get Goal stats
     let active_goals_count: i64 = conn.query_row("SELECT COUNT(*) FROM goals WHERE status = 'active'", [], |r| r.get(0)).unwrap_or(0);
@@ -156,12 +178,12 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
     // Get breakdowns per account type (by category)
     // This is complex, we'll group by category and account type
     let mut breakdowns = std::collections::HashMap::new();
-    let types = vec!["bank", "cash", "investment"];
+    let types = vec!["bank", "cash", "investment", "credit_card"];
     
     for t in types {
         let mut b_stmt = conn.prepare("
-            SELECT c.name, SUM(t.amount) 
-            FROM transactions t
+            SELECT c.name, COALESCE(SUM(t.base_amount), 0) 
+            FROM transaction_lines t
             JOIN categories c ON t.category_id = c.id
             JOIN accounts a ON (t.to_account_id = a.id OR t.from_account_id = a.id)
             WHERE (LOWER(a.type) = ?1 OR a.parent_id IN (SELECT id FROM accounts WHERE LOWER(type) = ?1))
@@ -170,7 +192,7 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
                 OR (t.direction = 'expense' AND t.from_account_id = a.id)
             )
             GROUP BY c.name
-            ORDER BY SUM(t.amount) DESC
+            ORDER BY SUM(t.base_amount) DESC
         ").map_err(|e| e.to_string())?;
         
         let sums = b_stmt.query_map([t], |row| {
@@ -184,10 +206,13 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
     }
 
     Ok(DashboardData {
+        base_currency: base_currency(&conn)?,
         total_balance,
         bank_balance,
         cash_balance,
         investment_balance,
+        credit_card_balance,
+        loan_balance,
         individual_accounts,
         current_month_income,
         current_month_expense,
@@ -196,5 +221,7 @@ pub fn get_dashboard_data(db: State<DbConnection>) -> Result<DashboardData, Stri
         completed_goals_count,
         goals,
         breakdowns,
+        credit_cards: credit_card_summaries(&conn)?,
+        upcoming_card_dues: load_card_statements(&conn, None, true)?,
     })
 }
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use chrono::{Duration, Local, Months};
+use crate::db::DbConnection;
+use super::balances::rolled_up_balance_as_of;
+use super::budget::calculate_next_date;
+use super::currency::resolve_transaction_currency;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForecastDay {
+    pub date: String,
+    pub inflow: f64,
+    pub outflow: f64,
+    pub balance: f64, // End of day
+    pub below_zero: bool,
+    pub below_floor: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountForecast {
+    pub account_id: i64,
+    pub name: String,
+    pub account_type: String,
+    pub currency: String,
+    pub starting_balance: f64, // End of today, before schedules still to run
+    pub floor: Option<f64>,    // The account's balance floor; credit cards default to minus their limit
+    pub lowest_balance: f64,
+    pub lowest_on: String,
+    pub first_below_zero: Option<String>,
+    pub first_below_floor: Option<String>,
+    pub days: Vec<ForecastDay>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CashFlowForecast {
+    pub start_date: String,
+    pub end_date: String,
+    pub accounts: Vec<AccountForecast>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Adds a flow to an account's day, split into inflow and outflow
+fn add_flow(flows: &mut HashMap<(i64, String), (f64, f64)>, account_id: i64, date: &str, amount: f64) {
+    let entry = flows.entry((account_id, date.to_string())).or_insert((0.0, 0.0));
+    if amount >= 0.0 {
+        entry.0 += amount;
+    } else {
+        entry.1 -= amount;
+    }
+}
+
+/// Projected end-of-day balance of every open account for the next `months`
+/// months (3 by default). Starts from today's balance, adds transactions
+/// already recorded for later dates and expands active schedules into their
+/// occurrences. Schedules overdue but not yet processed count on today.
+/// Buckets are folded into their parent account, so moves between a parent
+/// and its buckets don't show as flows.
+#[tauri::command]
+pub fn get_cash_flow_forecast(
+    db: State<DbConnection>,
+    months: Option<u32>,
+    account_id: Option<i64>,
+) -> Result<CashFlowForecast, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let months = months.unwrap_or(3);
+    if !(1..=24).contains(&months) {
+        return Err("Forecast must cover 1 to 24 months".to_string());
+    }
+    let start = Local::now().date_naive();
+    let end = start.checked_add_months(Months::new(months)).ok_or("Invalid forecast range")?;
+    let today = start.format("%Y-%m-%d").to_string();
+    let end_date = end.format("%Y-%m-%d").to_string();
+
+    // Loans run their own schedule and are left out; asking for a bucket
+    // forecasts its parent
+    let mut stmt = conn.prepare(
+        "SELECT id, name, type, currency, balance_floor, credit_limit FROM accounts
+         WHERE closed_on IS NULL AND type != 'loan' AND parent_id IS NULL
+           AND (?1 IS NULL OR id = (SELECT COALESCE(parent_id, id) FROM accounts WHERE id = ?1))
+         ORDER BY name"
+    ).map_err(|e| e.to_string())?;
+    let accounts = stmt.query_map([account_id], |r| {
+        Ok((
+            r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?, r.get::<_, Option<f64>>(4)?, r.get::<_, Option<f64>>(5)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    if account_id.is_some() && accounts.is_empty() {
+        return Err("Account not found or closed".to_string());
+    }
+
+    // Every account mapped to the top-level account it rolls up into
+    let mut stmt = conn.prepare("SELECT id, COALESCE(parent_id, id) FROM accounts").map_err(|e| e.to_string())?;
+    let family: HashMap<i64, i64> = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let root = |acc: Option<i64>| acc.map(|id| family.get(&id).copied().unwrap_or(id));
+
+    let mut flows: HashMap<(i64, String), (f64, f64)> = HashMap::new();
+
+    // Transactions already entered for dates after today
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(a.parent_id, a.id), f.date, f.amount
+         FROM account_flows f
+         JOIN accounts a ON a.id = f.account_id
+         JOIN transactions t ON t.id = f.transaction_id
+         LEFT JOIN accounts src ON src.id = t.from_account_id
+         LEFT JOIN accounts dst ON dst.id = t.to_account_id
+         WHERE f.date > ?1 AND f.date <= ?2
+           AND NOT (src.id IS NOT NULL AND dst.id IS NOT NULL
+                    AND COALESCE(src.parent_id, src.id) = COALESCE(dst.parent_id, dst.id))"
+    ).map_err(|e| e.to_string())?;
+    let recorded = stmt.query_map(params![today, end_date], |r| {
+        Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, f64>(2)?))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    for (acc, date, amount) in recorded {
+        add_flow(&mut flows, acc, &date, amount);
+    }
+
+    // Scheduled occurrences. process_pending_schedules logs both stored legs
+    // whatever the type, so the source pays `amount` and the destination
+    // receives `to_amount`
+    let mut stmt = conn.prepare(
+        "SELECT amount, frequency, frequency_interval, next_run_date, from_account_id, to_account_id, date(created_at)
+         FROM scheduled_transactions
+         WHERE is_active = 1 AND next_run_date <= ?1"
+    ).map_err(|e| e.to_string())?;
+    let schedules = stmt.query_map([&end_date], |r| {
+        Ok((
+            r.get::<_, f64>(0)?, r.get::<_, String>(1)?, r.get::<_, i32>(2)?, r.get::<_, String>(3)?,
+            r.get::<_, Option<i64>>(4)?, r.get::<_, Option<i64>>(5)?, r.get::<_, String>(6)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    for (amount, freq, interval, next_run, from_acc, to_acc, created_on) in schedules {
+        let to_amount = match (from_acc, to_acc) {
+            (Some(_), Some(_)) => resolve_transaction_currency(&conn, &today, amount, from_acc, to_acc, None, None)
+                .ok()
+                .and_then(|r| r.to_amount)
+                .unwrap_or(amount),
+            _ => amount,
+        };
+
+        let (from_root, to_root) = (root(from_acc), root(to_acc));
+        if from_root.is_some() && from_root == to_root {
+            continue;
+        }
+
+        let mut run_date = next_run;
+        while run_date <= end_date {
+            // Runs dated before the schedule existed are skipped when processed
+            if run_date >= created_on {
+                let on = if run_date < today { today.as_str() } else { run_date.as_str() };
+                if let Some(acc) = from_root {
+                    add_flow(&mut flows, acc, on, -amount);
+                }
+                if let Some(acc) = to_root {
+                    add_flow(&mut flows, acc, on, to_amount);
+                }
+            }
+            let next = calculate_next_date(&run_date, &freq, interval)?;
+            if next <= run_date {
+                break;
+            }
+            run_date = next;
+        }
+    }
+
+    let mut result = Vec::new();
+    for (id, name, account_type, currency, balance_floor, credit_limit) in accounts {
+        let is_card = account_type == "credit_card";
+        let floor = balance_floor.or_else(|| credit_limit.filter(|_| is_card).map(|limit| -limit));
+        let starting_balance = round2(rolled_up_balance_as_of(&conn, id, Some(&today))?);
+
+        let mut balance = starting_balance;
+        let mut lowest_balance = starting_balance;
+        let mut lowest_on = today.clone();
+        let mut first_below_zero = None;
+        let mut first_below_floor = None;
+        let mut days = Vec::new();
+
+        let mut day = start;
+        while day <= end {
+            let date = day.format("%Y-%m-%d").to_string();
+            let (inflow, outflow) = flows.get(&(id, date.clone())).copied().unwrap_or((0.0, 0.0));
+            balance = round2(balance + inflow - outflow);
+
+            // Card balances are negative while dues are outstanding, so only the floor applies
+            let below_zero = !is_card && balance < 0.0;
+            let below_floor = floor.is_some_and(|f| balance < f);
+            if below_zero && first_below_zero.is_none() {
+                first_below_zero = Some(date.clone());
+            }
+            if below_floor && first_below_floor.is_none() {
+                first_below_floor = Some(date.clone());
+            }
+            if balance < lowest_balance {
+                lowest_balance = balance;
+                lowest_on = date.clone();
+            }
+
+            days.push(ForecastDay {
+                date,
+                inflow: round2(inflow),
+                outflow: round2(outflow),
+                balance,
+                below_zero,
+                below_floor,
+            });
+            day += Duration::days(1);
+        }
+
+        result.push(AccountForecast {
+            account_id: id,
+            name,
+            account_type,
+            currency,
+            starting_balance,
+            floor,
+            lowest_balance,
+            lowest_on,
+            first_below_zero,
+            first_below_floor,
+            days,
+        });
+    }
+
+    Ok(CashFlowForecast {
+        start_date: today,
+        end_date,
+        accounts: result,
+    })
+}
@@ -1,7 +1,9 @@
+use chrono::{Datelike, Local, Months, NaiveDate};
 use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Goal {
@@ -10,24 +12,86 @@ pub struct Goal {
     pub name: String,
     pub target_amount: f64,
     pub current_amount: f64,
-    pub status: String, // active, completed
+    pub status: String, // active, completed, paused, abandoned
     pub deadline: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalStatusChange {
+    pub status: String,
+    pub changed_at: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalMonthProgress {
+    pub month: String, // YYYY-MM
+    pub contributed: f64,
+    pub withdrawn: f64,
+    pub cumulative: f64, // Net progress at the end of the month
+    pub percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalMilestone {
+    pub percent: f64,
+    pub reached_on: Option<String>, // First day the running total got there
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalTimeline {
+    pub goal_id: i64,
+    pub target_amount: f64,
+    pub current_amount: f64,
+    pub status: String,
+    pub months: Vec<GoalMonthProgress>,
+    pub milestones: Vec<GoalMilestone>,
+    pub status_history: Vec<GoalStatusChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoalProjection {
+    pub goal_id: i64,
+    pub name: String,
+    pub bucket_id: i64,
+    pub target_amount: f64,
+    pub current_amount: f64,
+    pub remaining: f64,
+    pub deadline: Option<String>,
+    pub first_contribution: Option<String>,
+    pub monthly_contribution: f64,       // Average net contribution per month since the first one
+    pub annual_growth_percent: Option<f64>, // Set when the goal's bucket is investment-backed
+    pub projected_completion: Option<String>, // None when the current pace never gets there
+    pub months_to_deadline: Option<i64>,
+    pub required_monthly: Option<f64>,   // Needed each month from now to meet the deadline
+    pub status: String,                  // completed, on_track, behind, overdue, no_deadline
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AllocationSettings {
-    pub emergency_target: f64,
+    pub emergency_target: f64, // Fill target for key buckets without their own target_balance
     pub trigger_category_id: Option<i64>,
     pub is_enabled: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationShare {
+    pub bucket_role: String,
+    pub pc: f64, // Fraction of the income, 0.25 = 25%
+}
+
+/// One allocation tier. Tiers are tried in `tier` order and the first whose
+/// condition holds is used: the bucket with `key_role` is filled below
+/// `fill_below` (fraction of its target). A tier without a key always applies.
+/// Whatever the shares leave over stays in the parent account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AllocationRule {
     pub id: Option<i64>,
     pub tier: i64,
-    pub emergency_pc: f64,
-    pub asset_pc: f64,
-    pub travel_pc: f64,
+    pub name: Option<String>,
+    pub key_role: Option<String>,
+    pub fill_below: Option<f64>,
+    pub shares: Vec<AllocationShare>,
 }
 
 #[tauri::command]
@@ -57,36 +121,327 @@ pub fn get_goals(db: State<DbConnection>, bucket_id: Option<i64>) -> Result<Vec<
 
 #[tauri::command]
 pub fn create_goal(db: State<DbConnection>, goal: Goal) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_goal")?;
+    check_goal_status(&goal.status)?;
     
-    conn.execute(
+    tx.execute(
         "INSERT INTO goals (bucket_id, name, target_amount, current_amount, status, deadline) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
         params![goal.bucket_id, goal.name, goal.target_amount, goal.current_amount, goal.status, goal.deadline],
     ).map_err(|e| e.to_string())?;
-    
-    Ok(conn.last_insert_rowid())
+    let id = tx.last_insert_rowid();
+    record_goal_status(&tx, id, &goal.status, None)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
 }
 
 #[tauri::command]
 pub fn update_goal(db: State<DbConnection>, goal: Goal) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_goal")?;
     let id = goal.id.ok_or("Goal ID required")?;
+    check_goal_status(&goal.status)?;
+    let old_status: String = tx.query_row("SELECT status FROM goals WHERE id = ?1", [id], |r| r.get(0))
+        .map_err(|_| "Goal not found".to_string())?;
     
-    conn.execute(
+    tx.execute(
         "UPDATE goals SET name = ?1, target_amount = ?2, current_amount = ?3, status = ?4, deadline = ?5 WHERE id = ?6",
         params![goal.name, goal.target_amount, goal.current_amount, goal.status, goal.deadline, id],
     ).map_err(|e| e.to_string())?;
-    
+    if goal.status != old_status {
+        record_goal_status(&tx, id, &goal.status, None)?;
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
 pub fn delete_goal(db: State<DbConnection>, id: i64) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM goals WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_goal")?;
+    tx.execute("DELETE FROM goal_status_history WHERE goal_id = ?1", [id]).map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM goals WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+const GOAL_STATUSES: &[&str] = &["active", "completed", "paused", "abandoned"];
+
+fn check_goal_status(status: &str) -> Result<(), String> {
+    if !GOAL_STATUSES.contains(&status) {
+        return Err(format!("Unknown goal status: {}", status));
+    }
+    Ok(())
+}
+
+/// Appends a status change to the goal's history.
+pub(crate) fn record_goal_status(conn: &rusqlite::Connection, goal_id: i64, status: &str, note: Option<&str>) -> Result<(), String> {
+    check_goal_status(status)?;
+    conn.execute(
+        "INSERT INTO goal_status_history (goal_id, status, note) VALUES (?1, ?2, ?3)",
+        params![goal_id, status, note],
+    ).map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// Moves a goal to another status (pause, resume, abandon, reopen) and
+/// records the change. Progress keeps syncing whatever the status.
+#[tauri::command]
+pub fn set_goal_status(db: State<DbConnection>, goal_id: i64, status: String, note: Option<String>) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_goal_status")?;
+
+    let current: String = tx.query_row("SELECT status FROM goals WHERE id = ?1", [goal_id], |r| r.get(0))
+        .map_err(|_| "Goal not found".to_string())?;
+    check_goal_status(&status)?;
+    if current == status {
+        return Ok(());
+    }
+
+    record_goal_status(&tx, goal_id, &status, note.as_deref())?;
+    tx.execute("UPDATE goals SET status = ?1 WHERE id = ?2", params![status, goal_id]).map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Month-by-month progress of a goal from its linked transactions, the dates
+/// it first reached 25/50/75/100% of the target, and its status history.
+#[tauri::command]
+pub fn get_goal_timeline(db: State<DbConnection>, goal_id: i64) -> Result<GoalTimeline, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let (bucket_id, target, current, status): (i64, f64, f64, String) = conn.query_row(
+        "SELECT bucket_id, target_amount, current_amount, status FROM goals WHERE id = ?1",
+        [goal_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    ).map_err(|_| "Goal not found".to_string())?;
+
+    // Signed movements into (+) and out of (-) the goal's bucket
+    let mut stmt = conn.prepare("
+        SELECT date,
+               CASE WHEN to_account_id = ?2 THEN COALESCE(to_amount, amount) ELSE 0 END
+             - CASE WHEN from_account_id = ?2 THEN amount ELSE 0 END
+        FROM transactions
+        WHERE goal_id = ?1 AND (to_account_id = ?2 OR from_account_id = ?2)
+        ORDER BY date, id
+    ").map_err(|e| e.to_string())?;
+    let movements = stmt
+        .query_map(params![goal_id, bucket_id], |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let percent_of = |amount: f64| if target > 0.0 { (amount / target * 1000.0).round() / 10.0 } else { 0.0 };
+    let mut milestones: Vec<GoalMilestone> = [25.0, 50.0, 75.0, 100.0]
+        .iter()
+        .map(|p| GoalMilestone { percent: *p, reached_on: None })
+        .collect();
+
+    let mut months: Vec<GoalMonthProgress> = Vec::new();
+    let mut cumulative = 0.0;
+    for (date, amount) in &movements {
+        cumulative += amount;
+        for milestone in milestones.iter_mut().filter(|m| m.reached_on.is_none()) {
+            if target > 0.0 && cumulative >= target * milestone.percent / 100.0 - 0.005 {
+                milestone.reached_on = Some(date[..date.len().min(10)].to_string());
+            }
+        }
+
+        let month = date[..date.len().min(7)].to_string();
+        if months.last().map(|m| &m.month) != Some(&month) {
+            months.push(GoalMonthProgress { month, contributed: 0.0, withdrawn: 0.0, cumulative: 0.0, percent: 0.0 });
+        }
+        if let Some(entry) = months.last_mut() {
+            if *amount >= 0.0 {
+                entry.contributed += amount;
+            } else {
+                entry.withdrawn -= amount;
+            }
+            entry.cumulative = (cumulative * 100.0).round() / 100.0;
+            entry.percent = percent_of(cumulative);
+        }
+    }
+
+    // Months without activity carry the running total forward, up to this month
+    let mut filled: Vec<GoalMonthProgress> = Vec::new();
+    let this_month = Local::now().format("%Y-%m").to_string();
+    let mut next_entries = months.into_iter().peekable();
+    if let Some(first) = next_entries.peek().map(|m| m.month.clone()) {
+        let mut month = NaiveDate::parse_from_str(&format!("{}-01", first), "%Y-%m-%d").map_err(|e| e.to_string())?;
+        let mut carried = 0.0;
+        loop {
+            let key = month.format("%Y-%m").to_string();
+            if key > this_month && next_entries.peek().is_none() {
+                break;
+            }
+            match next_entries.next_if(|m| m.month == key) {
+                Some(mut entry) => {
+                    entry.contributed = (entry.contributed * 100.0).round() / 100.0;
+                    entry.withdrawn = (entry.withdrawn * 100.0).round() / 100.0;
+                    carried = entry.cumulative;
+                    filled.push(entry);
+                }
+                None => filled.push(GoalMonthProgress { month: key, contributed: 0.0, withdrawn: 0.0, cumulative: carried, percent: percent_of(carried) }),
+            }
+            month = month + Months::new(1);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT status, changed_at, note FROM goal_status_history WHERE goal_id = ?1 ORDER BY changed_at, id")
+        .map_err(|e| e.to_string())?;
+    let status_history = stmt
+        .query_map([goal_id], |r| Ok(GoalStatusChange { status: r.get(0)?, changed_at: r.get(1)?, note: r.get(2)? }))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(GoalTimeline {
+        goal_id,
+        target_amount: target,
+        current_amount: current,
+        status,
+        months: filled,
+        milestones,
+        status_history,
+    })
+}
+
+// Whole months from `from` to `to`, counting a started month only once its day is reached
+fn months_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    let mut months = (to.year() - from.year()) as i64 * 12 + to.month() as i64 - from.month() as i64;
+    if to.day() < from.day() {
+        months -= 1;
+    }
+    months
+}
+
+// Balance after `months` of monthly contributions, compounding monthly at `rate`
+fn future_value(current: f64, monthly: f64, rate: f64, months: i64) -> f64 {
+    if rate == 0.0 {
+        return current + monthly * months as f64;
+    }
+    let growth = (1.0 + rate).powi(months as i32);
+    current * growth + monthly * (growth - 1.0) / rate
+}
+
+/// Pace and outlook for every active goal: the monthly contribution rate from
+/// its linked transactions, when that pace completes it, and what it takes
+/// each month to meet the deadline. `expected_annual_return` (percent) is
+/// applied to goals saved in an active investment account or a bucket under
+/// one; other buckets grow by contributions only.
+#[tauri::command]
+pub fn get_goal_projections(db: State<DbConnection>, expected_annual_return: Option<f64>) -> Result<Vec<GoalProjection>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    goal_projections(&conn, expected_annual_return)
+}
+
+fn goal_projections(conn: &rusqlite::Connection, expected_annual_return: Option<f64>) -> Result<Vec<GoalProjection>, String> {
+    let today = Local::now().date_naive();
+
+    let mut stmt = conn.prepare("
+        SELECT g.id, g.name, g.bucket_id, g.target_amount, g.current_amount, g.deadline,
+               (a.type = 'investment' AND a.is_investment_active = 1) OR IFNULL(p.type = 'investment' AND p.is_investment_active = 1, 0),
+               (SELECT MIN(t.date) FROM transactions t WHERE t.goal_id = g.id),
+               COALESCE((SELECT SUM(COALESCE(t.to_amount, t.amount)) FROM transactions t WHERE t.goal_id = g.id AND t.to_account_id = g.bucket_id), 0)
+             - COALESCE((SELECT SUM(t.amount) FROM transactions t WHERE t.goal_id = g.id AND t.from_account_id = g.bucket_id), 0)
+        FROM goals g
+        JOIN accounts a ON g.bucket_id = a.id
+        LEFT JOIN accounts p ON a.parent_id = p.id
+        WHERE g.status = 'active'
+        ORDER BY g.deadline IS NULL, g.deadline, g.name
+    ").map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, i64>(2)?, r.get::<_, f64>(3)?, r.get::<_, f64>(4)?,
+            r.get::<_, Option<String>>(5)?, r.get::<_, bool>(6)?, r.get::<_, Option<String>>(7)?, r.get::<_, f64>(8)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut projections = Vec::new();
+    for (goal_id, name, bucket_id, target, current, deadline, invested, first_contribution, contributed) in rows {
+        let remaining = ((target - current).max(0.0) * 100.0).round() / 100.0;
+        let annual_growth_percent = expected_annual_return.filter(|_| invested);
+        let rate = annual_growth_percent.unwrap_or(0.0) / 100.0 / 12.0;
+
+        // Contribution pace over the goal's history, counting at least one month
+        let monthly_contribution = match first_contribution.as_deref().and_then(|d| NaiveDate::parse_from_str(&d[..d.len().min(10)], "%Y-%m-%d").ok()) {
+            Some(first) => {
+                let months = ((today - first).num_days() as f64 / 30.4375).max(1.0);
+                (contributed / months * 100.0).round() / 100.0
+            }
+            None => 0.0,
+        };
+
+        let projected_completion = if remaining <= 0.0 {
+            Some(today)
+        } else {
+            (1..=1200)
+                .find(|m| future_value(current, monthly_contribution.max(0.0), rate, *m) >= target)
+                .and_then(|m| today.checked_add_months(Months::new(m as u32)))
+        };
+
+        let deadline_date = deadline.as_deref().and_then(|d| NaiveDate::parse_from_str(&d[..d.len().min(10)], "%Y-%m-%d").ok());
+        let months_to_deadline = deadline_date.map(|d| months_between(today, d).max(0));
+        let required_monthly = months_to_deadline.map(|months| {
+            let required = if remaining <= 0.0 {
+                0.0
+            } else if months == 0 {
+                remaining // Due now
+            } else if rate == 0.0 {
+                remaining / months as f64
+            } else {
+                let growth = (1.0 + rate).powi(months as i32);
+                ((target - current * growth) * rate / (growth - 1.0)).max(0.0)
+            };
+            (required * 100.0).round() / 100.0
+        });
+
+        let status = if remaining <= 0.0 {
+            "completed"
+        } else {
+            match deadline_date {
+                None => "no_deadline",
+                Some(d) if d < today => "overdue",
+                Some(d) if projected_completion.is_some_and(|p| p <= d) => "on_track",
+                Some(_) => "behind",
+            }
+        };
+
+        projections.push(GoalProjection {
+            goal_id,
+            name,
+            bucket_id,
+            target_amount: target,
+            current_amount: current,
+            remaining,
+            deadline,
+            first_contribution,
+            monthly_contribution,
+            annual_growth_percent,
+            projected_completion: projected_completion.map(|d| d.format("%Y-%m-%d").to_string()),
+            months_to_deadline,
+            required_monthly,
+            status: status.to_string(),
+        });
+    }
+
+    Ok(projections)
+}
+
 #[tauri::command]
 pub fn get_allocation_settings(db: State<DbConnection>) -> Result<AllocationSettings, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -107,40 +462,140 @@ pub fn get_allocation_settings(db: State<DbConnection>) -> Result<AllocationSett
     Ok(settings)
 }
 
-#[tauri::command]
-pub fn get_allocation_rules(db: State<DbConnection>) -> Result<Vec<AllocationRule>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
-    let mut stmt = conn.prepare("SELECT id, tier, emergency_pc, asset_pc, travel_pc FROM allocation_rules ORDER BY tier ASC").map_err(|e| e.to_string())?;
-    let rules = stmt.query_map([], |row| {
+pub(crate) fn load_allocation_rules(conn: &rusqlite::Connection) -> Result<Vec<AllocationRule>, String> {
+    let mut stmt = conn.prepare("SELECT id, tier, name, key_role, fill_below FROM allocation_tiers ORDER BY tier ASC").map_err(|e| e.to_string())?;
+    let mut rules = stmt.query_map([], |row| {
         Ok(AllocationRule {
             id: Some(row.get(0)?),
             tier: row.get(1)?,
-            emergency_pc: row.get(2)?,
-            asset_pc: row.get(3)?,
-            travel_pc: row.get(4)?,
+            name: row.get(2)?,
+            key_role: row.get(3)?,
+            fill_below: row.get(4)?,
+            shares: Vec::new(),
         })
     }).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
-    
+
+    let mut share_stmt = conn.prepare("SELECT bucket_role, pc FROM allocation_tier_shares WHERE tier_id = ?1 ORDER BY id").map_err(|e| e.to_string())?;
+    for rule in rules.iter_mut() {
+        rule.shares = share_stmt.query_map([rule.id], |row| {
+            Ok(AllocationShare { bucket_role: row.get(0)?, pc: row.get(1)? })
+        }).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    }
+
     Ok(rules)
 }
 
+// Roles are matched by exact text, so surrounding spaces are dropped before
+// validating and saving; a blank key role means the tier has none
+fn normalize_allocation_rule(rule: &mut AllocationRule) {
+    for share in rule.shares.iter_mut() {
+        share.bucket_role = share.bucket_role.trim().to_string();
+    }
+    rule.key_role = rule.key_role.as_deref().map(str::trim).filter(|r| !r.is_empty()).map(String::from);
+}
+
+fn validate_allocation_rule(rule: &AllocationRule) -> Result<(), String> {
+    let mut roles = std::collections::HashSet::new();
+    let mut total = 0.0;
+    for share in &rule.shares {
+        if share.bucket_role.is_empty() || share.bucket_role == "none" {
+            return Err("Every share needs a bucket role".to_string());
+        }
+        if !roles.insert(share.bucket_role.as_str()) {
+            return Err(format!("Bucket role '{}' appears twice", share.bucket_role));
+        }
+        if share.pc < 0.0 {
+            return Err("Percentages cannot be negative".to_string());
+        }
+        total += share.pc;
+    }
+    if total > 1.0 + 1e-9 {
+        return Err(format!("Percentages add up to {}%; they must not exceed 100%", (total * 100.0).round()));
+    }
+    if rule.key_role.is_some() && !rule.fill_below.is_some_and(|f| f > 0.0) {
+        return Err("A keyed tier needs a fill level above 0%".to_string());
+    }
+    Ok(())
+}
+
+fn save_allocation_shares(conn: &rusqlite::Connection, tier_id: i64, shares: &[AllocationShare]) -> Result<(), String> {
+    conn.execute("DELETE FROM allocation_tier_shares WHERE tier_id = ?1", [tier_id]).map_err(|e| e.to_string())?;
+    for share in shares {
+        conn.execute(
+            "INSERT INTO allocation_tier_shares (tier_id, bucket_role, pc) VALUES (?1, ?2, ?3)",
+            params![tier_id, share.bucket_role, share.pc],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
-pub fn update_allocation_rule(db: State<DbConnection>, rule: AllocationRule) -> Result<(), String> {
+pub fn get_allocation_rules(db: State<DbConnection>) -> Result<Vec<AllocationRule>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_allocation_rules(&conn)
+}
+
+#[tauri::command]
+pub fn create_allocation_rule(db: State<DbConnection>, mut rule: AllocationRule) -> Result<i64, String> {
+    normalize_allocation_rule(&mut rule);
+    validate_allocation_rule(&rule)?;
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_allocation_rule")?;
+
+    tx.execute(
+        "INSERT INTO allocation_tiers (tier, name, key_role, fill_below) VALUES (?1, ?2, ?3, ?4)",
+        params![rule.tier, rule.name, rule.key_role, rule.fill_below],
+    ).map_err(|e| e.to_string())?;
+    let id = tx.last_insert_rowid();
+    save_allocation_shares(&tx, id, &rule.shares)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn update_allocation_rule(db: State<DbConnection>, mut rule: AllocationRule) -> Result<(), String> {
     let id = rule.id.ok_or("Rule ID required")?;
-    
-    conn.execute(
-        "UPDATE allocation_rules SET emergency_pc = ?1, asset_pc = ?2, travel_pc = ?3 WHERE id = ?4",
-        params![rule.emergency_pc, rule.asset_pc, rule.travel_pc, id],
+    normalize_allocation_rule(&mut rule);
+    validate_allocation_rule(&rule)?;
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_allocation_rule")?;
+
+    let updated = tx.execute(
+        "UPDATE allocation_tiers SET tier = ?1, name = ?2, key_role = ?3, fill_below = ?4 WHERE id = ?5",
+        params![rule.tier, rule.name, rule.key_role, rule.fill_below, id],
     ).map_err(|e| e.to_string())?;
-    
+    if updated == 0 {
+        return Err("Rule not found".to_string());
+    }
+    save_allocation_shares(&tx, id, &rule.shares)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_allocation_rule(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_allocation_rule")?;
+    tx.execute("DELETE FROM allocation_tier_shares WHERE tier_id = ?1", [id]).map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM allocation_tiers WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
 pub fn update_allocation_settings(db: State<DbConnection>, settings: AllocationSettings) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_allocation_settings")?;
     let enabled = if settings.is_enabled { 1 } else { 0 };
     
     conn.execute(
@@ -150,3 +605,52 @@ pub fn update_allocation_settings(db: State<DbConnection>, settings: AllocationS
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growth_applies_to_buckets_under_an_active_investment_account() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance, is_investment_active) VALUES
+                (1, 'Brokerage', 'investment', 0, 1), (2, 'Closed fund', 'investment', 0, 0), (3, 'Bank', 'bank', 0, 0);
+             INSERT INTO accounts (id, name, type, opening_balance, parent_id) VALUES
+                (4, 'Retirement', 'bucket', 0, 1), (5, 'Old fund', 'bucket', 0, 2), (6, 'Trip', 'bucket', 0, 3);
+             INSERT INTO goals (id, bucket_id, name, target_amount, current_amount, status) VALUES
+                (1, 4, 'Retire', 1000, 100, 'active'), (2, 5, 'Old', 1000, 100, 'active'), (3, 6, 'Trip', 1000, 100, 'active');",
+        ).unwrap();
+
+        let projections = goal_projections(&conn, Some(8.0)).unwrap();
+        let growth = |id: i64| projections.iter().find(|p| p.goal_id == id).unwrap().annual_growth_percent;
+        assert_eq!(growth(1), Some(8.0));
+        assert_eq!(growth(2), None);
+        assert_eq!(growth(3), None);
+    }
+
+    #[test]
+    fn allocation_roles_are_trimmed_before_validation() {
+        let share = |role: &str| AllocationShare { bucket_role: role.to_string(), pc: 0.25 };
+        let mut rule = AllocationRule {
+            id: None,
+            tier: 1,
+            name: Some("Gifts".to_string()),
+            key_role: Some("  ".to_string()),
+            fill_below: None,
+            shares: vec![share("gift"), share("gift ")],
+        };
+        normalize_allocation_rule(&mut rule);
+        assert_eq!(rule.key_role, None);
+        assert_eq!(validate_allocation_rule(&rule), Err("Bucket role 'gift' appears twice".to_string()));
+
+        rule.shares = vec![share(" emergency "), share("gift")];
+        rule.key_role = Some(" emergency".to_string());
+        rule.fill_below = Some(0.5);
+        normalize_allocation_rule(&mut rule);
+        assert_eq!(rule.key_role.as_deref(), Some("emergency"));
+        assert_eq!(rule.shares[0].bucket_role, "emergency");
+        assert!(validate_allocation_rule(&rule).is_ok());
+    }
+}
@@ -0,0 +1,471 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use chrono::NaiveDate;
+use crate::db::DbConnection;
+use super::journal::ChangeScope;
+use super::rules::apply_categorization_rules;
+use super::statement_parsers::{parse_ofx, parse_qif, ParseContext};
+use super::transactions::{insert_transaction, Transaction};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CsvColumnMapping {
+    pub date_column: usize,
+    pub description_column: Option<usize>,
+    pub debit_column: Option<usize>,
+    pub credit_column: Option<usize>,
+    pub amount_column: Option<usize>, // Single signed column, negative = debit
+    pub date_format: Option<String>,  // chrono format, e.g. "%d/%m/%Y"
+    pub delimiter: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportRow {
+    pub line_number: usize,
+    pub date: String,
+    pub description: String,
+    pub amount: f64,
+    pub direction: String, // income, expense
+    pub category_id: i64,
+    pub is_duplicate: bool,
+    pub duplicate_of: Option<i64>,
+    pub selected: bool,
+    pub external_id: Option<String>,
+    pub matched_rule_id: Option<i64>, // Categorization rule that picked category_id
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub rows: Vec<ImportRow>,
+    pub skipped_lines: Vec<usize>,
+    pub duplicate_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub imported: usize,
+    pub skipped: usize,
+    pub transaction_ids: Vec<i64>,
+}
+
+// Column layouts of the statement downloads offered by each bank's netbanking
+fn bank_preset(bank_format: &str) -> Result<CsvColumnMapping, String> {
+    match bank_format {
+        // Date, Narration, Chq./Ref.No., Value Dt, Withdrawal Amt., Deposit Amt., Closing Balance
+        "hdfc" => Ok(CsvColumnMapping {
+            date_column: 0,
+            description_column: Some(1),
+            debit_column: Some(4),
+            credit_column: Some(5),
+            amount_column: None,
+            date_format: Some("%d/%m/%y".to_string()),
+            delimiter: None,
+        }),
+        // Txn Date, Value Date, Description, Ref No./Cheque No., Debit, Credit, Balance
+        "sbi" => Ok(CsvColumnMapping {
+            date_column: 0,
+            description_column: Some(2),
+            debit_column: Some(4),
+            credit_column: Some(5),
+            amount_column: None,
+            date_format: Some("%d %b %Y".to_string()),
+            delimiter: None,
+        }),
+        // S No., Value Date, Transaction Date, Cheque Number, Transaction Remarks, Withdrawal Amount, Deposit Amount, Balance
+        "icici" => Ok(CsvColumnMapping {
+            date_column: 2,
+            description_column: Some(4),
+            debit_column: Some(5),
+            credit_column: Some(6),
+            amount_column: None,
+            date_format: Some("%d/%m/%Y".to_string()),
+            delimiter: None,
+        }),
+        _ => Err(format!("Unknown bank format: {}", bank_format)),
+    }
+}
+
+/// Splits CSV content into records, honouring quoted fields (including
+/// embedded delimiters, newlines and doubled quotes).
+pub(crate) fn parse_csv_records(content: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            if ch == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                if ch == '\n' { line += 1; }
+                field.push(ch);
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    records
+}
+
+pub(crate) fn parse_import_date(value: &str, preferred_format: Option<&str>) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    // Two-digit year formats go first: "%Y" would happily read "24" as year 0024
+    let fallbacks = ["%Y-%m-%d", "%d/%m/%y", "%d/%m/%Y", "%d-%m-%y", "%d-%m-%Y", "%d %b %y", "%d %b %Y", "%d-%b-%y", "%d-%b-%Y", "%d.%m.%Y"];
+    preferred_format.into_iter().chain(fallbacks)
+        .find_map(|fmt| NaiveDate::parse_from_str(value, fmt).ok())
+        .map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+/// Parses bank-formatted amounts such as "1,24,500.00", "₹ 499", "(250.00)"
+/// or "1200.00 Dr". Returns a signed value where debits are negative.
+pub(crate) fn parse_import_amount(value: &str) -> Option<f64> {
+    let mut s = value.trim().to_string();
+    if s.is_empty() || s == "-" {
+        return None;
+    }
+
+    let mut sign = 1.0;
+    let upper = s.to_uppercase();
+    if upper.ends_with("DR") {
+        sign = -1.0;
+        s.truncate(s.len() - 2);
+    } else if upper.ends_with("CR") {
+        s.truncate(s.len() - 2);
+    }
+    if s.starts_with('(') && s.ends_with(')') {
+        sign = -sign;
+    }
+
+    let cleaned: String = s.chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+        .collect();
+    cleaned.parse::<f64>().ok().map(|v| v * sign)
+}
+
+fn find_by_external_id(conn: &rusqlite::Connection, account_id: i64, external_id: &str) -> Option<i64> {
+    conn.query_row(
+        "SELECT id FROM transactions
+         WHERE external_id = ?1 AND (from_account_id = ?2 OR to_account_id = ?2)
+         LIMIT 1",
+        params![external_id, account_id],
+        |row| row.get(0),
+    ).ok()
+}
+
+fn find_duplicate(conn: &rusqlite::Connection, account_id: i64, date: &str, amount: f64) -> Option<i64> {
+    conn.query_row(
+        "SELECT id FROM transactions
+         WHERE date = ?1 AND ABS(amount - ?2) < 0.005
+         AND (from_account_id = ?3 OR to_account_id = ?3)
+         ORDER BY id LIMIT 1",
+        params![date, amount, account_id],
+        |row| row.get(0),
+    ).ok()
+}
+
+fn row_to_transaction(account_id: i64, row: ImportRow) -> Transaction {
+    let is_income = row.direction == "income";
+    Transaction {
+        id: None,
+        date: row.date,
+        amount: row.amount,
+        direction: row.direction,
+        from_account_id: if is_income { None } else { Some(account_id) },
+        to_account_id: if is_income { Some(account_id) } else { None },
+        category_id: row.category_id,
+        client_id: None,
+        project_id: None,
+        investment_id: None,
+        goal_id: None,
+        notes: if row.description.is_empty() { None } else { Some(row.description) },
+        external_id: row.external_id,
+        currency: None,
+        to_amount: None,
+    }
+}
+
+// Shows the rule-picked category in the preview; commit saves the rows as the user left them
+fn apply_rules_to_rows(conn: &rusqlite::Connection, account_id: i64, rows: &mut [ImportRow]) -> Result<(), String> {
+    for row in rows.iter_mut() {
+        let mut transaction = row_to_transaction(account_id, row.clone());
+        let mut tag_ids = Vec::new();
+        row.matched_rule_id = apply_categorization_rules(conn, &mut transaction, &mut tag_ids)?;
+        row.category_id = transaction.category_id;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn preview_csv_import(
+    db: State<DbConnection>,
+    content: String,
+    account_id: i64,
+    bank_format: String,
+    mapping: Option<CsvColumnMapping>,
+    expense_category_id: i64,
+    income_category_id: i64,
+) -> Result<ImportPreview, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mapping = if bank_format == "custom" {
+        mapping.ok_or("Column mapping is required for custom CSV imports")?
+    } else {
+        bank_preset(&bank_format)?
+    };
+
+    if mapping.amount_column.is_none() && mapping.debit_column.is_none() && mapping.credit_column.is_none() {
+        return Err("Mapping needs an amount column or debit/credit columns".to_string());
+    }
+
+    let delimiter = mapping.delimiter.as_deref().and_then(|d| d.chars().next()).unwrap_or(',');
+    let cell = |record: &[String], idx: Option<usize>| -> String {
+        idx.and_then(|i| record.get(i)).map(|v| v.trim().to_string()).unwrap_or_default()
+    };
+
+    let mut rows = Vec::new();
+    let mut skipped_lines = Vec::new();
+
+    for (line_number, record) in parse_csv_records(&content, delimiter) {
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+
+        // Headers, bank preambles and summary footers have no parseable date
+        let date = match parse_import_date(&cell(&record, Some(mapping.date_column)), mapping.date_format.as_deref()) {
+            Some(d) => d,
+            None => {
+                skipped_lines.push(line_number);
+                continue;
+            }
+        };
+
+        let signed_amount = if let Some(col) = mapping.amount_column {
+            parse_import_amount(&cell(&record, Some(col)))
+        } else {
+            let debit = parse_import_amount(&cell(&record, mapping.debit_column)).map(f64::abs).unwrap_or(0.0);
+            let credit = parse_import_amount(&cell(&record, mapping.credit_column)).map(f64::abs).unwrap_or(0.0);
+            if debit > 0.0 { Some(-debit) } else if credit > 0.0 { Some(credit) } else { None }
+        };
+
+        let signed_amount = match signed_amount {
+            Some(a) if a != 0.0 => a,
+            _ => {
+                skipped_lines.push(line_number);
+                continue;
+            }
+        };
+
+        let (direction, category_id) = if signed_amount < 0.0 {
+            ("expense", expense_category_id)
+        } else {
+            ("income", income_category_id)
+        };
+        let amount = (signed_amount.abs() * 100.0).round() / 100.0;
+        let duplicate_of = find_duplicate(&conn, account_id, &date, amount);
+
+        rows.push(ImportRow {
+            line_number,
+            date,
+            description: cell(&record, mapping.description_column),
+            amount,
+            direction: direction.to_string(),
+            category_id,
+            is_duplicate: duplicate_of.is_some(),
+            duplicate_of,
+            selected: duplicate_of.is_none(),
+            external_id: None,
+            matched_rule_id: None,
+        });
+    }
+
+    apply_rules_to_rows(&conn, account_id, &mut rows)?;
+
+    let duplicate_count = rows.iter().filter(|r| r.is_duplicate).count();
+
+    Ok(ImportPreview {
+        rows,
+        skipped_lines,
+        duplicate_count,
+    })
+}
+
+#[tauri::command]
+pub fn preview_statement_import(
+    db: State<DbConnection>,
+    content: String,
+    file_format: String,
+    account_id: i64,
+    expense_category_id: i64,
+    income_category_id: i64,
+    date_format: Option<String>,
+) -> Result<ImportPreview, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let ctx = ParseContext {
+        account_id,
+        expense_category_id,
+        income_category_id,
+        date_format,
+    };
+
+    let parsed = match file_format.to_lowercase().as_str() {
+        "ofx" | "qfx" => parse_ofx(&content, &ctx)?,
+        "qif" => parse_qif(&content, &ctx)?,
+        other => return Err(format!("Unsupported statement format: {}", other)),
+    };
+
+    let mut rows = Vec::new();
+    for (line_number, t) in parsed.entries {
+        // A known external id is a certain duplicate; date + amount is only a likely one
+        let duplicate_of = t.external_id.as_deref()
+            .and_then(|ext| find_by_external_id(&conn, account_id, ext))
+            .or_else(|| find_duplicate(&conn, account_id, &t.date, t.amount));
+
+        rows.push(ImportRow {
+            line_number,
+            date: t.date,
+            description: t.notes.unwrap_or_default(),
+            amount: t.amount,
+            direction: t.direction,
+            category_id: t.category_id,
+            is_duplicate: duplicate_of.is_some(),
+            duplicate_of,
+            selected: duplicate_of.is_none(),
+            external_id: t.external_id,
+            matched_rule_id: None,
+        });
+    }
+
+    apply_rules_to_rows(&conn, account_id, &mut rows)?;
+
+    let duplicate_count = rows.iter().filter(|r| r.is_duplicate).count();
+
+    Ok(ImportPreview {
+        rows,
+        skipped_lines: parsed.skipped_lines,
+        duplicate_count,
+    })
+}
+
+#[tauri::command]
+pub fn commit_import(
+    db: State<DbConnection>,
+    account_id: i64,
+    rows: Vec<ImportRow>,
+) -> Result<ImportResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "commit_import")?;
+
+    let mut transaction_ids = Vec::new();
+    let mut skipped = 0;
+
+    for row in rows {
+        if !row.selected {
+            skipped += 1;
+            continue;
+        }
+
+        // Re-importing an overlapping statement must never double-post a known FITID
+        if let Some(ext) = row.external_id.as_deref() {
+            if find_by_external_id(&tx, account_id, ext).is_some() {
+                skipped += 1;
+                continue;
+            }
+        }
+
+        let transaction = row_to_transaction(account_id, row);
+        transaction_ids.push(insert_transaction(&tx, &transaction, &[], false)?);
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ImportResult {
+        imported: transaction_ids.len(),
+        skipped,
+        transaction_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(records: &[(usize, Vec<String>)]) -> Vec<(usize, Vec<&str>)> {
+        records.iter().map(|(line, r)| (*line, r.iter().map(String::as_str).collect())).collect()
+    }
+
+    #[test]
+    fn csv_quotes_and_line_numbers() {
+        let content = "\u{feff}date,notes\r\n\"x, y\",\"say \"\"hi\"\"\"\n\"multi\nline\",2\nlast,,3";
+        let records = parse_csv_records(content, ',');
+        assert_eq!(cells(&records), [
+            (1, vec!["date", "notes"]),
+            (2, vec!["x, y", "say \"hi\""]),
+            (3, vec!["multi\nline", "2"]),
+            (5, vec!["last", "", "3"]),
+        ]);
+    }
+
+    #[test]
+    fn csv_blank_lines_and_other_delimiters() {
+        let records = parse_csv_records("a;b\n\nc;\"d;e\"\n", ';');
+        assert_eq!(cells(&records), [(1, vec!["a", "b"]), (2, vec![""]), (3, vec!["c", "d;e"])]);
+        assert!(parse_csv_records("", ',').is_empty());
+    }
+
+    #[test]
+    fn import_amounts_in_bank_formats() {
+        assert_eq!(parse_import_amount("1,24,500.00"), Some(124500.0));
+        assert_eq!(parse_import_amount("₹ 499"), Some(499.0));
+        assert_eq!(parse_import_amount("(250.00)"), Some(-250.0));
+        assert_eq!(parse_import_amount("1200.00 Dr"), Some(-1200.0));
+        assert_eq!(parse_import_amount("1200.00 CR"), Some(1200.0));
+        assert_eq!(parse_import_amount(" -45.5 "), Some(-45.5));
+        assert_eq!(parse_import_amount("-"), None);
+        assert_eq!(parse_import_amount(""), None);
+        assert_eq!(parse_import_amount("n/a"), None);
+    }
+
+    #[test]
+    fn import_dates_fall_back_to_day_first() {
+        assert_eq!(parse_import_date("2026-01-31", None).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("31/01/26", None).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("31/01/2026", None).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("31 Jan 2026", None).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("31.01.2026", None).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("31/13/2026", None), None);
+    }
+}
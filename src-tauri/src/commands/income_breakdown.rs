@@ -2,6 +2,7 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CategoryHour {
@@ -67,6 +68,7 @@ pub fn get_category_hours(
 #[tauri::command]
 pub fn create_category_hour(db: State<DbConnection>, hour: CategoryHour) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_category_hour")?;
     conn.execute(
         "INSERT INTO category_hours (category_id, date, hours, notes) VALUES (?1, ?2, ?3, ?4)",
         params![hour.category_id, hour.date, hour.hours, hour.notes],
@@ -77,6 +79,7 @@ pub fn create_category_hour(db: State<DbConnection>, hour: CategoryHour) -> Resu
 #[tauri::command]
 pub fn update_category_hour(db: State<DbConnection>, hour: CategoryHour) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_category_hour")?;
     let id = hour.id.ok_or("ID is required")?;
     conn.execute(
         "UPDATE category_hours SET date = ?1, hours = ?2, notes = ?3 WHERE id = ?4",
@@ -88,6 +91,7 @@ pub fn update_category_hour(db: State<DbConnection>, hour: CategoryHour) -> Resu
 #[tauri::command]
 pub fn delete_category_hour(db: State<DbConnection>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_category_hour")?;
     conn.execute("DELETE FROM category_hours WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -119,7 +123,7 @@ pub fn get_income_breakdown(
 
         // 3. Get income from transactions for this category (non-project)
         let manual_income: f64 = conn.query_row(
-            "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE category_id = ?1 AND project_id IS NULL AND direction = 'income' AND date >= ?2 AND date <= ?3",
+            "SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts WHERE category_id = ?1 AND project_id IS NULL AND direction = 'income' AND date >= ?2 AND date <= ?3",
             params![cat_id, start_date, end_date],
             |row| row.get(0)
         ).unwrap_or(0.0);
@@ -151,7 +155,7 @@ pub fn get_income_breakdown(
 
         for (p_id, p_name) in related_projects {
             let p_income: f64 = conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE project_id = ?1 AND category_id = ?2 AND direction = 'income' AND date >= ?3 AND date <= ?4",
+                "SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts WHERE project_id = ?1 AND category_id = ?2 AND direction = 'income' AND date >= ?3 AND date <= ?4",
                 params![p_id, cat_id, start_date, end_date],
                 |row| row.get(0)
             ).unwrap_or(0.0);
@@ -2,6 +2,8 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::balances::account_balance_as_of;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InvestmentLot {
@@ -198,13 +200,13 @@ pub fn get_investments_summary(db: State<DbConnection>) -> Result<Vec<Investment
 
             // Include transfers and extra expenses from transactions (legacy/manual)
             let total_transfers: f64 = conn.query_row(
-                "SELECT COALESCE(ROUND(SUM(amount), 2), 0) FROM transactions WHERE investment_id = ?1 AND direction = 'transfer'",
+                "SELECT COALESCE(ROUND(SUM(base_amount), 2), 0) FROM transaction_amounts WHERE investment_id = ?1 AND direction = 'transfer'",
                 [inv_id],
                 |r| r.get(0)
             ).unwrap_or(0.0);
 
             let total_expenses_tx: f64 = conn.query_row(
-                "SELECT COALESCE(ROUND(SUM(amount), 2), 0) FROM transactions WHERE investment_id = ?1 AND direction = 'expense'",
+                "SELECT COALESCE(ROUND(SUM(base_amount), 2), 0) FROM transaction_amounts WHERE investment_id = ?1 AND direction = 'expense'",
                 [inv_id],
                 |r| r.get(0)
             ).unwrap_or(0.0);
@@ -257,6 +259,7 @@ pub fn get_investments_summary(db: State<DbConnection>) -> Result<Vec<Investment
 #[tauri::command]
 pub fn create_investment(db: State<DbConnection>, investment: Investment) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_investment")?;
     
     conn.execute(
         "INSERT INTO investments (name, type, account_id, units, avg_buy_price, current_price, principal_amount, interest_rate, maturity_date, maturity_amount, monthly_deposit, notes, provider_symbol, principal_charges, tenure_months, opening_date, compounding, bank_name, category_id)
@@ -291,6 +294,7 @@ pub fn create_investment(db: State<DbConnection>, investment: Investment) -> Res
 #[tauri::command]
 pub fn update_investment(db: State<DbConnection>, investment: Investment) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_investment")?;
     let id = investment.id.ok_or("Investment ID is required")?;
     
     conn.execute(
@@ -328,18 +332,22 @@ pub fn update_investment(db: State<DbConnection>, investment: Investment) -> Res
 
 #[tauri::command]
 pub fn delete_investment(db: State<DbConnection>, id: i64) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_investment")?;
     
     // First clear references and dependent data
-    conn.execute("UPDATE transactions SET investment_id = NULL WHERE investment_id = ?1", [id])
+    tx.execute("UPDATE transactions SET investment_id = NULL WHERE investment_id = ?1", [id])
         .map_err(|e| e.to_string())?;
         
-    conn.execute("DELETE FROM investment_lots WHERE investment_id = ?1", [id])
+    tx.execute("DELETE FROM investment_lots WHERE investment_id = ?1", [id])
         .map_err(|e| e.to_string())?;
         
-    conn.execute("DELETE FROM investments WHERE id = ?1", [id])
+    tx.execute("DELETE FROM investments WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
-        
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -355,25 +363,14 @@ pub fn get_investment_platform_summary(db: State<DbConnection>) -> Result<Vec<Pl
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
-        "SELECT id, name, opening_balance FROM accounts WHERE type = 'investment' AND is_investment_active = 1"
+        "SELECT id, name, opening_balance FROM accounts WHERE type = 'investment' AND is_investment_active = 1 AND closed_on IS NULL"
     ).map_err(|e| e.to_string())?;
     
     let accounts_iter = stmt.query_map([], |row| {
         let id: i64 = row.get(0)?;
         let name: String = row.get(1)?;
         let opening_balance: f64 = row.get(2)?;
-        
-        let incoming: f64 = conn.query_row(
-            "SELECT COALESCE(ROUND(SUM(amount), 2), 0) FROM transactions WHERE to_account_id = ?1",
-            [id],
-            |r| r.get(0)
-        ).unwrap_or(0.0);
-        
-        let outgoing: f64 = conn.query_row(
-            "SELECT COALESCE(ROUND(SUM(amount), 2), 0) FROM transactions WHERE from_account_id = ?1",
-            [id],
-            |r| r.get(0)
-        ).unwrap_or(0.0);
+        let account_balance = account_balance_as_of(&conn, id, None).unwrap_or(opening_balance);
 
         let allocated: f64 = conn.query_row(
             "SELECT COALESCE(SUM(ROUND(l.quantity * l.price_per_unit, 2) + l.charges), 0)
@@ -387,7 +384,7 @@ pub fn get_investment_platform_summary(db: State<DbConnection>) -> Result<Vec<Pl
         Ok(PlatformBalance {
             account_id: id,
             name,
-            balance: ((opening_balance + incoming - outgoing - allocated) * 100.0).round() / 100.0,
+            balance: ((account_balance - allocated) * 100.0).round() / 100.0,
         })
     }).map_err(|e| e.to_string())?;
     
@@ -401,6 +398,7 @@ pub fn get_investment_platform_summary(db: State<DbConnection>) -> Result<Vec<Pl
 #[tauri::command]
 pub fn add_investment_lot(db: State<DbConnection>, lot: InvestmentLot) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "add_investment_lot")?;
     
     conn.execute(
         "INSERT INTO investment_lots (investment_id, quantity, price_per_unit, charges, date, lot_type)
@@ -422,6 +420,7 @@ pub fn add_investment_lot(db: State<DbConnection>, lot: InvestmentLot) -> Result
 #[tauri::command]
 pub fn update_investment_lot(db: State<DbConnection>, lot: InvestmentLot) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_investment_lot")?;
     let id = lot.id.ok_or("Lot ID is required")?;
     
     conn.execute(
@@ -443,6 +442,7 @@ pub fn update_investment_lot(db: State<DbConnection>, lot: InvestmentLot) -> Res
 #[tauri::command]
 pub fn delete_investment_lot(db: State<DbConnection>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_investment_lot")?;
     conn.execute("DELETE FROM investment_lots WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
     Ok(())
@@ -534,9 +534,11 @@ pub async fn sync_investment_prices(db: State<'_, DbConnection>, force: bool) ->
 
 #[tauri::command]
 pub fn update_fixed_income_daily(db: State<DbConnection>) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_fixed_income_daily")?;
     
-    let mut stmt = conn.prepare(
+    let mut stmt = tx.prepare(
         "SELECT id, name, type, principal_amount, interest_rate, opening_date, monthly_deposit, tenure_months, compounding, current_price, last_updated_at 
          FROM investments 
          WHERE type IN ('fd', 'rd')"
@@ -556,12 +558,15 @@ pub fn update_fixed_income_daily(db: State<DbConnection>) -> Result<(), String>
             row.get::<_, Option<f64>>(9)?, // current_price
             row.get::<_, Option<String>>(10)?, // last_updated
         ))
-    }).map_err(|e| e.to_string())?;
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    drop(stmt);
     
     let now = chrono::Local::now();
     
     for inv in investments {
-        let (id, _name, _inv_type, _principal, rate, _opening_date, _monthly_deposit, _tenure, compounding, _current_price, _last_updated) = inv.map_err(|e| e.to_string())?;
+        let (id, _name, _inv_type, _principal, rate, _opening_date, _monthly_deposit, _tenure, compounding, _current_price, _last_updated) = inv;
         
         let rate = rate.unwrap_or(0.0) / 100.0;
         let compounding_freq = match compounding.as_deref() {
@@ -573,7 +578,7 @@ pub fn update_fixed_income_daily(db: State<DbConnection>) -> Result<(), String>
         let mut total_valuation = 0.0;
 
         // 1. Fetch all lots for this investment
-        let mut stmt_lots = conn.prepare("SELECT quantity, price_per_unit, date FROM investment_lots WHERE investment_id = ?1").map_err(|e| e.to_string())?;
+        let mut stmt_lots = tx.prepare("SELECT quantity, price_per_unit, date FROM investment_lots WHERE investment_id = ?1").map_err(|e| e.to_string())?;
         let lots_iter = stmt_lots.query_map(params![id], |row| {
             Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?, row.get::<_, String>(2)?))
         }).map_err(|e| e.to_string())?;
@@ -600,7 +605,7 @@ pub fn update_fixed_income_daily(db: State<DbConnection>) -> Result<(), String>
         }
 
         // 2. Fetch all transactions for this investment (e.g. SIP installments logged as transactions)
-        let mut stmt_txs = conn.prepare("SELECT amount, date FROM transactions WHERE investment_id = ?1 AND direction = 'transfer'").map_err(|e| e.to_string())?;
+        let mut stmt_txs = tx.prepare("SELECT amount, date FROM transactions WHERE investment_id = ?1 AND direction = 'transfer'").map_err(|e| e.to_string())?;
         let txs_iter = stmt_txs.query_map(params![id], |row| {
             Ok((row.get::<_, f64>(0)?, row.get::<_, String>(1)?))
         }).map_err(|e| e.to_string())?;
@@ -628,13 +633,15 @@ pub fn update_fixed_income_daily(db: State<DbConnection>) -> Result<(), String>
 
         if total_valuation > 0.0 {
             let now_ts = now.format("%Y-%m-%d %H:%M:%S").to_string();
-            conn.execute(
+            tx.execute(
                 "UPDATE investments SET current_price = ?1, last_updated_at = ?2 WHERE id = ?3",
                 params![total_valuation, now_ts, id]
             ).map_err(|e| e.to_string())?;
         }
     }
     
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -756,15 +763,19 @@ pub fn get_investment_benchmark(db: State<DbConnection>) -> Result<Option<Invest
 
 #[tauri::command]
 pub fn set_investment_benchmark(db: State<DbConnection>, target_amount: f64, start_date: String) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_investment_benchmark")?;
     
     // We only keep one active benchmark for simplicity, or just update the latest
-    conn.execute("DELETE FROM investment_benchmarks", []).map_err(|e| e.to_string())?;
-    conn.execute(
+    tx.execute("DELETE FROM investment_benchmarks", []).map_err(|e| e.to_string())?;
+    tx.execute(
         "INSERT INTO investment_benchmarks (target_amount, start_date) VALUES (?1, ?2)",
         params![target_amount, start_date]
     ).map_err(|e| e.to_string())?;
-    
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -827,8 +838,8 @@ pub fn get_investment_benchmark_report(db: State<DbConnection>) -> Result<Invest
             -- Exclude transactions that are going INTO an investment account (type='investment')
             -- because those are already tracked via Lots in Source A.
             SELECT strftime('%Y-%m', t.date) as mth,
-                   ROUND(SUM(t.amount), 2) as total
-            FROM transactions t
+                   ROUND(SUM(t.base_amount), 2) as total
+            FROM transaction_amounts t
             JOIN categories c ON t.category_id = c.id
             LEFT JOIN accounts acc_to ON t.to_account_id = acc_to.id
             WHERE COALESCE(c.is_investment, 0) = 1
@@ -0,0 +1,656 @@
+use rusqlite::{params, OptionalExtension, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use tauri::State;
+use crate::db::DbConnection;
+use super::search::refresh_search_index;
+use super::transactions::sync_goal_progress;
+
+// Tables whose row changes are journaled. Link tables without an `id` column
+// are identified by their full row contents.
+pub(crate) const JOURNALED_TABLES: &[&str] = &[
+    "accounts",
+    "categories",
+    "clients",
+    "projects",
+    "tags",
+    "transactions",
+    "transaction_tags",
+    "transaction_splits",
+    "transaction_split_tags",
+    "scheduled_transactions",
+    "budgets",
+    "budget_moves",
+    "budget_settings",
+    "monthly_income",
+    "goals",
+    "goal_status_history",
+    "allocation_rules",
+    "allocation_tiers",
+    "allocation_tier_shares",
+    "allocation_batches",
+    "bucket_allocation_settings",
+    "category_hours",
+    "investments",
+    "investment_lots",
+    "investment_benchmarks",
+    "time_logs",
+    "categorization_rules",
+    "categorization_rule_tags",
+    "account_statements",
+    "fx_rates",
+    "fx_settings",
+    "card_statements",
+    "loans",
+    "loan_payments",
+    "balance_assertions",
+    "attachment_files",
+    "attachments",
+];
+
+// Columns maintained by background recalculation (goal sync, price refresh).
+// Reverts leave them alone and they never count as a conflicting later edit.
+// A goal's status is not one of them: completion sticks, so a revert must
+// restore it along with the status history.
+const DERIVED_COLUMNS: &[(&str, &[&str])] = &[
+    ("goals", &["current_amount"]),
+    ("investments", &["current_price", "last_updated_at"]),
+];
+
+#[derive(Debug, Serialize)]
+pub struct ChangeEntry {
+    pub entity: String,
+    pub entity_id: Option<i64>,
+    pub action: String, // insert, update, delete
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeRecord {
+    pub id: i64,
+    pub command: String,
+    pub created_at: String,
+    pub reverts_change_id: Option<i64>,
+    pub reverted_by: Option<i64>,
+    pub entries: Vec<ChangeEntry>,
+}
+
+/// Groups every journaled row change made while it is alive into one change
+/// entry attributed to `command`. Bind it to a named variable (`_change`) so
+/// it lives until the command returns.
+pub(crate) struct ChangeScope<'a> {
+    conn: &'a rusqlite::Connection,
+    change_id: i64,
+}
+
+impl<'a> ChangeScope<'a> {
+    pub(crate) fn begin(conn: &'a rusqlite::Connection, command: &str) -> Result<Self, String> {
+        Self::open(conn, command, None)
+    }
+
+    fn open(conn: &'a rusqlite::Connection, command: &str, reverts_change_id: Option<i64>) -> Result<Self, String> {
+        conn.execute(
+            "INSERT INTO change_journal (command, reverts_change_id) VALUES (?1, ?2)",
+            params![command, reverts_change_id],
+        ).map_err(|e| e.to_string())?;
+        let change_id = conn.last_insert_rowid();
+
+        conn.execute("DELETE FROM temp.active_change", []).map_err(|e| e.to_string())?;
+        conn.execute("INSERT INTO temp.active_change (change_id) VALUES (?1)", [change_id])
+            .map_err(|e| e.to_string())?;
+
+        Ok(ChangeScope { conn, change_id })
+    }
+}
+
+impl Drop for ChangeScope<'_> {
+    fn drop(&mut self) {
+        let _ = self.conn.execute("DELETE FROM temp.active_change", []);
+        // Commands that ended up changing nothing leave no journal entry
+        let _ = self.conn.execute(
+            "DELETE FROM change_journal WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM change_journal_entries WHERE change_id = ?1)",
+            [self.change_id],
+        );
+    }
+}
+
+fn table_columns(conn: &rusqlite::Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA main.table_info(\"{}\")", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(columns)
+}
+
+// json_object('a', <prefix>"a", ...) over every column of the table
+fn row_json_expr(columns: &[String], prefix: &str) -> String {
+    let pairs: Vec<String> = columns
+        .iter()
+        .map(|c| format!("'{}', {}\"{}\"", c, prefix, c))
+        .collect();
+    format!("json_object({})", pairs.join(", "))
+}
+
+fn derived_columns(table: &str) -> &'static [&'static str] {
+    DERIVED_COLUMNS
+        .iter()
+        .find(|(t, _)| *t == table)
+        .map(|(_, cols)| *cols)
+        .unwrap_or(&[])
+}
+
+/// Installs the per-connection triggers that feed the journal. They only
+/// record while a `ChangeScope` is open, so migrations and background price
+/// refreshes stay out of the history.
+pub(crate) fn install_journal_triggers(conn: &rusqlite::Connection) -> Result<()> {
+    // INSERT OR REPLACE must journal the row it replaces
+    conn.execute_batch(
+        "PRAGMA recursive_triggers = ON;
+         CREATE TEMP TABLE IF NOT EXISTS active_change (change_id INTEGER NOT NULL);"
+    )?;
+
+    for table in JOURNALED_TABLES {
+        let columns = table_columns(conn, table)?;
+        if columns.is_empty() {
+            continue;
+        }
+        let has_id = columns.iter().any(|c| c == "id");
+        let id_of = |prefix: &str| if has_id { format!("{}id", prefix) } else { "NULL".to_string() };
+        let old_json = row_json_expr(&columns, "OLD.");
+        let new_json = row_json_expr(&columns, "NEW.");
+        // Updates that only touch derived columns (price refreshes) are not changes
+        let compared: Vec<String> = columns.iter().filter(|c| !derived_columns(table).contains(&c.as_str())).cloned().collect();
+        let old_compared = row_json_expr(&compared, "OLD.");
+        let new_compared = row_json_expr(&compared, "NEW.");
+
+        conn.execute_batch(&format!(
+            "CREATE TEMP TRIGGER IF NOT EXISTS journal_{t}_insert AFTER INSERT ON main.{t}
+             BEGIN
+                 INSERT INTO change_journal_entries (change_id, entity, entity_id, action, before_json, after_json)
+                 SELECT change_id, '{t}', {new_id}, 'insert', NULL, {new_json} FROM temp.active_change;
+             END;
+             CREATE TEMP TRIGGER IF NOT EXISTS journal_{t}_update AFTER UPDATE ON main.{t}
+             WHEN {old_compared} IS NOT {new_compared}
+             BEGIN
+                 INSERT INTO change_journal_entries (change_id, entity, entity_id, action, before_json, after_json)
+                 SELECT change_id, '{t}', {new_id}, 'update', {old_json}, {new_json} FROM temp.active_change;
+             END;
+             CREATE TEMP TRIGGER IF NOT EXISTS journal_{t}_delete AFTER DELETE ON main.{t}
+             BEGIN
+                 INSERT INTO change_journal_entries (change_id, entity, entity_id, action, before_json, after_json)
+                 SELECT change_id, '{t}', {old_id}, 'delete', {old_json}, NULL FROM temp.active_change;
+             END;",
+            t = table,
+            new_id = id_of("NEW."),
+            old_id = id_of("OLD."),
+            old_json = old_json,
+            new_json = new_json,
+            old_compared = old_compared,
+            new_compared = new_compared,
+        ))?;
+    }
+
+    Ok(())
+}
+
+struct JournalEntry {
+    entity: String,
+    entity_id: Option<i64>,
+    action: String,
+    before_json: Option<String>,
+    after_json: Option<String>,
+}
+
+fn load_entries(conn: &rusqlite::Connection, change_id: i64) -> Result<Vec<JournalEntry>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT entity, entity_id, action, before_json, after_json FROM change_journal_entries WHERE change_id = ?1 ORDER BY id"
+    ).map_err(|e| e.to_string())?;
+    let entries = stmt.query_map([change_id], |row| {
+        Ok(JournalEntry {
+            entity: row.get(0)?,
+            entity_id: row.get(1)?,
+            action: row.get(2)?,
+            before_json: row.get(3)?,
+            after_json: row.get(4)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+    Ok(entries)
+}
+
+fn without_derived(table: &str, json: &str) -> Result<serde_json::Value, String> {
+    let mut value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if let Some(obj) = value.as_object_mut() {
+        for col in derived_columns(table) {
+            obj.remove(*col);
+        }
+    }
+    Ok(value)
+}
+
+// A revert is only safe while each touched row is still exactly as the change left it
+fn check_unchanged_since(conn: &rusqlite::Connection, entry: &JournalEntry) -> Result<bool, String> {
+    if !JOURNALED_TABLES.contains(&entry.entity.as_str()) {
+        return Err(format!("Unknown journal entity: {}", entry.entity));
+    }
+    let columns = table_columns(conn, &entry.entity).map_err(|e| e.to_string())?;
+    let json_expr = row_json_expr(&columns, "");
+
+    match entry.entity_id {
+        Some(id) => {
+            let current: Option<String> = conn.query_row(
+                &format!("SELECT {} FROM {} WHERE id = ?1", json_expr, entry.entity),
+                [id],
+                |r| r.get(0),
+            ).optional().map_err(|e| e.to_string())?;
+
+            match (current, entry.after_json.as_deref()) {
+                (None, None) => Ok(true),
+                (Some(now), Some(expected)) => Ok(without_derived(&entry.entity, &now)? == without_derived(&entry.entity, expected)?),
+                _ => Ok(false),
+            }
+        }
+        None => {
+            let row = entry.after_json.as_deref().or(entry.before_json.as_deref()).unwrap_or("{}");
+            let exists: bool = conn.query_row(
+                &format!("SELECT EXISTS (SELECT 1 FROM {} WHERE {} = json(?1))", entry.entity, json_expr),
+                [row],
+                |r| r.get(0),
+            ).map_err(|e| e.to_string())?;
+            Ok(exists == entry.after_json.is_some())
+        }
+    }
+}
+
+fn delete_row(conn: &rusqlite::Connection, table: &str, columns: &[String], entity_id: Option<i64>, row_json: &str) -> Result<(), String> {
+    match entity_id {
+        Some(id) => conn.execute(&format!("DELETE FROM {} WHERE id = ?1", table), [id]),
+        None => conn.execute(
+            &format!("DELETE FROM {t} WHERE rowid IN (SELECT rowid FROM {t} WHERE {} = json(?1) LIMIT 1)", row_json_expr(columns, ""), t = table),
+            [row_json],
+        ),
+    }.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn insert_row(conn: &rusqlite::Connection, table: &str, columns: &[String], row_json: &str) -> Result<(), String> {
+    let names: Vec<String> = columns.iter().map(|c| format!("\"{}\"", c)).collect();
+    let values: Vec<String> = columns.iter().map(|c| format!("json_extract(?1, '$.{}')", c)).collect();
+    conn.execute(
+        &format!("INSERT OR REPLACE INTO {} ({}) SELECT {}", table, names.join(", "), values.join(", ")),
+        [row_json],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn restore_row(conn: &rusqlite::Connection, table: &str, columns: &[String], id: i64, row_json: &str) -> Result<(), String> {
+    let derived = derived_columns(table);
+    let sets: Vec<String> = columns
+        .iter()
+        .filter(|c| c.as_str() != "id" && !derived.contains(&c.as_str()))
+        .map(|c| format!("\"{}\" = json_extract(?1, '$.{}')", c, c))
+        .collect();
+    conn.execute(
+        &format!("UPDATE {} SET {} WHERE id = ?2", table, sets.join(", ")),
+        params![row_json, id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn json_field(json: Option<&str>, field: &str) -> Option<i64> {
+    json.and_then(|j| serde_json::from_str::<serde_json::Value>(j).ok())
+        .and_then(|v| v.get(field).and_then(|f| f.as_i64()))
+}
+
+/// Restores every row touched by `change_id` to its prior state, as a new
+/// journaled change. Returns the id of that new change.
+fn revert_change_in(conn: &rusqlite::Connection, change_id: i64, command: &str) -> Result<i64, String> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM change_journal WHERE id = ?1)",
+        [change_id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!("Change {} not found", change_id));
+    }
+
+    let reverted_by: Option<i64> = conn.query_row(
+        "SELECT id FROM change_journal WHERE reverts_change_id = ?1",
+        [change_id],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+    if let Some(by) = reverted_by {
+        return Err(format!("Change {} was already reverted by change {}", change_id, by));
+    }
+
+    let entries = load_entries(conn, change_id)?;
+
+    // Only the last entry per row describes the state the change left behind
+    let mut seen = HashSet::new();
+    for entry in entries.iter().rev() {
+        let key = match entry.entity_id {
+            Some(id) => (entry.entity.clone(), id.to_string()),
+            None => (entry.entity.clone(), entry.after_json.clone().or(entry.before_json.clone()).unwrap_or_default()),
+        };
+        if seen.insert(key) && !check_unchanged_since(conn, entry)? {
+            return Err(format!(
+                "A later change modified {} {}; revert that change first",
+                entry.entity,
+                entry.entity_id.map(|id| id.to_string()).unwrap_or_default()
+            ));
+        }
+    }
+
+    // Rows come back parent-first or child-first depending on the original order
+    conn.execute_batch("PRAGMA defer_foreign_keys = ON").map_err(|e| e.to_string())?;
+
+    let scope = ChangeScope::open(conn, command, Some(change_id))?;
+    let mut transaction_ids = HashSet::new();
+    let mut goal_ids = HashSet::new();
+
+    for entry in entries.iter().rev() {
+        let columns = table_columns(conn, &entry.entity).map_err(|e| e.to_string())?;
+        let before = entry.before_json.as_deref();
+        let after = entry.after_json.as_deref();
+
+        match (entry.action.as_str(), entry.entity_id) {
+            ("insert", _) => delete_row(conn, &entry.entity, &columns, entry.entity_id, after.unwrap_or("{}"))?,
+            ("delete", _) => insert_row(conn, &entry.entity, &columns, before.unwrap_or("{}"))?,
+            ("update", Some(id)) => restore_row(conn, &entry.entity, &columns, id, before.unwrap_or("{}"))?,
+            ("update", None) => {
+                delete_row(conn, &entry.entity, &columns, None, after.unwrap_or("{}"))?;
+                insert_row(conn, &entry.entity, &columns, before.unwrap_or("{}"))?;
+            }
+            (other, _) => return Err(format!("Unknown journal action: {}", other)),
+        }
+
+        match entry.entity.as_str() {
+            "transactions" => {
+                transaction_ids.extend(entry.entity_id);
+                goal_ids.extend(json_field(before, "goal_id"));
+                goal_ids.extend(json_field(after, "goal_id"));
+            }
+            "transaction_tags" | "transaction_splits" => {
+                transaction_ids.extend(json_field(before.or(after), "transaction_id"));
+            }
+            "goals" => goal_ids.extend(entry.entity_id),
+            _ => {}
+        }
+    }
+
+    for id in transaction_ids {
+        refresh_search_index(conn, id)?;
+    }
+    for gid in goal_ids {
+        let goal_exists: bool = conn
+            .query_row("SELECT EXISTS (SELECT 1 FROM goals WHERE id = ?1)", [gid], |r| r.get(0))
+            .map_err(|e| e.to_string())?;
+        if goal_exists {
+            sync_goal_progress(conn, gid)?;
+        }
+    }
+
+    let new_change_id = scope.change_id;
+    drop(scope);
+    Ok(new_change_id)
+}
+
+#[tauri::command]
+pub fn get_change_journal(db: State<DbConnection>, limit: Option<i64>) -> Result<Vec<ChangeRecord>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT c.id, c.command, c.created_at, c.reverts_change_id,
+               (SELECT r.id FROM change_journal r WHERE r.reverts_change_id = c.id)
+        FROM change_journal c
+        ORDER BY c.id DESC
+        LIMIT ?1
+    ").map_err(|e| e.to_string())?;
+
+    let mut changes = stmt.query_map([limit.unwrap_or(100)], |row| {
+        Ok(ChangeRecord {
+            id: row.get(0)?,
+            command: row.get(1)?,
+            created_at: row.get(2)?,
+            reverts_change_id: row.get(3)?,
+            reverted_by: row.get(4)?,
+            entries: Vec::new(),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    for change in &mut changes {
+        for entry in load_entries(&conn, change.id)? {
+            change.entries.push(ChangeEntry {
+                entity: entry.entity,
+                entity_id: entry.entity_id,
+                action: entry.action,
+                before: entry.before_json.and_then(|j| serde_json::from_str(&j).ok()),
+                after: entry.after_json.and_then(|j| serde_json::from_str(&j).ok()),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+#[tauri::command]
+pub fn revert_change(db: State<DbConnection>, change_id: i64) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let new_change_id = revert_change_in(&tx, change_id, "revert_change")?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(new_change_id)
+}
+
+/// Reverts the most recent change that has not been undone yet. Repeated calls
+/// walk further back; reverts themselves are skipped. Returns None when there
+/// is nothing left to undo.
+#[tauri::command]
+pub fn undo_last_change(db: State<DbConnection>) -> Result<Option<i64>, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let last: Option<i64> = conn.query_row(
+        "SELECT c.id FROM change_journal c
+         WHERE c.reverts_change_id IS NULL
+         AND NOT EXISTS (SELECT 1 FROM change_journal r WHERE r.reverts_change_id = c.id)
+         ORDER BY c.id DESC LIMIT 1",
+        [],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+
+    let change_id = match last {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let new_change_id = revert_change_in(&tx, change_id, "undo_last_change")?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(Some(new_change_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::splits::{set_transaction_splits, TransactionSplit};
+    use crate::commands::transactions::{
+        create_transaction, delete_transaction, insert_transaction, update_transaction, Transaction,
+    };
+    use std::sync::Mutex;
+
+    fn goal_state(conn: &rusqlite::Connection) -> (String, f64, i64) {
+        conn.query_row(
+            "SELECT status, current_amount, (SELECT COUNT(*) FROM goal_status_history WHERE goal_id = 1 AND status = 'completed') FROM goals WHERE id = 1",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        ).unwrap()
+    }
+
+    #[test]
+    fn undo_restores_goal_completed_by_a_transaction() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 5000), (2, 'Trip', 'bank', 0);
+             INSERT INTO categories (id, name, kind) VALUES (1, 'Savings', 'expense');
+             INSERT INTO goals (id, bucket_id, name, target_amount, status) VALUES (1, 2, 'Trip', 1000, 'active');",
+        ).unwrap();
+
+        let change_id = {
+            let scope = ChangeScope::begin(&conn, "create_transaction").unwrap();
+            insert_transaction(&conn, &Transaction {
+                id: None,
+                date: "2026-03-01".to_string(),
+                amount: 1000.0,
+                direction: "transfer".to_string(),
+                from_account_id: Some(1),
+                to_account_id: Some(2),
+                category_id: 1,
+                client_id: None,
+                project_id: None,
+                investment_id: None,
+                goal_id: Some(1),
+                notes: None,
+                external_id: None,
+                currency: None,
+                to_amount: None,
+            }, &[], false).unwrap();
+            scope.change_id
+        };
+        assert_eq!(goal_state(&conn), ("completed".to_string(), 1000.0, 1));
+
+        revert_change_in(&conn, change_id, "revert_change").unwrap();
+        assert_eq!(goal_state(&conn), ("active".to_string(), 0.0, 0));
+    }
+
+    type Snapshot = (Option<(f64, Option<String>)>, Vec<i64>, Vec<(i64, f64, Vec<i64>)>);
+
+    fn transaction_state(db: &DbConnection, id: i64) -> Snapshot {
+        let conn = db.0.lock().unwrap();
+        let row = conn.query_row(
+            "SELECT amount, notes FROM transactions WHERE id = ?1",
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        ).optional().unwrap();
+        let tags = conn.prepare("SELECT tag_id FROM transaction_tags WHERE transaction_id = ?1 ORDER BY tag_id").unwrap()
+            .query_map([id], |r| r.get(0)).unwrap()
+            .collect::<Result<Vec<i64>, _>>().unwrap();
+        let splits = conn.prepare(
+            "SELECT s.category_id, s.amount,
+                    (SELECT GROUP_CONCAT(tag_id) FROM (SELECT tag_id FROM transaction_split_tags WHERE split_id = s.id ORDER BY tag_id))
+             FROM transaction_splits s WHERE s.transaction_id = ?1 ORDER BY s.category_id",
+        ).unwrap()
+            .query_map([id], |r| {
+                let tags: Option<String> = r.get(2)?;
+                let tags = tags.map(|t| t.split(',').map(|v| v.parse().unwrap()).collect()).unwrap_or_default();
+                Ok((r.get(0)?, r.get(1)?, tags))
+            }).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap();
+        (row, tags, splits)
+    }
+
+    fn split(category_id: i64, amount: f64, tag_ids: Vec<i64>) -> TransactionSplit {
+        TransactionSplit {
+            id: None,
+            transaction_id: None,
+            category_id,
+            amount,
+            client_id: None,
+            project_id: None,
+            notes: None,
+            tag_ids,
+        }
+    }
+
+    /// A bank expense of 100 tagged 1, split 60 (tag 2) / 40.
+    fn split_expense(db: &DbConnection) -> i64 {
+        db.0.lock().unwrap().execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 500);
+             INSERT INTO categories (id, name, kind) VALUES (1, 'Food', 'expense'), (2, 'Home', 'expense');
+             INSERT INTO tags (id, name) VALUES (1, 'family'), (2, 'work'), (3, 'trip');",
+        ).unwrap();
+
+        let id = create_transaction(State::from_ref(db), Transaction {
+            id: None,
+            date: "2026-03-01".to_string(),
+            amount: 100.0,
+            direction: "expense".to_string(),
+            from_account_id: Some(1),
+            to_account_id: None,
+            category_id: 1,
+            client_id: None,
+            project_id: None,
+            investment_id: None,
+            goal_id: None,
+            notes: Some("groceries".to_string()),
+            external_id: None,
+            currency: None,
+            to_amount: None,
+        }, vec![1]).unwrap();
+        set_transaction_splits(State::from_ref(db), id, vec![split(1, 60.0, vec![2]), split(2, 40.0, vec![])]).unwrap();
+        id
+    }
+
+    fn test_db() -> DbConnection {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        DbConnection(Mutex::new(conn))
+    }
+
+    #[test]
+    fn revert_of_delete_restores_transaction_tags_and_splits() {
+        let db = test_db();
+        let id = split_expense(&db);
+        let before = transaction_state(&db, id);
+        assert_eq!(before.2.len(), 2);
+
+        delete_transaction(State::from_ref(&db), id).unwrap();
+        assert_eq!(transaction_state(&db, id), (None, vec![], vec![]));
+
+        let change_id: i64 = db.0.lock().unwrap()
+            .query_row("SELECT MAX(id) FROM change_journal WHERE command = 'delete_transaction'", [], |r| r.get(0))
+            .unwrap();
+        revert_change(State::from_ref(&db), change_id).unwrap();
+        assert_eq!(transaction_state(&db, id), before);
+    }
+
+    #[test]
+    fn undo_of_update_restores_transaction_tags_and_splits() {
+        let db = test_db();
+        let id = split_expense(&db);
+        let before = transaction_state(&db, id);
+
+        update_transaction(State::from_ref(&db), Transaction {
+            id: Some(id),
+            date: "2026-03-01".to_string(),
+            amount: 100.0,
+            direction: "expense".to_string(),
+            from_account_id: Some(1),
+            to_account_id: None,
+            category_id: 1,
+            client_id: None,
+            project_id: None,
+            investment_id: None,
+            goal_id: None,
+            notes: Some("weekly shop".to_string()),
+            external_id: None,
+            currency: None,
+            to_amount: None,
+        }, vec![3]).unwrap();
+        set_transaction_splits(State::from_ref(&db), id, vec![split(1, 70.0, vec![3]), split(2, 30.0, vec![1])]).unwrap();
+        assert_eq!(
+            transaction_state(&db, id),
+            (Some((100.0, Some("weekly shop".to_string()))), vec![3], vec![(1, 70.0, vec![3]), (2, 30.0, vec![1])]),
+        );
+
+        undo_last_change(State::from_ref(&db)).unwrap();
+        undo_last_change(State::from_ref(&db)).unwrap();
+        assert_eq!(transaction_state(&db, id), before);
+    }
+}
@@ -0,0 +1,540 @@
+use chrono::{Local, Months, NaiveDate};
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::account_balance_as_of;
+use super::currency::{account_currency, base_currency, normalize_currency};
+use super::journal::ChangeScope;
+use super::transactions::{insert_transaction, remove_transaction, Transaction};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Loan {
+    pub id: Option<i64>,
+    pub account_id: Option<i64>, // Liability account created with the loan
+    pub name: String,
+    pub principal: f64,
+    pub annual_rate: f64,       // Percent per year, e.g. 8.5
+    pub tenure_months: i64,
+    pub start_date: String,     // Disbursement date; EMIs fall due monthly after it
+    pub emi: Option<f64>,       // Computed from the terms when not given
+    pub category_id: i64,          // Category for principal repayments (transfers)
+    pub interest_category_id: i64, // Expense category for the interest part
+    pub currency: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoanSummary {
+    pub id: i64,
+    pub account_id: i64,
+    pub name: String,
+    pub currency: String,
+    pub principal: f64,
+    pub annual_rate: f64,
+    pub tenure_months: i64,
+    pub start_date: String,
+    pub emi: f64,
+    pub outstanding: f64,
+    pub installments_paid: i64,
+    pub remaining_installments: i64,
+    pub next_due_date: Option<String>,
+    pub interest_paid: f64,
+    pub principal_paid: f64,
+    pub error: Option<String>, // Terms that cannot be projected; the schedule fields are then empty
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoanScheduleEntry {
+    pub payment_id: Option<i64>,  // Recorded payments only
+    pub installment: Option<i64>, // None for prepayments
+    pub date: String,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub outstanding: f64, // After this payment
+    pub status: String,   // paid, prepayment, projected
+}
+
+struct LoanTerms {
+    account_id: i64,
+    name: String,
+    annual_rate: f64,
+    start_date: NaiveDate,
+    emi: f64,
+    category_id: i64,
+    interest_category_id: i64,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn monthly_rate(annual_rate: f64) -> f64 {
+    annual_rate / 12.0 / 100.0
+}
+
+/// Standard reducing-balance EMI: P·r·(1+r)^n / ((1+r)^n − 1).
+fn emi_for(principal: f64, rate: f64, months: i64) -> f64 {
+    if rate == 0.0 {
+        return principal / months as f64;
+    }
+    let growth = (1.0 + rate).powi(months as i32);
+    principal * rate * growth / (growth - 1.0)
+}
+
+fn due_date(start: NaiveDate, installment: i64) -> String {
+    (start + Months::new(installment as u32)).format("%Y-%m-%d").to_string()
+}
+
+// Amortizes `outstanding` at the current EMI until it reaches zero
+fn project_schedule(outstanding: f64, rate: f64, emi: f64, start: NaiveDate, first_installment: i64) -> Result<Vec<LoanScheduleEntry>, String> {
+    let mut entries = Vec::new();
+    let mut balance = round2(outstanding);
+    let mut installment = first_installment;
+
+    while balance > 0.005 {
+        let interest = round2(balance * rate);
+        if emi <= interest {
+            return Err("The EMI does not cover the monthly interest; the loan would never be repaid".to_string());
+        }
+        if entries.len() >= 1200 {
+            return Err("Loan schedule exceeds 100 years".to_string());
+        }
+        let principal = round2((emi - interest).min(balance));
+        balance = round2(balance - principal);
+        entries.push(LoanScheduleEntry {
+            payment_id: None,
+            installment: Some(installment),
+            date: due_date(start, installment),
+            payment: round2(interest + principal),
+            interest,
+            principal,
+            outstanding: balance,
+            status: "projected".to_string(),
+        });
+        installment += 1;
+    }
+
+    Ok(entries)
+}
+
+fn load_terms(conn: &rusqlite::Connection, loan_id: i64) -> Result<LoanTerms, String> {
+    let (account_id, name, annual_rate, start_date, emi, category_id, interest_category_id): (i64, String, f64, String, f64, i64, i64) = conn.query_row(
+        "SELECT l.account_id, a.name, l.annual_rate, l.start_date, l.emi, l.category_id, l.interest_category_id
+         FROM loans l JOIN accounts a ON l.account_id = a.id WHERE l.id = ?1",
+        [loan_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?)),
+    ).map_err(|_| "Loan not found".to_string())?;
+
+    Ok(LoanTerms {
+        account_id,
+        name,
+        annual_rate,
+        start_date: NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").map_err(|e| e.to_string())?,
+        emi,
+        category_id,
+        interest_category_id,
+    })
+}
+
+/// Principal still owed (positive) in the loan's currency, from the liability
+/// account's balance up to `date` (all time when None).
+fn outstanding_as_of(conn: &rusqlite::Connection, account_id: i64, date: Option<&str>) -> Result<f64, String> {
+    let balance = account_balance_as_of(conn, account_id, date)?;
+    Ok(round2(-balance).max(0.0))
+}
+
+fn emis_paid(conn: &rusqlite::Connection, loan_id: i64) -> Result<i64, String> {
+    conn.query_row("SELECT COUNT(*) FROM loan_payments WHERE loan_id = ?1 AND kind = 'emi'", [loan_id], |r| r.get(0))
+        .map_err(|e| e.to_string())
+}
+
+// Payments must come from an account in the loan's currency so both legs carry the same amount
+fn check_payment_account(conn: &rusqlite::Connection, terms: &LoanTerms, from_account_id: i64) -> Result<(), String> {
+    let loan_currency = account_currency(conn, terms.account_id)?;
+    let from_currency = account_currency(conn, from_account_id)?;
+    if loan_currency != from_currency {
+        return Err(format!("Pay this loan from a {} account", loan_currency));
+    }
+    Ok(())
+}
+
+fn repayment_transfer(terms: &LoanTerms, from_account_id: i64, date: &str, amount: f64, notes: String) -> Transaction {
+    Transaction {
+        id: None,
+        date: date.to_string(),
+        amount,
+        direction: "transfer".to_string(),
+        from_account_id: Some(from_account_id),
+        to_account_id: Some(terms.account_id),
+        category_id: terms.category_id,
+        client_id: None,
+        project_id: None,
+        investment_id: None,
+        goal_id: None,
+        notes: Some(notes),
+        external_id: None,
+        currency: None,
+        to_amount: None,
+    }
+}
+
+/// Creates the liability account (opening balance = −principal) together with
+/// the loan terms. Returns the loan id.
+#[tauri::command]
+pub fn create_loan(db: State<DbConnection>, loan: Loan) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    if loan.principal <= 0.0 {
+        return Err("Principal must be greater than zero".to_string());
+    }
+    if loan.tenure_months <= 0 {
+        return Err("Tenure must be at least one month".to_string());
+    }
+    if loan.annual_rate < 0.0 {
+        return Err("Interest rate cannot be negative".to_string());
+    }
+    NaiveDate::parse_from_str(&loan.start_date, "%Y-%m-%d").map_err(|_| "Start date must be YYYY-MM-DD".to_string())?;
+
+    let rate = monthly_rate(loan.annual_rate);
+    let emi = round2(loan.emi.filter(|e| *e > 0.0).unwrap_or_else(|| emi_for(loan.principal, rate, loan.tenure_months)));
+    if emi <= round2(loan.principal * rate) {
+        return Err("The EMI does not cover the monthly interest; the loan would never be repaid".to_string());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_loan")?;
+
+    let currency = match loan.currency.as_deref().filter(|c| !c.trim().is_empty()) {
+        Some(code) => normalize_currency(code)?,
+        None => base_currency(&tx)?,
+    };
+
+    tx.execute(
+        "INSERT INTO accounts (name, type, opening_balance, notes, currency, created_at) VALUES (?1, 'loan', ?2, ?3, ?4, ?5)",
+        params![loan.name, -loan.principal, loan.notes, currency, loan.start_date],
+    ).map_err(|e| e.to_string())?;
+    let account_id = tx.last_insert_rowid();
+
+    tx.execute(
+        "INSERT INTO loans (account_id, principal, annual_rate, tenure_months, start_date, emi, category_id, interest_category_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![account_id, loan.principal, loan.annual_rate, loan.tenure_months, loan.start_date, emi, loan.category_id, loan.interest_category_id],
+    ).map_err(|e| e.to_string())?;
+    let loan_id = tx.last_insert_rowid();
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(loan_id)
+}
+
+#[tauri::command]
+pub fn get_loans(db: State<DbConnection>) -> Result<Vec<LoanSummary>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let today = Local::now().format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn.prepare("
+        SELECT l.id, l.account_id, a.name, a.currency, l.principal, l.annual_rate, l.tenure_months, l.start_date, l.emi,
+               COALESCE((SELECT SUM(interest) FROM loan_payments p WHERE p.loan_id = l.id), 0),
+               COALESCE((SELECT SUM(principal) FROM loan_payments p WHERE p.loan_id = l.id), 0)
+        FROM loans l
+        JOIN accounts a ON l.account_id = a.id
+        ORDER BY l.start_date, a.name
+    ").map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, i64>(0)?, r.get::<_, i64>(1)?, r.get::<_, String>(2)?, r.get::<_, String>(3)?,
+            r.get::<_, f64>(4)?, r.get::<_, f64>(5)?, r.get::<_, i64>(6)?, r.get::<_, String>(7)?,
+            r.get::<_, f64>(8)?, r.get::<_, f64>(9)?, r.get::<_, f64>(10)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut loans = Vec::new();
+    for (id, account_id, name, currency, principal, annual_rate, tenure_months, start_date, emi, interest_paid, principal_paid) in rows {
+        let outstanding = outstanding_as_of(&conn, account_id, Some(&today))?;
+        let installments_paid = emis_paid(&conn, id)?;
+        // One loan with bad terms is reported on its own rather than hiding the rest
+        let projected = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid start date: {}", start_date))
+            .and_then(|start| project_schedule(outstanding, monthly_rate(annual_rate), emi, start, installments_paid + 1));
+        let (remaining, error) = match projected {
+            Ok(entries) => (entries, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+
+        loans.push(LoanSummary {
+            id,
+            account_id,
+            name,
+            currency,
+            principal,
+            annual_rate,
+            tenure_months,
+            start_date,
+            emi,
+            outstanding,
+            installments_paid,
+            remaining_installments: remaining.len() as i64,
+            next_due_date: remaining.first().map(|e| e.date.clone()),
+            interest_paid: round2(interest_paid),
+            principal_paid: round2(principal_paid),
+            error,
+        });
+    }
+
+    Ok(loans)
+}
+
+/// Recorded EMIs and prepayments followed by the projected installments at the
+/// current EMI. Before any payment this is the full amortization schedule.
+#[tauri::command]
+pub fn get_loan_schedule(db: State<DbConnection>, loan_id: i64) -> Result<Vec<LoanScheduleEntry>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let terms = load_terms(&conn, loan_id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT date, kind, amount, interest, principal, outstanding_after, id FROM loan_payments WHERE loan_id = ?1 ORDER BY date, id"
+    ).map_err(|e| e.to_string())?;
+    let recorded = stmt.query_map([loan_id], |r| {
+        Ok((
+            r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, f64>(2)?, r.get::<_, f64>(3)?,
+            r.get::<_, f64>(4)?, r.get::<_, f64>(5)?, r.get::<_, i64>(6)?,
+        ))
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut schedule = Vec::new();
+    let mut installment = 0;
+    for (date, kind, amount, interest, principal, outstanding, payment_id) in recorded {
+        let is_emi = kind == "emi";
+        if is_emi {
+            installment += 1;
+        }
+        schedule.push(LoanScheduleEntry {
+            payment_id: Some(payment_id),
+            installment: if is_emi { Some(installment) } else { None },
+            date,
+            payment: amount,
+            interest,
+            principal,
+            outstanding,
+            status: if is_emi { "paid" } else { "prepayment" }.to_string(),
+        });
+    }
+
+    let outstanding = outstanding_as_of(&conn, terms.account_id, None)?;
+    schedule.extend(project_schedule(outstanding, monthly_rate(terms.annual_rate), terms.emi, terms.start_date, installment + 1)?);
+    Ok(schedule)
+}
+
+/// Records one EMI: a month's interest on the outstanding principal is booked
+/// as an expense and the rest as a principal transfer into the loan account.
+/// `amount` defaults to the loan's EMI. Returns the loan payment id.
+#[tauri::command]
+pub fn record_loan_payment(
+    db: State<DbConnection>,
+    loan_id: i64,
+    from_account_id: i64,
+    date: String,
+    amount: Option<f64>,
+) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "record_loan_payment")?;
+
+    let terms = load_terms(&tx, loan_id)?;
+    check_payment_account(&tx, &terms, from_account_id)?;
+
+    let outstanding = outstanding_as_of(&tx, terms.account_id, Some(&date))?;
+    if outstanding <= 0.0 {
+        return Err("This loan is already repaid".to_string());
+    }
+
+    let interest = round2(outstanding * monthly_rate(terms.annual_rate));
+    let payment = amount.unwrap_or(terms.emi);
+    if payment <= interest {
+        return Err(format!("Payment must exceed the interest due of {:.2}", interest));
+    }
+    let principal = round2((payment - interest).min(outstanding));
+
+    let principal_tx = insert_transaction(
+        &tx,
+        &repayment_transfer(&terms, from_account_id, &date, principal, format!("{} EMI principal", terms.name)),
+        &[],
+        false,
+    )?;
+    let interest_tx = if interest > 0.0 {
+        Some(insert_transaction(&tx, &Transaction {
+            direction: "expense".to_string(),
+            to_account_id: None,
+            category_id: terms.interest_category_id,
+            amount: interest,
+            notes: Some(format!("{} EMI interest", terms.name)),
+            ..repayment_transfer(&terms, from_account_id, &date, interest, String::new())
+        }, &[], false)?)
+    } else {
+        None
+    };
+
+    tx.execute(
+        "INSERT INTO loan_payments (loan_id, date, kind, amount, interest, principal, outstanding_after, principal_transaction_id, interest_transaction_id)
+         VALUES (?1, ?2, 'emi', ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![loan_id, date, round2(interest + principal), interest, principal, round2(outstanding - principal), principal_tx, interest_tx],
+    ).map_err(|e| e.to_string())?;
+    let payment_id = tx.last_insert_rowid();
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(payment_id)
+}
+
+/// Records a principal-only prepayment. `mode` is "reduce_tenure" (keep the
+/// EMI, finish earlier) or "reduce_emi" (keep the remaining installments and
+/// recompute a smaller EMI). Returns the loan payment id.
+#[tauri::command]
+pub fn record_loan_prepayment(
+    db: State<DbConnection>,
+    loan_id: i64,
+    from_account_id: i64,
+    date: String,
+    amount: f64,
+    mode: String,
+) -> Result<i64, String> {
+    if mode != "reduce_tenure" && mode != "reduce_emi" {
+        return Err("Prepayment mode must be reduce_tenure or reduce_emi".to_string());
+    }
+    if amount <= 0.0 {
+        return Err("Prepayment amount must be greater than zero".to_string());
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "record_loan_prepayment")?;
+
+    let terms = load_terms(&tx, loan_id)?;
+    check_payment_account(&tx, &terms, from_account_id)?;
+
+    let outstanding = outstanding_as_of(&tx, terms.account_id, Some(&date))?;
+    let amount = round2(amount);
+    if amount > outstanding {
+        return Err(format!("Prepayment exceeds the outstanding principal of {:.2}", outstanding));
+    }
+
+    let rate = monthly_rate(terms.annual_rate);
+    let remaining = project_schedule(outstanding, rate, terms.emi, terms.start_date, 1)?.len() as i64;
+    let outstanding_after = round2(outstanding - amount);
+
+    let principal_tx = insert_transaction(
+        &tx,
+        &repayment_transfer(&terms, from_account_id, &date, amount, format!("{} prepayment", terms.name)),
+        &[],
+        false,
+    )?;
+
+    let previous_emi = if mode == "reduce_emi" && outstanding_after > 0.0 && remaining > 0 {
+        tx.execute(
+            "UPDATE loans SET emi = ?1 WHERE id = ?2",
+            params![round2(emi_for(outstanding_after, rate, remaining)), loan_id],
+        ).map_err(|e| e.to_string())?;
+        Some(terms.emi)
+    } else {
+        None
+    };
+
+    tx.execute(
+        "INSERT INTO loan_payments (loan_id, date, kind, amount, interest, principal, outstanding_after, principal_transaction_id, previous_emi)
+         VALUES (?1, ?2, 'prepayment', ?3, 0, ?3, ?4, ?5, ?6)",
+        params![loan_id, date, amount, outstanding_after, principal_tx, previous_emi],
+    ).map_err(|e| e.to_string())?;
+    let payment_id = tx.last_insert_rowid();
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(payment_id)
+}
+
+/// Deletes a loan's most recent EMI or prepayment together with its principal
+/// and interest transactions. Later payments were computed on top of earlier
+/// ones, so only the latest can go; an EMI cut by a prepayment is restored.
+#[tauri::command]
+pub fn delete_loan_payment(db: State<DbConnection>, payment_id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_loan_payment")?;
+
+    let (loan_id, principal_tx, interest_tx, previous_emi): (i64, Option<i64>, Option<i64>, Option<f64>) = tx.query_row(
+        "SELECT loan_id, principal_transaction_id, interest_transaction_id, previous_emi FROM loan_payments WHERE id = ?1",
+        [payment_id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+    ).map_err(|_| "Loan payment not found".to_string())?;
+
+    let latest: i64 = tx.query_row(
+        "SELECT id FROM loan_payments WHERE loan_id = ?1 ORDER BY date DESC, id DESC LIMIT 1",
+        [loan_id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+    if latest != payment_id {
+        return Err("Only the most recent payment of a loan can be deleted".to_string());
+    }
+
+    tx.execute("DELETE FROM loan_payments WHERE id = ?1", [payment_id]).map_err(|e| e.to_string())?;
+    for id in [principal_tx, interest_tx].into_iter().flatten() {
+        remove_transaction(&tx, id)?;
+    }
+    if let Some(emi) = previous_emi {
+        tx.execute("UPDATE loans SET emi = ?1 WHERE id = ?2", params![emi, loan_id]).map_err(|e| e.to_string())?;
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()
+    }
+
+    #[test]
+    fn emi_matches_reducing_balance_formula() {
+        assert_eq!(round2(emi_for(100000.0, monthly_rate(12.0), 12)), 8884.88);
+        assert_eq!(round2(emi_for(5000.0, 0.0, 5)), 1000.0);
+    }
+
+    #[test]
+    fn schedule_amortizes_to_zero() {
+        let rate = monthly_rate(12.0);
+        let entries = project_schedule(100000.0, rate, 8884.88, start(), 1).unwrap();
+        assert_eq!(entries.len(), 12);
+        assert_eq!((entries[0].interest, entries[0].principal, entries[0].outstanding), (1000.0, 7884.88, 92115.12));
+        assert_eq!(entries.last().unwrap().outstanding, 0.0);
+        let principal: f64 = entries.iter().map(|e| e.principal).sum();
+        assert_eq!(round2(principal), 100000.0);
+        // Month-end start dates clamp to shorter months
+        assert_eq!(entries[0].date, "2026-02-28");
+        assert_eq!(entries[2].date, "2026-04-30");
+    }
+
+    #[test]
+    fn schedule_continues_from_installment() {
+        let entries = project_schedule(3000.0, 0.0, 1000.0, start(), 4).unwrap();
+        assert_eq!(entries.iter().map(|e| e.installment.unwrap()).collect::<Vec<_>>(), [4, 5, 6]);
+        assert_eq!(entries[0].date, "2026-05-31");
+        assert!(project_schedule(0.0, 0.0, 1000.0, start(), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn schedule_rejects_emi_below_interest() {
+        assert!(project_schedule(100000.0, monthly_rate(12.0), 1000.0, start(), 1).is_err());
+    }
+}
@@ -11,6 +11,22 @@ pub mod budget;
 pub mod goals;
 pub mod utils;
 pub mod income_breakdown;
+pub mod import;
+pub mod statement_parsers;
+pub mod splits;
+pub mod search;
+pub mod rules;
+pub mod journal;
+pub mod bulk;
+pub mod reconcile;
+pub mod attachments;
+pub mod currency;
+pub mod credit_cards;
+pub mod loans;
+pub mod balances;
+pub mod assertions;
+pub mod allocations;
+pub mod forecast;
 
 pub use accounts::*;
 pub use categories::*;
@@ -25,3 +41,18 @@ pub use budget::*;
 pub use goals::*;
 pub use utils::*;
 pub use income_breakdown::*;
+pub use import::*;
+pub use splits::*;
+pub use search::*;
+pub use rules::*;
+pub use journal::*;
+pub use bulk::*;
+pub use reconcile::*;
+pub use attachments::*;
+pub use currency::*;
+pub use credit_cards::*;
+pub use loans::*;
+pub use balances::*;
+pub use assertions::*;
+pub use allocations::*;
+pub use forecast::*;
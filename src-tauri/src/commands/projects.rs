@@ -2,6 +2,7 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Project {
@@ -55,8 +56,8 @@ pub fn get_projects(db: State<DbConnection>) -> Result<Vec<Project>, String> {
             SELECT 
                 p.id, p.name, p.client_id, p.category_id, p.expected_amount, p.hourly_rate, p.start_date, p.end_date, p.notes, p.completed, p.status,
                 p.srs_internal_link, p.srs_client_approved_link, p.srs_status, p.srs_approved_date,
-                (SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE project_id = p.id AND direction = 'income') as received,
-                (SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE project_id = p.id AND direction = 'expense') as spent,
+                (SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts WHERE project_id = p.id AND direction = 'income') as received,
+                (SELECT COALESCE(SUM(base_amount), 0) FROM transaction_amounts WHERE project_id = p.id AND direction = 'expense') as spent,
                 (SELECT COALESCE(SUM(hours), 0) FROM time_logs WHERE project_id = p.id) as hours
             FROM projects p 
             ORDER BY CASE p.status WHEN 'active' THEN 0 WHEN 'on_hold' THEN 1 WHEN 'prospect' THEN 2 WHEN 'completed' THEN 3 WHEN 'archived' THEN 4 ELSE 5 END, p.name
@@ -99,6 +100,7 @@ pub fn create_project(
     project: Project,
 ) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_project")?;
     
     conn.execute(
         "INSERT INTO projects (name, client_id, category_id, expected_amount, hourly_rate, start_date, end_date, notes, completed, status, 
@@ -132,6 +134,7 @@ pub fn update_project(
     project: Project,
 ) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_project")?;
     
     let id = project.id.ok_or("Project ID is required")?;
     
@@ -186,6 +189,7 @@ pub fn get_time_logs(db: State<DbConnection>, project_id: i64) -> Result<Vec<Tim
 #[tauri::command]
 pub fn create_time_log(db: State<DbConnection>, log: TimeLog) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_time_log")?;
     conn.execute(
         "INSERT INTO time_logs (project_id, date, hours, task) VALUES (?1, ?2, ?3, ?4)",
         params![log.project_id, log.date, log.hours, log.task],
@@ -196,6 +200,7 @@ pub fn create_time_log(db: State<DbConnection>, log: TimeLog) -> Result<i64, Str
 #[tauri::command]
 pub fn update_time_log(db: State<DbConnection>, log: TimeLog) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "update_time_log")?;
     let id = log.id.ok_or("Time log ID is required")?;
     conn.execute(
         "UPDATE time_logs SET date = ?1, hours = ?2, task = ?3 WHERE id = ?4",
@@ -207,6 +212,7 @@ pub fn update_time_log(db: State<DbConnection>, log: TimeLog) -> Result<(), Stri
 #[tauri::command]
 pub fn delete_time_log(db: State<DbConnection>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_time_log")?;
     conn.execute("DELETE FROM time_logs WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
     Ok(())
 }
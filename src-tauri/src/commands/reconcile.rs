@@ -0,0 +1,304 @@
+use rusqlite::{params, OptionalExtension, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::balances::{rolled_up_balance_as_of, statement_rows, ACCOUNT_WITH_BUCKETS};
+use super::journal::ChangeScope;
+use super::transactions::{map_transaction_row, TransactionWithDetails, TRANSACTION_DETAILS_SELECT};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountStatement {
+    pub id: Option<i64>,
+    pub account_id: i64,
+    pub end_date: String,
+    pub closing_balance: f64,
+    pub status: Option<String>, // open, reconciled
+    pub notes: Option<String>,
+    pub reconciled_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationSummary {
+    pub statement: AccountStatement,
+    pub ledger_balance: f64,  // Every transaction up to the statement end date
+    pub cleared_balance: f64, // Only cleared and reconciled transactions
+    pub difference: f64,      // closing_balance - cleared_balance
+    pub uncleared: Vec<TransactionWithDetails>,
+}
+
+/// Reconciled rows are locked; they must be un-reconciled before any edit.
+pub(crate) fn ensure_not_reconciled(conn: &rusqlite::Connection, transaction_id: i64) -> Result<(), String> {
+    let status: Option<String> = conn.query_row(
+        "SELECT cleared_status FROM transactions WHERE id = ?1",
+        [transaction_id],
+        |r| r.get(0),
+    ).optional().map_err(|e| e.to_string())?;
+
+    if status.as_deref() == Some("reconciled") {
+        return Err(format!("Transaction {} is reconciled; un-reconcile it before editing", transaction_id));
+    }
+    Ok(())
+}
+
+fn load_statement(conn: &rusqlite::Connection, id: i64) -> Result<AccountStatement, String> {
+    conn.query_row(
+        "SELECT id, account_id, end_date, closing_balance, status, notes, reconciled_at FROM account_statements WHERE id = ?1",
+        [id],
+        |row| Ok(AccountStatement {
+            id: Some(row.get(0)?),
+            account_id: row.get(1)?,
+            end_date: row.get(2)?,
+            closing_balance: row.get(3)?,
+            status: row.get(4)?,
+            notes: row.get(5)?,
+            reconciled_at: row.get(6)?,
+        }),
+    ).map_err(|_| "Statement not found".to_string())
+}
+
+// Opening balance plus net flow into the account and its buckets up to
+// `end_date`, optionally counting only cleared/reconciled rows. The full ledger
+// balance comes from the daily balance table; the cleared one needs the
+// per-row status.
+fn balance_as_of(conn: &rusqlite::Connection, account_id: i64, end_date: &str, cleared_only: bool) -> Result<f64, String> {
+    let total = rolled_up_balance_as_of(conn, account_id, Some(end_date))?;
+    if !cleared_only {
+        return Ok(total);
+    }
+
+    // Parent/bucket moves net to zero, so only uncleared outside rows are backed out
+    let uncleared: f64 = conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(CASE WHEN to_account_id IN {f} THEN COALESCE(to_amount, amount) ELSE 0 END), 0)
+                  - COALESCE(SUM(CASE WHEN from_account_id IN {f} THEN amount ELSE 0 END), 0)
+             FROM transactions
+             WHERE {rows} AND date <= ?2 AND cleared_status = 'uncleared'",
+            f = ACCOUNT_WITH_BUCKETS,
+            rows = statement_rows(""),
+        ),
+        params![account_id, end_date],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    Ok(total - uncleared)
+}
+
+fn summarize(conn: &rusqlite::Connection, statement_id: i64) -> Result<ReconciliationSummary, String> {
+    let statement = load_statement(conn, statement_id)?;
+    let ledger_balance = balance_as_of(conn, statement.account_id, &statement.end_date, false)?;
+    let cleared_balance = balance_as_of(conn, statement.account_id, &statement.end_date, true)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE {} AND t.date <= ?2 AND t.cleared_status = 'uncleared' ORDER BY t.date, t.id",
+        TRANSACTION_DETAILS_SELECT,
+        statement_rows("t."),
+    )).map_err(|e| e.to_string())?;
+    let uncleared = stmt
+        .query_map(params![statement.account_id, statement.end_date], map_transaction_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let difference = ((statement.closing_balance - cleared_balance) * 100.0).round() / 100.0;
+
+    Ok(ReconciliationSummary {
+        statement,
+        ledger_balance,
+        cleared_balance,
+        difference,
+        uncleared,
+    })
+}
+
+#[tauri::command]
+pub fn get_account_statements(db: State<DbConnection>, account_id: Option<i64>) -> Result<Vec<AccountStatement>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare("
+        SELECT id, account_id, end_date, closing_balance, status, notes, reconciled_at
+        FROM account_statements
+        WHERE (?1 IS NULL OR account_id = ?1)
+        ORDER BY end_date DESC, id DESC
+    ").map_err(|e| e.to_string())?;
+
+    let statements = stmt.query_map([account_id], |row| {
+        Ok(AccountStatement {
+            id: Some(row.get(0)?),
+            account_id: row.get(1)?,
+            end_date: row.get(2)?,
+            closing_balance: row.get(3)?,
+            status: row.get(4)?,
+            notes: row.get(5)?,
+            reconciled_at: row.get(6)?,
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    Ok(statements)
+}
+
+#[tauri::command]
+pub fn create_account_statement(db: State<DbConnection>, statement: AccountStatement) -> Result<i64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_account_statement")?;
+
+    conn.execute(
+        "INSERT INTO account_statements (account_id, end_date, closing_balance, notes) VALUES (?1, ?2, ?3, ?4)",
+        params![statement.account_id, statement.end_date, statement.closing_balance, statement.notes],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+#[tauri::command]
+pub fn delete_account_statement(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "delete_account_statement")?;
+
+    if load_statement(&conn, id)?.status.as_deref() == Some("reconciled") {
+        return Err("Un-reconcile the statement before deleting it".to_string());
+    }
+    conn.execute("DELETE FROM account_statements WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_reconciliation_summary(db: State<DbConnection>, statement_id: i64) -> Result<ReconciliationSummary, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    summarize(&conn, statement_id)
+}
+
+/// Toggles cleared/uncleared; reconciled rows are left alone and reported as an error.
+#[tauri::command]
+pub fn set_transactions_cleared(db: State<DbConnection>, ids: Vec<i64>, cleared: bool) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_transactions_cleared")?;
+
+    let status = if cleared { "cleared" } else { "uncleared" };
+    for id in ids {
+        ensure_not_reconciled(&tx, id)?;
+        tx.execute(
+            "UPDATE transactions SET cleared_status = ?1 WHERE id = ?2",
+            params![status, id],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Locks every cleared transaction up to the statement date once the cleared
+/// balance matches the statement's closing balance.
+#[tauri::command]
+pub fn finish_reconciliation(db: State<DbConnection>, statement_id: i64) -> Result<ReconciliationSummary, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "finish_reconciliation")?;
+
+    let summary = summarize(&tx, statement_id)?;
+    if summary.difference.abs() > 0.005 {
+        return Err(format!("Statement is off by {:.2}; clear the missing items first", summary.difference));
+    }
+
+    tx.execute(
+        &format!(
+            "UPDATE transactions SET cleared_status = 'reconciled', statement_id = ?3
+             WHERE {} AND date <= ?2 AND cleared_status = 'cleared'",
+            statement_rows(""),
+        ),
+        params![summary.statement.account_id, summary.statement.end_date, statement_id],
+    ).map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE account_statements SET status = 'reconciled', reconciled_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        [statement_id],
+    ).map_err(|e| e.to_string())?;
+
+    let summary = summarize(&tx, statement_id)?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(summary)
+}
+
+/// Unlocks every transaction reconciled against the statement (they go back to
+/// cleared) and reopens it.
+#[tauri::command]
+pub fn unreconcile_statement(db: State<DbConnection>, statement_id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "unreconcile_statement")?;
+
+    load_statement(&tx, statement_id)?;
+    tx.execute(
+        "UPDATE transactions SET cleared_status = 'cleared', statement_id = NULL WHERE statement_id = ?1",
+        [statement_id],
+    ).map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE account_statements SET status = 'open', reconciled_at = NULL WHERE id = ?1",
+        [statement_id],
+    ).map_err(|e| e.to_string())?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Unlocks a single transaction. Its statement no longer balances on its own,
+/// so it is reopened as well.
+#[tauri::command]
+pub fn unreconcile_transaction(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "unreconcile_transaction")?;
+
+    let statement_id: Option<i64> = tx.query_row(
+        "SELECT statement_id FROM transactions WHERE id = ?1",
+        [id],
+        |r| r.get(0),
+    ).map_err(|_| "Transaction not found".to_string())?;
+
+    tx.execute(
+        "UPDATE transactions SET cleared_status = 'cleared', statement_id = NULL WHERE id = ?1 AND cleared_status = 'reconciled'",
+        [id],
+    ).map_err(|e| e.to_string())?;
+    if let Some(sid) = statement_id {
+        tx.execute(
+            "UPDATE account_statements SET status = 'open', reconciled_at = NULL WHERE id = ?1",
+            [sid],
+        ).map_err(|e| e.to_string())?;
+    }
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statement_covers_buckets_but_not_moves_into_them() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 1000);
+             INSERT INTO accounts (id, name, type, opening_balance, parent_id, bucket_role) VALUES (2, 'Emergency', 'bucket', 200, 1, 'emergency');
+             INSERT INTO categories (id, name, kind) VALUES (1, 'Misc', 'expense');
+             INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, cleared_status)
+             VALUES ('2026-03-02', 300, 'transfer', 1, 2, 1, 'uncleared'),
+                    ('2026-03-03', 100, 'expense', 1, NULL, 1, 'cleared'),
+                    ('2026-03-04', 50, 'expense', 2, NULL, 1, 'uncleared');
+             INSERT INTO account_statements (id, account_id, end_date, closing_balance) VALUES (1, 1, '2026-03-31', 1100);",
+        ).unwrap();
+
+        let summary = summarize(&conn, 1).unwrap();
+        assert_eq!(summary.ledger_balance, 1050.0);
+        assert_eq!(summary.cleared_balance, 1100.0);
+        assert_eq!(summary.difference, 0.0);
+        assert_eq!(summary.uncleared.iter().map(|t| t.amount).collect::<Vec<_>>(), [50.0]);
+    }
+}
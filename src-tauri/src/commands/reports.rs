@@ -2,6 +2,7 @@ use rusqlite::Result;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::currency::rate_to_base_sql;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MonthlySummary {
@@ -65,6 +66,7 @@ pub struct NetWorthPoint {
     pub month: String,
     pub cash: f64,
     pub invested: f64,
+    pub liabilities: f64, // Outstanding loans, negative
     pub total: f64,
 }
 
@@ -115,10 +117,10 @@ pub fn get_monthly_summary(
     let mut query = String::from("
         SELECT 
             strftime('%Y-%m', t.date) as month,
-            SUM(CASE WHEN t.direction = 'income' THEN t.amount ELSE 0 END) as income,
-            SUM(CASE WHEN t.direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.amount ELSE 0 END) as expense,
-            SUM(CASE WHEN COALESCE(c.is_investment, 0) = 1 THEN t.amount ELSE 0 END) as investment
-        FROM transactions t
+            COALESCE(SUM(CASE WHEN t.direction = 'income' THEN t.base_amount ELSE 0 END), 0) as income,
+            COALESCE(SUM(CASE WHEN t.direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.base_amount ELSE 0 END), 0) as expense,
+            COALESCE(SUM(CASE WHEN COALESCE(c.is_investment, 0) = 1 THEN t.base_amount ELSE 0 END), 0) as investment
+        FROM transaction_amounts t
         LEFT JOIN categories c ON t.category_id = c.id
         WHERE strftime('%Y', t.date) = ?
     ");
@@ -161,9 +163,9 @@ pub fn get_category_summary(
     // Special handling for 'investment' - query categories marked as is_investment
     if direction == "investment" {
         let mut query = String::from("
-            SELECT c.name, COALESCE(SUM(t.amount), 0) as total, COUNT(t.id) as count
+            SELECT c.name, COALESCE(SUM(t.base_amount), 0) as total, COUNT(t.id) as count
             FROM categories c
-            LEFT JOIN transactions t ON t.category_id = c.id
+            LEFT JOIN transaction_lines t ON t.category_id = c.id
             WHERE COALESCE(c.is_investment, 0) = 1
         ");
         
@@ -189,10 +191,10 @@ pub fn get_category_summary(
         return Ok(summaries);
     }
     
-    // Standard direction-based query (income/expense)
+    // Standard direction-based query (income/expense), counting split lines by their own category
     let mut query = String::from("
-        SELECT c.name, SUM(t.amount) as total, COUNT(*) as count
-        FROM transactions t
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0) as total, COUNT(*) as count
+        FROM transaction_lines t
         JOIN categories c ON t.category_id = c.id
         WHERE t.direction = ?
     ");
@@ -224,8 +226,8 @@ pub fn get_client_summary(db: State<DbConnection>, filters: ReportFilters) -> Re
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
     let mut query = String::from("
-        SELECT c.name, SUM(t.amount) as total, COUNT(*) as count
-        FROM transactions t
+        SELECT c.name, COALESCE(SUM(t.base_amount), 0) as total, COUNT(*) as count
+        FROM transaction_amounts t
         JOIN clients c ON t.client_id = c.id
         WHERE t.direction = 'income' AND t.client_id IS NOT NULL
     ");
@@ -259,10 +261,10 @@ pub fn get_overall_stats(db: State<DbConnection>, filters: ReportFilters) -> Res
     // Get income, expense, and transaction count
     let mut query = String::from("
         SELECT 
-            SUM(CASE WHEN direction = 'income' THEN amount ELSE 0 END) as total_income,
-            SUM(CASE WHEN direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.amount ELSE 0 END) as total_expense,
+            SUM(CASE WHEN direction = 'income' THEN t.base_amount ELSE 0 END) as total_income,
+            SUM(CASE WHEN direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.base_amount ELSE 0 END) as total_expense,
             COUNT(*) as transaction_count
-        FROM transactions t
+        FROM transaction_amounts t
         LEFT JOIN categories c ON t.category_id = c.id
         WHERE 1=1
     ");
@@ -282,8 +284,8 @@ pub fn get_overall_stats(db: State<DbConnection>, filters: ReportFilters) -> Res
     
     // Get total invested (transactions in categories marked as is_investment)
     let mut invest_query = String::from("
-        SELECT COALESCE(SUM(t.amount), 0)
-        FROM transactions t
+        SELECT COALESCE(SUM(t.base_amount), 0)
+        FROM transaction_amounts t
         JOIN categories c ON t.category_id = c.id
         WHERE COALESCE(c.is_investment, 0) = 1
     ");
@@ -341,20 +343,20 @@ pub fn get_project_income_report(
                     p.expected_amount as total_expected,
                     -- Actual in this specific month
                     COALESCE((
-                        SELECT SUM(amount) 
-                        FROM transactions 
+                        SELECT SUM(base_amount) 
+                        FROM transaction_amounts 
                         WHERE project_id = p.id AND direction = 'income' AND strftime('%Y-%m', date) = ?1
                     ), 0) as monthly_actual,
                     -- Actual received BEFORE this month
                     COALESCE((
-                        SELECT SUM(amount) 
-                        FROM transactions 
+                        SELECT SUM(base_amount) 
+                        FROM transaction_amounts 
                         WHERE project_id = p.id AND direction = 'income' AND strftime('%Y-%m', date) < ?1
                     ), 0) as prior_actual,
                     -- Actual received UP TO AND INCLUDING this month
                     COALESCE((
-                        SELECT SUM(amount) 
-                        FROM transactions 
+                        SELECT SUM(base_amount) 
+                        FROM transaction_amounts 
                         WHERE project_id = p.id AND direction = 'income' AND strftime('%Y-%m', date) <= ?1
                     ), 0) as cumulative_actual,
                     -- Is the deadline in this month?
@@ -416,10 +418,10 @@ pub fn get_net_worth_trend(db: State<DbConnection>) -> Result<Vec<NetWorthPoint>
     let mut stmt = conn.prepare("
         SELECT 
             strftime('%Y-%m', t.date) as month,
-            SUM(CASE WHEN t.direction = 'income' THEN t.amount ELSE 0 END) as income,
-            SUM(CASE WHEN t.direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.amount ELSE 0 END) as expense,
-            SUM(CASE WHEN COALESCE(c.is_investment, 0) = 1 THEN t.amount ELSE 0 END) as investment
-        FROM transactions t
+            COALESCE(SUM(CASE WHEN t.direction = 'income' THEN t.base_amount ELSE 0 END), 0) as income,
+            COALESCE(SUM(CASE WHEN t.direction = 'expense' AND COALESCE(c.is_investment, 0) = 0 THEN t.base_amount ELSE 0 END), 0) as expense,
+            COALESCE(SUM(CASE WHEN COALESCE(c.is_investment, 0) = 1 THEN t.base_amount ELSE 0 END), 0) as investment
+        FROM transaction_amounts t
         LEFT JOIN categories c ON t.category_id = c.id
         GROUP BY month
         ORDER BY month
@@ -434,8 +436,9 @@ pub fn get_net_worth_trend(db: State<DbConnection>) -> Result<Vec<NetWorthPoint>
         ))
     }).map_err(|e| e.to_string())?;
 
-    let initial_cash: f64 = conn.query_row("SELECT COALESCE(SUM(opening_balance), 0) FROM accounts WHERE LOWER(type) != 'investment'", [], |r| r.get(0)).unwrap_or(0.0);
-    let initial_invested: f64 = conn.query_row("SELECT COALESCE(SUM(opening_balance), 0) FROM accounts WHERE LOWER(type) = 'investment'", [], |r| r.get(0)).unwrap_or(0.0);
+    let base_opening = format!("opening_balance * COALESCE({}, 0)", rate_to_base_sql("a.currency", "date(a.created_at)"));
+    let initial_cash: f64 = conn.query_row(&format!("SELECT COALESCE(SUM({}), 0) FROM accounts a WHERE LOWER(type) NOT IN ('investment', 'loan')", base_opening), [], |r| r.get(0)).unwrap_or(0.0);
+    let initial_invested: f64 = conn.query_row(&format!("SELECT COALESCE(SUM({}), 0) FROM accounts a WHERE LOWER(type) = 'investment'", base_opening), [], |r| r.get(0)).unwrap_or(0.0);
 
     let mut trend = Vec::new();
     let mut cumulative_income = 0.0;
@@ -444,6 +447,19 @@ pub fn get_net_worth_trend(db: State<DbConnection>) -> Result<Vec<NetWorthPoint>
 
     let initial_wealth = initial_cash + initial_invested;
 
+    // Loans count against net worth from the month they were taken. Principal
+    // repayments are transfers, so they only move value from cash to the loan.
+    let loans_as_of = |month_end: &str| -> Result<(f64, f64), String> {
+        conn.query_row(&format!("
+            SELECT COALESCE(SUM({opening}), 0),
+                   COALESCE(SUM({opening} + COALESCE((SELECT SUM(b.net_base_amount) FROM account_daily_balances b WHERE b.account_id = a.id AND b.date <= ?1), 0)), 0)
+            FROM accounts a
+            LEFT JOIN loans l ON l.account_id = a.id
+            WHERE LOWER(a.type) = 'loan' AND COALESCE(l.start_date, date(a.created_at)) <= ?1
+        ", opening = base_opening), [month_end], |r| Ok((r.get(0)?, r.get(1)?)))
+        .map_err(|e| e.to_string())
+    };
+
     for row in rows {
         let (month, income, expense, investment) = row.map_err(|e| e.to_string())?;
         
@@ -451,17 +467,20 @@ pub fn get_net_worth_trend(db: State<DbConnection>) -> Result<Vec<NetWorthPoint>
         cumulative_expense += expense;
         cumulative_invested += investment;
 
-        // Total wealth = Baseline + Cumulative Net Flow
-        let total_wealth = initial_wealth + (cumulative_income - cumulative_expense);
+        let (loans_taken, liabilities) = loans_as_of(&format!("{}-31", month))?;
+
+        // Total wealth = Baseline + Cumulative Net Flow - Loans taken so far
+        let total_wealth = initial_wealth + (cumulative_income - cumulative_expense) + loans_taken;
         // Total invested = Baseline Invested + Cumulative Transactions
         let current_invested = initial_invested + cumulative_invested;
-        // Cash = Total Wealth - Total Invested
-        let cash = total_wealth - current_invested;
+        // Cash = Total Wealth - Total Invested - Outstanding loans
+        let cash = total_wealth - current_invested - liabilities;
 
         trend.push(NetWorthPoint {
             month,
             cash: (cash * 100.0).round() / 100.0,
             invested: (current_invested * 100.0).round() / 100.0,
+            liabilities: (liabilities * 100.0).round() / 100.0,
             total: (total_wealth * 100.0).round() / 100.0,
         });
     }
@@ -497,9 +516,9 @@ pub fn get_asset_allocation(db: State<DbConnection>) -> Result<Vec<AssetAllocati
     // Add Liquid Cash as an asset class
     let cash_balance: f64 = conn.query_row("
         SELECT 
-            SUM(CASE WHEN direction = 'income' THEN amount ELSE 0 END) -
-            SUM(CASE WHEN direction = 'expense' THEN amount ELSE 0 END)
-        FROM transactions t
+            SUM(CASE WHEN direction = 'income' THEN base_amount ELSE 0 END) -
+            SUM(CASE WHEN direction = 'expense' THEN base_amount ELSE 0 END)
+        FROM transaction_amounts t
         LEFT JOIN categories c ON t.category_id = c.id
     ", [], |r| r.get(0)).unwrap_or(0.0);
 
@@ -530,16 +549,16 @@ pub fn get_source_category_breakdown(
     // This ensures that date/client filters apply to individual transactions correctly.
     
     let mut query = String::from("
-        SELECT direction, source_name, category_name, SUM(amount) as total, COUNT(*) as count
+        SELECT direction, source_name, category_name, COALESCE(SUM(base_amount), 0) as total, COUNT(*) as count
         FROM (
             -- Income
             SELECT 
                 'income' as direction,
                 COALESCE(cl.name, 'Direct') as source_name,
                 c.name as category_name,
-                t.amount,
+                t.base_amount,
                 t.date, t.client_id, t.project_id
-            FROM transactions t
+            FROM transaction_lines t
             LEFT JOIN categories c ON t.category_id = c.id
             LEFT JOIN clients cl ON t.client_id = cl.id
             WHERE t.direction = 'income'
@@ -551,9 +570,9 @@ pub fn get_source_category_breakdown(
                 'expense' as direction,
                 fa.name as source_name,
                 c.name as category_name,
-                t.amount,
+                t.base_amount,
                 t.date, t.client_id, t.project_id
-            FROM transactions t
+            FROM transaction_lines t
             LEFT JOIN categories c ON t.category_id = c.id
             LEFT JOIN accounts fa ON t.from_account_id = fa.id
             WHERE t.direction = 'expense'
@@ -565,9 +584,9 @@ pub fn get_source_category_breakdown(
                 'transfer' as direction,
                 fa.name as source_name,
                 COALESCE(g.name, ta.name) as category_name,
-                t.amount,
+                t.base_amount,
                 t.date, t.client_id, t.project_id
-            FROM transactions t
+            FROM transaction_amounts t
             LEFT JOIN accounts fa ON t.from_account_id = fa.id
             LEFT JOIN accounts ta ON t.to_account_id = ta.id
             LEFT JOIN goals g ON t.goal_id = g.id
@@ -0,0 +1,375 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::journal::ChangeScope;
+use super::search::refresh_search_index;
+use super::transactions::Transaction;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategorizationRule {
+    pub id: Option<i64>,
+    pub name: String,
+    pub priority: i64, // Lower runs first; the first matching rule wins
+    pub is_active: bool,
+    // Conditions (all set conditions must match)
+    pub notes_contains: Option<String>,
+    pub amount_equals: Option<f64>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub account_id: Option<i64>,
+    pub direction: Option<String>,
+    // Actions
+    pub set_category_id: Option<i64>,
+    pub set_client_id: Option<i64>,
+    pub set_project_id: Option<i64>,
+    pub add_tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuleApplication {
+    pub transaction_id: i64,
+    pub date: String,
+    pub amount: f64,
+    pub notes: Option<String>,
+    pub rule_id: i64,
+    pub rule_name: String,
+    pub old_category_id: i64,
+    pub new_category_id: i64,
+    pub old_client_id: Option<i64>,
+    pub new_client_id: Option<i64>,
+    pub old_project_id: Option<i64>,
+    pub new_project_id: Option<i64>,
+    pub added_tag_ids: Vec<i64>,
+}
+
+fn load_active_rules(conn: &rusqlite::Connection) -> Result<Vec<CategorizationRule>, String> {
+    Ok(load_rules(conn)?.into_iter().filter(|r| r.is_active).collect())
+}
+
+fn load_rules(conn: &rusqlite::Connection) -> Result<Vec<CategorizationRule>, String> {
+    let mut stmt = conn.prepare("
+        SELECT id, name, priority, is_active, notes_contains, amount_equals, min_amount, max_amount,
+               account_id, direction, set_category_id, set_client_id, set_project_id
+        FROM categorization_rules
+        ORDER BY priority ASC, id ASC
+    ").map_err(|e| e.to_string())?;
+
+    let mut rules = stmt.query_map([], |row| {
+        let is_active: i32 = row.get(3)?;
+        Ok(CategorizationRule {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            priority: row.get(2)?,
+            is_active: is_active != 0,
+            notes_contains: row.get(4)?,
+            amount_equals: row.get(5)?,
+            min_amount: row.get(6)?,
+            max_amount: row.get(7)?,
+            account_id: row.get(8)?,
+            direction: row.get(9)?,
+            set_category_id: row.get(10)?,
+            set_client_id: row.get(11)?,
+            set_project_id: row.get(12)?,
+            add_tag_ids: Vec::new(),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut tag_stmt = conn
+        .prepare("SELECT tag_id FROM categorization_rule_tags WHERE rule_id = ?1")
+        .map_err(|e| e.to_string())?;
+    for rule in &mut rules {
+        rule.add_tag_ids = tag_stmt
+            .query_map([rule.id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(rules)
+}
+
+fn rule_matches(rule: &CategorizationRule, direction: &str, amount: f64, notes: Option<&str>, from_account_id: Option<i64>, to_account_id: Option<i64>) -> bool {
+    if let Some(needle) = rule.notes_contains.as_deref().filter(|n| !n.trim().is_empty()) {
+        let haystack = notes.unwrap_or("").to_lowercase();
+        if !haystack.contains(&needle.trim().to_lowercase()) {
+            return false;
+        }
+    }
+    if let Some(eq) = rule.amount_equals {
+        if (amount - eq).abs() > 0.005 { return false; }
+    }
+    if let Some(min) = rule.min_amount {
+        if amount < min { return false; }
+    }
+    if let Some(max) = rule.max_amount {
+        if amount > max { return false; }
+    }
+    if let Some(acc) = rule.account_id {
+        if from_account_id != Some(acc) && to_account_id != Some(acc) { return false; }
+    }
+    if let Some(dir) = rule.direction.as_deref().filter(|d| !d.is_empty()) {
+        if dir != direction { return false; }
+    }
+    true
+}
+
+fn first_matching_rule<'a>(rules: &'a [CategorizationRule], transaction: &Transaction) -> Option<&'a CategorizationRule> {
+    rules.iter().find(|r| rule_matches(
+        r,
+        &transaction.direction,
+        transaction.amount,
+        transaction.notes.as_deref(),
+        transaction.from_account_id,
+        transaction.to_account_id,
+    ))
+}
+
+/// Applies the first matching rule to a transaction that is about to be saved.
+/// Returns the id of the rule that fired, if any.
+pub(crate) fn apply_categorization_rules(conn: &rusqlite::Connection, transaction: &mut Transaction, tag_ids: &mut Vec<i64>) -> Result<Option<i64>, String> {
+    let rules = load_active_rules(conn)?;
+    let rule = match first_matching_rule(&rules, transaction) {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    if let Some(cat) = rule.set_category_id { transaction.category_id = cat; }
+    if let Some(client) = rule.set_client_id { transaction.client_id = Some(client); }
+    if let Some(project) = rule.set_project_id { transaction.project_id = Some(project); }
+    for tag_id in &rule.add_tag_ids {
+        if !tag_ids.contains(tag_id) {
+            tag_ids.push(*tag_id);
+        }
+    }
+
+    Ok(rule.id)
+}
+
+fn validate_rule(rule: &CategorizationRule) -> Result<(), String> {
+    let has_condition = rule.notes_contains.as_deref().is_some_and(|n| !n.trim().is_empty())
+        || rule.amount_equals.is_some()
+        || rule.min_amount.is_some()
+        || rule.max_amount.is_some()
+        || rule.account_id.is_some()
+        || rule.direction.as_deref().is_some_and(|d| !d.is_empty());
+    let has_action = rule.set_category_id.is_some()
+        || rule.set_client_id.is_some()
+        || rule.set_project_id.is_some()
+        || !rule.add_tag_ids.is_empty();
+
+    if !has_condition {
+        return Err("A rule needs at least one condition".to_string());
+    }
+    if !has_action {
+        return Err("A rule needs at least one action".to_string());
+    }
+    Ok(())
+}
+
+fn save_rule_tags(conn: &rusqlite::Connection, rule_id: i64, tag_ids: &[i64]) -> Result<(), String> {
+    conn.execute("DELETE FROM categorization_rule_tags WHERE rule_id = ?1", [rule_id])
+        .map_err(|e| e.to_string())?;
+    for tag_id in tag_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO categorization_rule_tags (rule_id, tag_id) VALUES (?1, ?2)",
+            params![rule_id, tag_id],
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_categorization_rules(db: State<DbConnection>) -> Result<Vec<CategorizationRule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_rules(&conn)
+}
+
+#[tauri::command]
+pub fn create_categorization_rule(db: State<DbConnection>, rule: CategorizationRule) -> Result<i64, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_categorization_rule")?;
+    validate_rule(&rule)?;
+
+    tx.execute(
+        "INSERT INTO categorization_rules (name, priority, is_active, notes_contains, amount_equals, min_amount, max_amount,
+         account_id, direction, set_category_id, set_client_id, set_project_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            rule.name, rule.priority, rule.is_active as i32, rule.notes_contains, rule.amount_equals,
+            rule.min_amount, rule.max_amount, rule.account_id, rule.direction,
+            rule.set_category_id, rule.set_client_id, rule.set_project_id
+        ],
+    ).map_err(|e| e.to_string())?;
+    let id = tx.last_insert_rowid();
+
+    save_rule_tags(&tx, id, &rule.add_tag_ids)?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn update_categorization_rule(db: State<DbConnection>, rule: CategorizationRule) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_categorization_rule")?;
+    let id = rule.id.ok_or("Rule ID required")?;
+    validate_rule(&rule)?;
+
+    tx.execute(
+        "UPDATE categorization_rules SET name = ?1, priority = ?2, is_active = ?3, notes_contains = ?4, amount_equals = ?5,
+         min_amount = ?6, max_amount = ?7, account_id = ?8, direction = ?9, set_category_id = ?10, set_client_id = ?11,
+         set_project_id = ?12 WHERE id = ?13",
+        params![
+            rule.name, rule.priority, rule.is_active as i32, rule.notes_contains, rule.amount_equals,
+            rule.min_amount, rule.max_amount, rule.account_id, rule.direction,
+            rule.set_category_id, rule.set_client_id, rule.set_project_id, id
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    save_rule_tags(&tx, id, &rule.add_tag_ids)?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_categorization_rule(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_categorization_rule")?;
+    tx.execute("DELETE FROM categorization_rule_tags WHERE rule_id = ?1", [id]).map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM categorization_rules WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Re-runs the rules over existing transactions. With `dry_run` nothing is
+/// written and the list shows what would change.
+#[tauri::command]
+pub fn reapply_categorization_rules(
+    db: State<DbConnection>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    dry_run: bool,
+) -> Result<Vec<RuleApplication>, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let rules = load_active_rules(&conn)?;
+    if rules.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Split parents are categorized per child line and reconciled rows are locked,
+    // so rules leave both alone
+    let candidates: Vec<(Transaction, Vec<i64>)> = {
+        let mut stmt = conn.prepare("
+            SELECT id, date, amount, direction, from_account_id, to_account_id, category_id,
+                   client_id, project_id, investment_id, goal_id, notes, external_id, currency, to_amount
+            FROM transactions t
+            WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+            AND NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)
+            AND t.cleared_status != 'reconciled'
+            ORDER BY date, id
+        ").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![start_date, end_date], |row| {
+            Ok(Transaction {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                amount: row.get(2)?,
+                direction: row.get(3)?,
+                from_account_id: row.get(4)?,
+                to_account_id: row.get(5)?,
+                category_id: row.get(6)?,
+                client_id: row.get(7)?,
+                project_id: row.get(8)?,
+                investment_id: row.get(9)?,
+                goal_id: row.get(10)?,
+                notes: row.get(11)?,
+                external_id: row.get(12)?,
+                currency: row.get(13)?,
+                to_amount: row.get(14)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+        let mut tag_stmt = conn
+            .prepare("SELECT tag_id FROM transaction_tags WHERE transaction_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut out = Vec::new();
+        for t in rows {
+            let tags = tag_stmt
+                .query_map([t.id], |row| row.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<i64>, _>>()
+                .map_err(|e| e.to_string())?;
+            out.push((t, tags));
+        }
+        out
+    };
+
+    let mut changes = Vec::new();
+    for (t, tags) in candidates {
+        let rule = match first_matching_rule(&rules, &t) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        let new_category_id = rule.set_category_id.unwrap_or(t.category_id);
+        let new_client_id = rule.set_client_id.or(t.client_id);
+        let new_project_id = rule.set_project_id.or(t.project_id);
+        let added_tag_ids: Vec<i64> = rule.add_tag_ids.iter().filter(|id| !tags.contains(id)).copied().collect();
+
+        if new_category_id == t.category_id && new_client_id == t.client_id
+            && new_project_id == t.project_id && added_tag_ids.is_empty() {
+            continue;
+        }
+
+        changes.push(RuleApplication {
+            transaction_id: t.id.unwrap_or_default(),
+            date: t.date,
+            amount: t.amount,
+            notes: t.notes,
+            rule_id: rule.id.unwrap_or_default(),
+            rule_name: rule.name.clone(),
+            old_category_id: t.category_id,
+            new_category_id,
+            old_client_id: t.client_id,
+            new_client_id,
+            old_project_id: t.project_id,
+            new_project_id,
+            added_tag_ids,
+        });
+    }
+
+    if dry_run {
+        return Ok(changes);
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let scope = ChangeScope::begin(&tx, "reapply_categorization_rules")?;
+    for change in &changes {
+        tx.execute(
+            "UPDATE transactions SET category_id = ?1, client_id = ?2, project_id = ?3 WHERE id = ?4",
+            params![change.new_category_id, change.new_client_id, change.new_project_id, change.transaction_id],
+        ).map_err(|e| e.to_string())?;
+        for tag_id in &change.added_tag_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+                params![change.transaction_id, tag_id],
+            ).map_err(|e| e.to_string())?;
+        }
+        refresh_search_index(&tx, change.transaction_id)?;
+    }
+    drop(scope);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(changes)
+}
@@ -0,0 +1,114 @@
+use rusqlite::{params, Result};
+use serde::Serialize;
+use tauri::State;
+use crate::db::DbConnection;
+use super::transactions::{map_transaction_row, TransactionWithDetails, TRANSACTION_DETAILS_SELECT};
+
+#[derive(Debug, Serialize)]
+pub struct TransactionSearchResult {
+    pub transaction: TransactionWithDetails,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+// One document per transaction. Split lines contribute their notes, categories,
+// clients, projects and tags so a split parent is found by any child line.
+const SEARCH_DOCUMENT_SELECT: &str = "
+    SELECT t.id,
+        TRIM(COALESCE(t.notes, '') || ' ' || COALESCE((SELECT GROUP_CONCAT(s.notes, ' ') FROM transaction_splits s WHERE s.transaction_id = t.id), '')),
+        TRIM(COALESCE(c.name, '') || ' ' || COALESCE((SELECT GROUP_CONCAT(sc.name, ' ') FROM transaction_splits s JOIN categories sc ON s.category_id = sc.id WHERE s.transaction_id = t.id), '')),
+        TRIM(COALESCE(cl.name, '') || ' ' || COALESCE((SELECT GROUP_CONCAT(scl.name, ' ') FROM transaction_splits s JOIN clients scl ON s.client_id = scl.id WHERE s.transaction_id = t.id), '')),
+        TRIM(COALESCE(p.name, '') || ' ' || COALESCE((SELECT GROUP_CONCAT(sp.name, ' ') FROM transaction_splits s JOIN projects sp ON s.project_id = sp.id WHERE s.transaction_id = t.id), '')),
+        TRIM(COALESCE((SELECT GROUP_CONCAT(tg.name, ' ') FROM transaction_tags tt JOIN tags tg ON tt.tag_id = tg.id WHERE tt.transaction_id = t.id), '') || ' ' ||
+             COALESCE((SELECT GROUP_CONCAT(tg.name, ' ') FROM transaction_split_tags st JOIN transaction_splits s ON st.split_id = s.id JOIN tags tg ON st.tag_id = tg.id WHERE s.transaction_id = t.id), ''))
+    FROM transactions t
+    LEFT JOIN categories c ON t.category_id = c.id
+    LEFT JOIN clients cl ON t.client_id = cl.id
+    LEFT JOIN projects p ON t.project_id = p.id";
+
+/// Re-indexes a single transaction; removes it from the index if it no longer exists.
+pub(crate) fn refresh_search_index(conn: &rusqlite::Connection, transaction_id: i64) -> Result<(), String> {
+    conn.execute("DELETE FROM transaction_search WHERE rowid = ?1", [transaction_id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!("INSERT INTO transaction_search (rowid, notes, category, client, project, tags) {} WHERE t.id = ?1", SEARCH_DOCUMENT_SELECT),
+        [transaction_id],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub(crate) fn rebuild_search_index(conn: &rusqlite::Connection) -> Result<(), String> {
+    conn.execute("DELETE FROM transaction_search", []).map_err(|e| e.to_string())?;
+    conn.execute(
+        &format!("INSERT INTO transaction_search (rowid, notes, category, client, project, tags) {}", SEARCH_DOCUMENT_SELECT),
+        [],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Turns free text into an FTS5 query of quoted prefix terms, so user input can
+// never be parsed as FTS operators ("amazon refund" -> "amazon"* "refund"*).
+fn build_match_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() { None } else { Some(terms.join(" ")) }
+}
+
+#[tauri::command]
+pub fn search_transactions(
+    db: State<DbConnection>,
+    query: String,
+    limit: Option<i64>,
+) -> Result<Vec<TransactionSearchResult>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let match_query = match build_match_query(&query) {
+        Some(q) => q,
+        None => return Ok(Vec::new()),
+    };
+
+    // 1. Ranked matches with highlighted snippets (bm25: lower is better)
+    let mut stmt = conn.prepare("
+        SELECT rowid, snippet(transaction_search, -1, '<mark>', '</mark>', '…', 12), bm25(transaction_search)
+        FROM transaction_search
+        WHERE transaction_search MATCH ?1
+        ORDER BY bm25(transaction_search)
+        LIMIT ?2
+    ").map_err(|e| e.to_string())?;
+
+    let hits: Vec<(i64, String, f64)> = stmt
+        .query_map(params![match_query, limit.unwrap_or(50)], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if hits.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // 2. Details for all hits in one query, then restore rank order
+    let placeholders = vec!["?"; hits.len()].join(", ");
+    let mut detail_stmt = conn
+        .prepare(&format!("{} WHERE t.id IN ({})", TRANSACTION_DETAILS_SELECT, placeholders))
+        .map_err(|e| e.to_string())?;
+    let mut details: Vec<TransactionWithDetails> = detail_stmt
+        .query_map(rusqlite::params_from_iter(hits.iter().map(|h| h.0)), map_transaction_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut results = Vec::new();
+    for (id, snippet, rank) in hits {
+        if let Some(pos) = details.iter().position(|t| t.id == id) {
+            results.push(TransactionSearchResult {
+                transaction: details.swap_remove(pos),
+                snippet,
+                rank,
+            });
+        }
+    }
+
+    Ok(results)
+}
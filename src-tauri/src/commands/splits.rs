@@ -0,0 +1,171 @@
+use rusqlite::{params, Result};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::DbConnection;
+use super::journal::ChangeScope;
+use super::reconcile::ensure_not_reconciled;
+use super::search::refresh_search_index;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionSplit {
+    pub id: Option<i64>,
+    pub transaction_id: Option<i64>,
+    pub category_id: i64,
+    pub amount: f64,
+    pub client_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub notes: Option<String>,
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionSplitWithDetails {
+    pub id: i64,
+    pub transaction_id: i64,
+    pub category_id: i64,
+    pub category_name: String,
+    pub amount: f64,
+    pub client_id: Option<i64>,
+    pub client_name: Option<String>,
+    pub project_id: Option<i64>,
+    pub project_name: Option<String>,
+    pub notes: Option<String>,
+    pub tag_ids: Vec<i64>,
+    pub tags: Vec<String>,
+}
+
+pub(crate) fn load_splits(conn: &rusqlite::Connection, transaction_id: i64) -> Result<Vec<TransactionSplitWithDetails>, String> {
+    let mut stmt = conn.prepare("
+        SELECT s.id, s.transaction_id, s.category_id, c.name, s.amount,
+               s.client_id, cl.name, s.project_id, p.name, s.notes
+        FROM transaction_splits s
+        LEFT JOIN categories c ON s.category_id = c.id
+        LEFT JOIN clients cl ON s.client_id = cl.id
+        LEFT JOIN projects p ON s.project_id = p.id
+        WHERE s.transaction_id = ?1
+        ORDER BY s.id
+    ").map_err(|e| e.to_string())?;
+
+    let mut splits = stmt.query_map([transaction_id], |row| {
+        Ok(TransactionSplitWithDetails {
+            id: row.get(0)?,
+            transaction_id: row.get(1)?,
+            category_id: row.get(2)?,
+            category_name: row.get(3)?,
+            amount: row.get(4)?,
+            client_id: row.get(5)?,
+            client_name: row.get(6)?,
+            project_id: row.get(7)?,
+            project_name: row.get(8)?,
+            notes: row.get(9)?,
+            tag_ids: Vec::new(),
+            tags: Vec::new(),
+        })
+    })
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut tag_stmt = conn
+        .prepare("SELECT t.id, t.name FROM tags t JOIN transaction_split_tags st ON t.id = st.tag_id WHERE st.split_id = ?1")
+        .map_err(|e| e.to_string())?;
+    for split in &mut splits {
+        let tags = tag_stmt
+            .query_map([split.id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        for (tag_id, name) in tags {
+            split.tag_ids.push(tag_id);
+            split.tags.push(name);
+        }
+    }
+
+    Ok(splits)
+}
+
+/// Fails if a split parent's lines no longer add up to `amount`.
+pub(crate) fn validate_split_total(conn: &rusqlite::Connection, transaction_id: i64, amount: f64) -> Result<(), String> {
+    let (count, total): (i64, f64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(amount), 0) FROM transaction_splits WHERE transaction_id = ?1",
+        [transaction_id],
+        |r| Ok((r.get(0)?, r.get(1)?))
+    ).map_err(|e| e.to_string())?;
+
+    if count > 0 && (total - amount).abs() > 0.005 {
+        return Err(format!("Split lines total {:.2} but the transaction amount is {:.2}", total, amount));
+    }
+    Ok(())
+}
+
+pub(crate) fn delete_splits(conn: &rusqlite::Connection, transaction_id: i64) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM transaction_split_tags WHERE split_id IN (SELECT id FROM transaction_splits WHERE transaction_id = ?1)",
+        [transaction_id],
+    ).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM transaction_splits WHERE transaction_id = ?1", [transaction_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_transaction_splits(
+    db: State<DbConnection>,
+    transaction_id: i64,
+) -> Result<Vec<TransactionSplitWithDetails>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_splits(&conn, transaction_id)
+}
+
+/// Replaces all child lines of a transaction. An empty list turns it back
+/// into a regular single-category transaction.
+#[tauri::command]
+pub fn set_transaction_splits(
+    db: State<DbConnection>,
+    transaction_id: i64,
+    splits: Vec<TransactionSplit>,
+) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    ensure_not_reconciled(&conn, transaction_id)?;
+
+    let parent_amount: f64 = conn.query_row(
+        "SELECT amount FROM transactions WHERE id = ?1",
+        [transaction_id],
+        |r| r.get(0)
+    ).map_err(|_| "Transaction not found".to_string())?;
+
+    if splits.len() == 1 {
+        return Err("A split needs at least two lines".to_string());
+    }
+    if splits.iter().any(|s| s.amount <= 0.0) {
+        return Err("Split line amounts must be positive".to_string());
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "set_transaction_splits")?;
+    delete_splits(&tx, transaction_id)?;
+
+    for split in &splits {
+        tx.execute(
+            "INSERT INTO transaction_splits (transaction_id, category_id, amount, client_id, project_id, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![transaction_id, split.category_id, split.amount, split.client_id, split.project_id, split.notes],
+        ).map_err(|e| e.to_string())?;
+        let split_id = tx.last_insert_rowid();
+
+        for tag_id in &split.tag_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO transaction_split_tags (split_id, tag_id) VALUES (?1, ?2)",
+                params![split_id, tag_id],
+            ).map_err(|e| e.to_string())?;
+        }
+    }
+
+    validate_split_total(&tx, transaction_id, parent_amount)?;
+    refresh_search_index(&tx, transaction_id)?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use super::import::{parse_import_amount, parse_import_date};
+use super::transactions::Transaction;
+
+/// Defaults applied to every parsed entry; the statement itself only knows
+/// dates, amounts and payees.
+pub struct ParseContext {
+    pub account_id: i64,
+    pub expense_category_id: i64,
+    pub income_category_id: i64,
+    pub date_format: Option<String>,
+}
+
+/// Parsed entries with the file line each one starts on, plus the lines of
+/// records that could not be read.
+pub struct ParsedStatement {
+    pub entries: Vec<(usize, Transaction)>,
+    pub skipped_lines: Vec<usize>,
+}
+
+fn build_transaction(ctx: &ParseContext, date: String, signed_amount: f64, notes: String, external_id: Option<String>) -> Transaction {
+    let is_income = signed_amount > 0.0;
+    Transaction {
+        id: None,
+        date,
+        amount: (signed_amount.abs() * 100.0).round() / 100.0,
+        direction: if is_income { "income" } else { "expense" }.to_string(),
+        // Same shape create_transaction sanitizes to: income has no source, expense no target
+        from_account_id: if is_income { None } else { Some(ctx.account_id) },
+        to_account_id: if is_income { Some(ctx.account_id) } else { None },
+        category_id: if is_income { ctx.income_category_id } else { ctx.expense_category_id },
+        client_id: None,
+        project_id: None,
+        investment_id: None,
+        goal_id: None,
+        notes: if notes.is_empty() { None } else { Some(notes) },
+        external_id,
+        currency: None,
+        to_amount: None,
+    }
+}
+
+/// Parses OFX and QFX statements (QFX is OFX with an Intuit header).
+pub fn parse_ofx(content: &str, ctx: &ParseContext) -> Result<ParsedStatement, String> {
+    // ASCII-only uppercasing keeps byte offsets aligned with the original text
+    let upper = content.to_ascii_uppercase();
+    let mut transactions = Vec::new();
+    let mut cursor = 0;
+
+    while let Some(pos) = upper[cursor..].find("<STMTTRN>") {
+        let line_number = content[..cursor + pos].matches('\n').count() + 1;
+        let start = cursor + pos + "<STMTTRN>".len();
+        let end = upper[start..].find("</STMTTRN>")
+            .or_else(|| upper[start..].find("<STMTTRN>"))
+            .map(|e| start + e)
+            .unwrap_or(upper.len());
+        cursor = end;
+
+        // Tags are matched upper-cased, values are read from the original text.
+        // SGML (OFX 1.x) has no closing tags, so a value ends at the next tag or line.
+        let block_upper = &upper[start..end];
+        let block = &content[start..end];
+        let value = |tag: &str| -> Option<String> {
+            let open = format!("<{}>", tag);
+            let value_start = block_upper.find(&open)? + open.len();
+            let rest = &block[value_start..];
+            let value_end = rest.find(['<', '\n', '\r']).unwrap_or(rest.len());
+            let v = rest[..value_end].trim();
+            if v.is_empty() { None } else { Some(v.to_string()) }
+        };
+
+        let raw_date = value("DTPOSTED").ok_or("OFX transaction is missing DTPOSTED")?;
+        let date = raw_date.get(0..8)
+            .and_then(|d| parse_import_date(d, Some("%Y%m%d")))
+            .ok_or_else(|| format!("Invalid OFX date: {}", raw_date))?;
+        let amount = value("TRNAMT")
+            .and_then(|a| parse_import_amount(&a))
+            .ok_or("OFX transaction is missing TRNAMT")?;
+        if amount == 0.0 {
+            continue;
+        }
+
+        let name = value("NAME").or_else(|| value("PAYEE")).unwrap_or_default();
+        let memo = value("MEMO").unwrap_or_default();
+        let notes = match (name.is_empty(), memo.is_empty() || memo == name) {
+            (false, false) => format!("{} - {}", name, memo),
+            (false, true) => name,
+            (true, _) => memo,
+        };
+
+        transactions.push((line_number, build_transaction(ctx, date, amount, notes, value("FITID"))));
+    }
+
+    if transactions.is_empty() && !upper.contains("<OFX>") {
+        return Err("File does not look like an OFX/QFX statement".to_string());
+    }
+
+    Ok(ParsedStatement {
+        entries: transactions,
+        skipped_lines: Vec::new(),
+    })
+}
+
+// QIF comes from Quicken, which writes US month-first dates and years after
+// 2000 as 1/31'26. Other layouts only parse when the caller names a format.
+fn parse_qif_date(value: &str, date_format: Option<&str>) -> Option<String> {
+    let value = value.replace('\'', "/").replace(' ', "");
+    if date_format.is_some() {
+        return parse_import_date(&value, date_format);
+    }
+    ["%m/%d/%y", "%m/%d/%Y"].into_iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(&value, fmt).ok())
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .or_else(|| parse_import_date(&value, Some("%Y-%m-%d")))
+}
+
+/// Parses QIF bank/card registers. QIF has no transaction ids, so a stable
+/// fingerprint of date, amount, payee and occurrence is used as external id.
+/// Records without a readable date or amount are reported as skipped.
+pub fn parse_qif(content: &str, ctx: &ParseContext) -> Result<ParsedStatement, String> {
+    let mut transactions = Vec::new();
+    let mut skipped_lines = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    let mut record_line: Option<usize> = None;
+    let mut date: Option<String> = None;
+    let mut amount: Option<f64> = None;
+    let mut payee = String::new();
+    let mut memo = String::new();
+
+    for (idx, raw_line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('!') {
+            continue;
+        }
+        if !line.starts_with('^') && record_line.is_none() {
+            record_line = Some(idx + 1);
+        }
+
+        let mut chars = line.chars();
+        let code = chars.next();
+        let value = chars.as_str().trim();
+        match code {
+            Some('D') => date = parse_qif_date(value, ctx.date_format.as_deref()),
+            Some('T') | Some('U') => amount = parse_import_amount(value),
+            Some('P') => payee = value.to_string(),
+            Some('M') => memo = value.to_string(),
+            Some('^') => {
+                let Some(line_number) = record_line.take() else {
+                    continue;
+                };
+                match (date.take(), amount.take()) {
+                    (Some(d), Some(a)) if a != 0.0 => {
+                        let key = format!("{}|{:.2}|{}", d, a, payee.to_lowercase());
+                        let occurrence = seen.entry(key.clone()).or_insert(0);
+                        *occurrence += 1;
+                        let external_id = format!("qif:{}|{}", key, occurrence);
+
+                        let notes = match (payee.is_empty(), memo.is_empty()) {
+                            (false, false) => format!("{} - {}", payee, memo),
+                            (false, true) => payee.clone(),
+                            (true, _) => memo.clone(),
+                        };
+                        transactions.push((line_number, build_transaction(ctx, d, a, notes, Some(external_id))));
+                    }
+                    _ => skipped_lines.push(line_number),
+                }
+                payee.clear();
+                memo.clear();
+            }
+            _ => {}
+        }
+    }
+    // A last record missing its ^ terminator
+    if let Some(line_number) = record_line {
+        skipped_lines.push(line_number);
+    }
+
+    if transactions.is_empty() && !content.contains('^') {
+        return Err("File does not look like a QIF statement".to_string());
+    }
+
+    Ok(ParsedStatement {
+        entries: transactions,
+        skipped_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(date_format: Option<&str>) -> ParseContext {
+        ParseContext {
+            account_id: 1,
+            expense_category_id: 2,
+            income_category_id: 3,
+            date_format: date_format.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn import_date_prefers_given_format() {
+        assert_eq!(parse_import_date("1/5/26", Some("%m/%d/%y")).as_deref(), Some("2026-01-05"));
+        assert_eq!(parse_import_date("1/5/26", None).as_deref(), Some("2026-05-01"));
+        assert_eq!(parse_import_date("1/31/26", None), None);
+        assert_eq!(parse_import_date("2026-01-31", Some("%d/%m/%Y")).as_deref(), Some("2026-01-31"));
+        assert_eq!(parse_import_date("  ", None), None);
+    }
+
+    #[test]
+    fn ofx_sgml_blocks_without_closing_tags() {
+        let content = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKTRANLIST>\n\
+            <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20260105120000[-5:EST]\n<TRNAMT>-45.00\n<FITID>A1\n<NAME>Coffee\n<MEMO>Card 1234\n</STMTTRN>\n\
+            <STMTTRN>\n<TRNTYPE>CREDIT\n<DTPOSTED>20260110\n<TRNAMT>1,200.00\n<FITID>A2\n<NAME>Salary\n<MEMO>Salary\n\
+            <STMTTRN>\n<DTPOSTED>20260111\n<TRNAMT>0.00\n<FITID>A3\n\
+            </BANKTRANLIST>\n</OFX>\n";
+        let parsed = parse_ofx(content, &ctx(None)).unwrap();
+        assert_eq!(parsed.entries.len(), 2);
+        let (line, coffee) = &parsed.entries[0];
+        assert_eq!((*line, coffee.date.as_str(), coffee.amount, coffee.direction.as_str()), (6, "2026-01-05", 45.0, "expense"));
+        assert_eq!((coffee.from_account_id, coffee.category_id), (Some(1), 2));
+        assert_eq!(coffee.notes.as_deref(), Some("Coffee - Card 1234"));
+        assert_eq!(coffee.external_id.as_deref(), Some("A1"));
+        let (line, salary) = &parsed.entries[1];
+        assert_eq!((*line, salary.amount, salary.direction.as_str()), (14, 1200.0, "income"));
+        assert_eq!((salary.to_account_id, salary.category_id), (Some(1), 3));
+        // A memo repeating the name is not doubled
+        assert_eq!(salary.notes.as_deref(), Some("Salary"));
+    }
+
+    #[test]
+    fn ofx_xml_tags_in_any_case() {
+        let content = "<ofx><stmttrn><dtposted>20260201</dtposted><trnamt>-5.5</trnamt><fitid>X9</fitid><payee>Shop</payee></stmttrn></ofx>";
+        let parsed = parse_ofx(content, &ctx(None)).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        let (line, t) = &parsed.entries[0];
+        assert_eq!((*line, t.date.as_str(), t.amount), (1, "2026-02-01", 5.5));
+        assert_eq!((t.notes.as_deref(), t.external_id.as_deref()), (Some("Shop"), Some("X9")));
+    }
+
+    #[test]
+    fn ofx_rejects_bad_input() {
+        assert!(matches!(parse_ofx("<OFX><STMTTRN><TRNAMT>-5</STMTTRN></OFX>", &ctx(None)), Err(e) if e.contains("DTPOSTED")));
+        assert!(matches!(parse_ofx("<OFX><STMTTRN><DTPOSTED>2026</STMTTRN></OFX>", &ctx(None)), Err(e) if e.contains("Invalid OFX date")));
+        assert!(parse_ofx("date,amount\n2026-01-01,5\n", &ctx(None)).is_err());
+        assert!(parse_ofx("<OFX></OFX>", &ctx(None)).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn qif_dates_are_month_first() {
+        let content = "!Type:Bank\nD1/31'26\nT-45.00\nPCoffee\n^\nD1/5/26\nT1,200.00\nPSalary\n^\nD01/07/2026\nT-10\n^\n";
+        let parsed = parse_qif(content, &ctx(None)).unwrap();
+        let dates: Vec<_> = parsed.entries.iter().map(|(_, t)| t.date.as_str()).collect();
+        assert_eq!(dates, ["2026-01-31", "2026-01-05", "2026-01-07"]);
+        assert_eq!(parsed.entries.iter().map(|(l, _)| *l).collect::<Vec<_>>(), [2, 6, 10]);
+        assert_eq!(parsed.entries[0].1.direction, "expense");
+        assert_eq!(parsed.entries[1].1.direction, "income");
+        assert_eq!(parsed.entries[1].1.amount, 1200.0);
+        assert!(parsed.skipped_lines.is_empty());
+    }
+
+    #[test]
+    fn qif_honours_caller_date_format() {
+        let parsed = parse_qif("D05/01/26\nT-5\n^\n", &ctx(Some("%d/%m/%y"))).unwrap();
+        assert_eq!(parsed.entries[0].1.date, "2026-01-05");
+    }
+
+    #[test]
+    fn qif_reports_records_without_a_date() {
+        let content = "!Type:Bank\nD31/31/26\nT-45.00\n^\nT-5\nPNo date\n^\nD2/1'26\nT-7\n^\nD2/2'26\nT-8\n";
+        let parsed = parse_qif(content, &ctx(None)).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.entries[0].1.date, "2026-02-01");
+        assert_eq!(parsed.skipped_lines, [2, 5, 11]);
+    }
+
+    #[test]
+    fn qif_duplicates_get_distinct_external_ids() {
+        let parsed = parse_qif("D1/5'26\nT-5\nPTea\n^\nD1/5'26\nT-5\nPTea\n^\n", &ctx(None)).unwrap();
+        let ids: Vec<_> = parsed.entries.iter().map(|(_, t)| t.external_id.clone().unwrap()).collect();
+        assert_eq!(ids, ["qif:2026-01-05|-5.00|tea|1", "qif:2026-01-05|-5.00|tea|2"]);
+    }
+}
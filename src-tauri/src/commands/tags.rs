@@ -2,6 +2,7 @@ use rusqlite::{params, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
+use super::journal::ChangeScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Tag {
@@ -37,6 +38,7 @@ pub fn create_tag(
     tag: Tag,
 ) -> Result<i64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let _change = ChangeScope::begin(&conn, "create_tag")?;
     
     conn.execute(
         "INSERT INTO tags (name) VALUES (?1)",
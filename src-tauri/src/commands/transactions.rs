@@ -1,9 +1,18 @@
-use rusqlite::{params, Result};
+use rusqlite::{params, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 use crate::db::DbConnection;
-
-#[derive(Debug, Serialize, Deserialize)]
+use super::accounts::ensure_account_open;
+use super::allocations::{allocate_transaction, reverse_allocation};
+use super::goals::record_goal_status;
+use super::journal::ChangeScope;
+use super::currency::{base_currency, rate_to_base_sql, resolve_transaction_currency};
+use super::reconcile::ensure_not_reconciled;
+use super::rules::apply_categorization_rules;
+use super::search::refresh_search_index;
+use super::splits::{delete_splits, validate_split_total};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Transaction {
     pub id: Option<i64>,
     pub date: String,
@@ -17,6 +26,9 @@ pub struct Transaction {
     pub investment_id: Option<i64>,
     pub goal_id: Option<i64>,
     pub notes: Option<String>,
+    pub external_id: Option<String>, // Bank-assigned id (OFX FITID) for imported rows
+    pub currency: Option<String>, // Defaults to the paying account's; others are converted at the day's rate
+    pub to_amount: Option<f64>,   // Credited amount for transfers into another currency
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +54,13 @@ pub struct TransactionWithDetails {
     pub notes: Option<String>,
     pub tags: Vec<String>,
     pub category_is_investment: bool,
+    pub is_split: bool,
+    pub cleared_status: String, // uncleared, cleared, reconciled
+    pub currency: String,
+    pub to_amount: Option<f64>,
+    pub original_currency: Option<String>, // Set when entered in another currency
+    pub original_amount: Option<f64>,
+    pub original_rate: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,6 +70,28 @@ pub struct TransactionFilters {
     pub direction: Option<String>,
     pub from_account_id: Option<i64>,
     pub to_account_id: Option<i64>,
+    pub account_id: Option<i64>, // Either side of the transaction
+    pub category_id: Option<i64>,
+    pub client_id: Option<i64>,
+    pub project_id: Option<i64>,
+    pub goal_id: Option<i64>,
+    pub investment_id: Option<i64>,
+    pub tag_id: Option<i64>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub notes_query: Option<String>,
+    pub sort_by: Option<String>,    // date, amount, category, created
+    pub sort_order: Option<String>, // asc, desc
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionPage {
+    pub items: Vec<TransactionWithDetails>,
+    pub total_count: i64,
+    pub offset: i64,
+    pub limit: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,14 +99,16 @@ pub struct AccountBalance {
     pub account_id: i64,
     pub account_name: String,
     pub account_type: String,
-    pub opening_balance: f64,
+    pub currency: String,
+    pub opening_balance: f64, // In the account's currency
     pub current_balance: f64,
 }
 
 #[derive(Debug, Serialize)]
 pub struct TransactionBalances {
     pub accounts: Vec<AccountBalance>,
-    pub total_opening_balance: f64,
+    pub base_currency: String,
+    pub total_opening_balance: f64, // Converted to the base currency
     pub total_current_balance: f64,
 }
 
@@ -76,13 +119,16 @@ pub fn get_transaction_balances(
 ) -> Result<TransactionBalances, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
-    // Get all accounts with their opening balances, names, and types
+    // Get all accounts with their opening balances (native and in base currency), names, and types
     let mut stmt = conn
-        .prepare("SELECT id, name, type, opening_balance FROM accounts ORDER BY name")
+        .prepare(&format!(
+            "SELECT id, name, type, currency, opening_balance, opening_balance * COALESCE({}, 0) FROM accounts a ORDER BY name",
+            rate_to_base_sql("a.currency", "date(a.created_at)")
+        ))
         .map_err(|e| e.to_string())?;
     
-    let accounts: Vec<(i64, String, String, f64)> = stmt
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+    let accounts: Vec<(i64, String, String, String, f64, f64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)))
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -103,72 +149,42 @@ pub fn get_transaction_balances(
     } else {
         None
     };
+
+    // Net flow into an account as (native, base)
+    let net_flow = |account_id: i64, condition: &str, date: Option<&String>| -> (f64, f64) {
+        conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(net_amount), 0), COALESCE(SUM(net_base_amount), 0) FROM account_daily_balances WHERE account_id = ?1 AND (?2 IS NULL OR date {} ?2)",
+                condition
+            ),
+            params![account_id, date],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).unwrap_or((0.0, 0.0))
+    };
     
     // Calculate balances for each account
-    for (account_id, account_name, account_type, account_opening_balance) in accounts {
+    for (account_id, account_name, account_type, currency, account_opening_balance, base_opening_balance) in accounts {
         // Calculate balance at start of period (opening balance)
-        let incoming_before: f64 = if let Some(ref start) = start_date {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE to_account_id = ?1 AND date < ?2",
-                params![account_id, start.clone()],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        } else {
-            0.0
-        };
-        
-        let outgoing_before: f64 = if let Some(ref start) = start_date {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE from_account_id = ?1 AND date < ?2",
-                params![account_id, start.clone()],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        } else {
-            0.0
+        let (before, base_before) = match start_date {
+            Some(ref start) => net_flow(account_id, "<", Some(start)),
+            None => (0.0, 0.0),
         };
-        
-        let account_opening = account_opening_balance + incoming_before - outgoing_before;
+        let account_opening = account_opening_balance + before;
         
         // Calculate balance at end of period (current balance)
-        let incoming_upto: f64 = if let Some(ref end) = end_date {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE to_account_id = ?1 AND date <= ?2",
-                params![account_id, end.clone()],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        } else {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE to_account_id = ?1",
-                [account_id],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        };
-        
-        let outgoing_upto: f64 = if let Some(ref end) = end_date {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE from_account_id = ?1 AND date <= ?2",
-                params![account_id, end.clone()],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        } else {
-            conn.query_row(
-                "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE from_account_id = ?1",
-                [account_id],
-                |row| row.get(0)
-            ).unwrap_or(0.0)
-        };
-        
-        let account_current = account_opening_balance + incoming_upto - outgoing_upto;
+        let (upto, base_upto) = net_flow(account_id, "<=", end_date.as_ref());
+        let account_current = account_opening_balance + upto;
         
         // Add to totals
-        total_opening_balance += account_opening;
-        total_current_balance += account_current;
+        total_opening_balance += base_opening_balance + base_before;
+        total_current_balance += base_opening_balance + base_upto;
         
         // Add account balance to result
         account_balances.push(AccountBalance {
             account_id,
             account_name,
             account_type,
+            currency,
             opening_balance: account_opening,
             current_balance: account_current,
         });
@@ -176,109 +192,222 @@ pub fn get_transaction_balances(
     
     Ok(TransactionBalances {
         accounts: account_balances,
+        base_currency: base_currency(&conn)?,
         total_opening_balance,
         total_current_balance,
     })
 }
 
-#[tauri::command]
-pub fn get_transactions(
-    db: State<DbConnection>,
-    filters: Option<TransactionFilters>,
-) -> Result<Vec<TransactionWithDetails>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
-    let mut sql = String::from(
-        "SELECT 
-            t.id, t.date, t.amount, t.direction,
-            t.from_account_id, fa.name as from_account_name,
-            t.to_account_id, ta.name as to_account_name,
-            t.category_id, c.name as category_name,
-            t.client_id, cl.name as client_name,
-            t.project_id, p.name as project_name,
-            t.investment_id, i.name as investment_name,
-            t.goal_id, g.name as goal_name,
-            t.notes, c.is_investment as category_is_investment
-        FROM transactions t
-        LEFT JOIN accounts fa ON t.from_account_id = fa.id
-        LEFT JOIN accounts ta ON t.to_account_id = ta.id
-        LEFT JOIN categories c ON t.category_id = c.id
-        LEFT JOIN clients cl ON t.client_id = cl.id
-        LEFT JOIN projects p ON t.project_id = p.id
-        LEFT JOIN investments i ON t.investment_id = i.id
-        LEFT JOIN goals g ON t.goal_id = g.id
-        WHERE 1=1"
-    );
-    
-    if let Some(f) = &filters {
-        if let Some(start) = &f.start_date {
-            sql.push_str(&format!(" AND t.date >= '{}'", start));
-        }
-        if let Some(end) = &f.end_date {
-            sql.push_str(&format!(" AND t.date <= '{}'", end));
-        }
-        if let Some(dir) = &f.direction {
-            if !dir.is_empty() {
-                sql.push_str(&format!(" AND t.direction = '{}'", dir));
-            }
-        }
-        if let Some(from_id) = f.from_account_id {
-            sql.push_str(&format!(" AND t.from_account_id = {}", from_id));
+pub(crate) const TRANSACTION_DETAILS_SELECT: &str = "
+    SELECT 
+        t.id, t.date, t.amount, t.direction,
+        t.from_account_id, fa.name as from_account_name,
+        t.to_account_id, ta.name as to_account_name,
+        t.category_id, c.name as category_name,
+        t.client_id, cl.name as client_name,
+        t.project_id, p.name as project_name,
+        t.investment_id, i.name as investment_name,
+        t.goal_id, g.name as goal_name,
+        t.notes, c.is_investment as category_is_investment,
+        EXISTS(SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id) as is_split,
+        (SELECT GROUP_CONCAT(tg.name, char(31)) FROM transaction_tags tt JOIN tags tg ON tt.tag_id = tg.id WHERE tt.transaction_id = t.id) as tag_names,
+        t.cleared_status, t.currency, t.to_amount, t.original_currency, t.original_amount, t.original_rate
+    FROM transactions t
+    LEFT JOIN accounts fa ON t.from_account_id = fa.id
+    LEFT JOIN accounts ta ON t.to_account_id = ta.id
+    LEFT JOIN categories c ON t.category_id = c.id
+    LEFT JOIN clients cl ON t.client_id = cl.id
+    LEFT JOIN projects p ON t.project_id = p.id
+    LEFT JOIN investments i ON t.investment_id = i.id
+    LEFT JOIN goals g ON t.goal_id = g.id";
+
+pub(crate) fn map_transaction_row(row: &rusqlite::Row) -> rusqlite::Result<TransactionWithDetails> {
+    let tag_names: Option<String> = row.get(21)?;
+    Ok(TransactionWithDetails {
+        id: row.get(0)?,
+        date: row.get(1)?,
+        amount: row.get(2)?,
+        direction: row.get(3)?,
+        from_account_id: row.get(4)?,
+        from_account_name: row.get(5)?,
+        to_account_id: row.get(6)?,
+        to_account_name: row.get(7)?,
+        category_id: row.get(8)?,
+        category_name: row.get(9)?,
+        client_id: row.get(10)?,
+        client_name: row.get(11)?,
+        project_id: row.get(12)?,
+        project_name: row.get(13)?,
+        investment_id: row.get(14)?,
+        investment_name: row.get(15)?,
+        goal_id: row.get(16)?,
+        goal_name: row.get(17)?,
+        notes: row.get(18)?,
+        category_is_investment: row.get::<_, Option<i32>>(19)?.unwrap_or(0) != 0,
+        is_split: row.get::<_, i32>(20)? != 0,
+        tags: tag_names
+            .map(|names| names.split('\u{1f}').map(String::from).collect())
+            .unwrap_or_default(),
+        cleared_status: row.get(22)?,
+        currency: row.get(23)?,
+        to_amount: row.get(24)?,
+        original_currency: row.get(25)?,
+        original_amount: row.get(26)?,
+        original_rate: row.get(27)?,
+    })
+}
+
+// Builds the WHERE clause with bound parameters. Category, client, project and
+// tag filters also match split lines so a split parent is found by any child.
+pub(crate) fn apply_transaction_filters(sql: &mut String, filters: &TransactionFilters, params: &mut Vec<rusqlite::types::Value>) {
+    if let Some(start) = &filters.start_date {
+        sql.push_str(" AND t.date >= ?");
+        params.push(start.clone().into());
+    }
+    if let Some(end) = &filters.end_date {
+        sql.push_str(" AND t.date <= ?");
+        params.push(end.clone().into());
+    }
+    if let Some(dir) = &filters.direction {
+        if !dir.is_empty() {
+            sql.push_str(" AND t.direction = ?");
+            params.push(dir.clone().into());
         }
-        if let Some(to_id) = f.to_account_id {
-            sql.push_str(&format!(" AND t.to_account_id = {}", to_id));
+    }
+    if let Some(from_id) = filters.from_account_id {
+        sql.push_str(" AND t.from_account_id = ?");
+        params.push(from_id.into());
+    }
+    if let Some(to_id) = filters.to_account_id {
+        sql.push_str(" AND t.to_account_id = ?");
+        params.push(to_id.into());
+    }
+    if let Some(account_id) = filters.account_id {
+        sql.push_str(" AND (t.from_account_id = ? OR t.to_account_id = ?)");
+        params.push(account_id.into());
+        params.push(account_id.into());
+    }
+    if let Some(category_id) = filters.category_id {
+        sql.push_str(" AND (t.category_id = ? OR EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id AND s.category_id = ?))");
+        params.push(category_id.into());
+        params.push(category_id.into());
+    }
+    if let Some(client_id) = filters.client_id {
+        sql.push_str(" AND (t.client_id = ? OR EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id AND s.client_id = ?))");
+        params.push(client_id.into());
+        params.push(client_id.into());
+    }
+    if let Some(project_id) = filters.project_id {
+        sql.push_str(" AND (t.project_id = ? OR EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id AND s.project_id = ?))");
+        params.push(project_id.into());
+        params.push(project_id.into());
+    }
+    if let Some(goal_id) = filters.goal_id {
+        sql.push_str(" AND t.goal_id = ?");
+        params.push(goal_id.into());
+    }
+    if let Some(investment_id) = filters.investment_id {
+        sql.push_str(" AND t.investment_id = ?");
+        params.push(investment_id.into());
+    }
+    if let Some(tag_id) = filters.tag_id {
+        sql.push_str(" AND (EXISTS (SELECT 1 FROM transaction_tags tt WHERE tt.transaction_id = t.id AND tt.tag_id = ?)
+            OR EXISTS (SELECT 1 FROM transaction_split_tags st JOIN transaction_splits s ON st.split_id = s.id WHERE s.transaction_id = t.id AND st.tag_id = ?))");
+        params.push(tag_id.into());
+        params.push(tag_id.into());
+    }
+    if let Some(min) = filters.min_amount {
+        sql.push_str(" AND t.amount >= ?");
+        params.push(min.into());
+    }
+    if let Some(max) = filters.max_amount {
+        sql.push_str(" AND t.amount <= ?");
+        params.push(max.into());
+    }
+    if let Some(q) = &filters.notes_query {
+        let q = q.trim();
+        if !q.is_empty() {
+            let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            sql.push_str(" AND t.notes LIKE ? ESCAPE '\\'");
+            params.push(format!("%{}%", escaped).into());
         }
     }
-    
-    sql.push_str(" ORDER BY t.date DESC, t.id DESC");
-    
+}
+
+// Sort columns are whitelisted; only the direction keyword is ever interpolated
+fn transaction_order_clause(filters: &TransactionFilters) -> String {
+    let column = match filters.sort_by.as_deref() {
+        Some("amount") => "t.amount",
+        Some("category") => "c.name",
+        Some("created") => "t.created_at",
+        _ => "t.date",
+    };
+    let order = match filters.sort_order.as_deref() {
+        Some("asc") => "ASC",
+        _ => "DESC",
+    };
+    format!(" ORDER BY {} {}, t.id {}", column, order, order)
+}
+
+fn fetch_transactions(conn: &rusqlite::Connection, filters: Option<&TransactionFilters>) -> Result<TransactionPage, String> {
+    let mut where_sql = String::from(" WHERE 1=1");
+    let mut params_vec: Vec<rusqlite::types::Value> = vec![];
+    if let Some(f) = filters {
+        apply_transaction_filters(&mut where_sql, f, &mut params_vec);
+    }
+
+    let mut sql = format!("{}{}", TRANSACTION_DETAILS_SELECT, where_sql);
+    sql.push_str(&filters.map(transaction_order_clause).unwrap_or_else(|| " ORDER BY t.date DESC, t.id DESC".to_string()));
+
+    let offset = filters.and_then(|f| f.offset).unwrap_or(0).max(0);
+    let limit = filters.and_then(|f| f.limit).filter(|l| *l > 0);
+    let mut page_params = params_vec.clone();
+    if let Some(l) = limit {
+        sql.push_str(" LIMIT ? OFFSET ?");
+        page_params.push(l.into());
+        page_params.push(offset.into());
+    } else if offset > 0 {
+        sql.push_str(" LIMIT -1 OFFSET ?");
+        page_params.push(offset.into());
+    }
+
     let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
-    let mut transactions = stmt
-        .query_map([], |row| {
-            Ok(TransactionWithDetails {
-                id: row.get(0)?,
-                date: row.get(1)?,
-                amount: row.get(2)?,
-                direction: row.get(3)?,
-                from_account_id: row.get(4)?,
-                from_account_name: row.get(5)?,
-                to_account_id: row.get(6)?,
-                to_account_name: row.get(7)?,
-                category_id: row.get(8)?,
-                category_name: row.get(9)?,
-                client_id: row.get(10)?,
-                client_name: row.get(11)?,
-                project_id: row.get(12)?,
-                project_name: row.get(13)?,
-                investment_id: row.get(14)?,
-                investment_name: row.get(15)?,
-                goal_id: row.get(16)?,
-                goal_name: row.get(17)?,
-                notes: row.get(18)?,
-                category_is_investment: row.get::<_, i32>(19)? != 0,
-                tags: Vec::new(),
-            })
-        })
+    let items = stmt
+        .query_map(rusqlite::params_from_iter(page_params), map_transaction_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
-    
-    // Load tags for each transaction
-    for transaction in &mut transactions {
-        let mut tag_stmt = conn
-            .prepare("SELECT t.name FROM tags t JOIN transaction_tags tt ON t.id = tt.tag_id WHERE tt.transaction_id = ?1")
-            .map_err(|e| e.to_string())?;
-        
-        let tags = tag_stmt
-            .query_map([transaction.id], |row| row.get(0))
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<String>, _>>()
-            .map_err(|e| e.to_string())?;
-        
-        transaction.tags = tags;
-    }
-    
-    Ok(transactions)
+
+    let total_count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM transactions t{}", where_sql),
+        rusqlite::params_from_iter(params_vec),
+        |row| row.get(0),
+    ).map_err(|e| e.to_string())?;
+
+    Ok(TransactionPage {
+        items,
+        total_count,
+        offset,
+        limit,
+    })
+}
+
+#[tauri::command]
+pub fn get_transactions(
+    db: State<DbConnection>,
+    filters: Option<TransactionFilters>,
+) -> Result<Vec<TransactionWithDetails>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(fetch_transactions(&conn, filters.as_ref())?.items)
+}
+
+#[tauri::command]
+pub fn query_transactions(
+    db: State<DbConnection>,
+    filters: Option<TransactionFilters>,
+) -> Result<TransactionPage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    fetch_transactions(&conn, filters.as_ref())
 }
 
 #[tauri::command]
@@ -287,8 +416,40 @@ pub fn create_transaction(
     transaction: Transaction,
     tag_ids: Vec<i64>,
 ) -> Result<i64, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "create_transaction")?;
+    let id = insert_transaction(&tx, &transaction, &tag_ids, true)?;
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// Shared insert path for manual entry, statement imports and loan payments:
+/// applies categorization rules when asked, sanitizes account IDs, runs the
+/// auto-allocation hook, links tags and syncs goals.
+pub(crate) fn insert_transaction(
+    conn: &rusqlite::Connection,
+    transaction: &Transaction,
+    tag_ids: &[i64],
+    apply_rules: bool,
+) -> Result<i64, String> {
+    // AUTO-CATEGORIZATION HOOK
+    let mut transaction = transaction.clone();
+    let mut tag_ids = tag_ids.to_vec();
+    if apply_rules {
+        // A matching rule always adds its tags, client and project, but only
+        // picks the category when the user left it unset
+        let chosen_category_id = transaction.category_id;
+        apply_categorization_rules(conn, &mut transaction, &mut tag_ids)?;
+        if chosen_category_id != 0 {
+            transaction.category_id = chosen_category_id;
+        }
+    }
+    if transaction.category_id == 0 {
+        return Err("Category is required".to_string());
+    }
+
     let mut from_account_id = transaction.from_account_id;
     let mut to_account_id = transaction.to_account_id;
 
@@ -299,12 +460,21 @@ pub fn create_transaction(
         _ => {} // Transfers keep both
     }
 
+    ensure_account_open(conn, from_account_id, &transaction.date)?;
+    ensure_account_open(conn, to_account_id, &transaction.date)?;
+
+    let resolved = resolve_transaction_currency(
+        conn, &transaction.date, transaction.amount, from_account_id, to_account_id,
+        transaction.currency.as_deref(), transaction.to_amount,
+    )?;
+
     conn.execute(
-        "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, client_id, project_id, investment_id, notes, goal_id)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, client_id, project_id, investment_id, notes, goal_id, external_id, currency, to_amount,
+                                   original_currency, original_amount, original_rate)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
         params![
             transaction.date,
-            transaction.amount,
+            resolved.amount,
             transaction.direction,
             from_account_id,
             to_account_id,
@@ -314,6 +484,12 @@ pub fn create_transaction(
             transaction.investment_id,
             transaction.notes,
             transaction.goal_id,
+            transaction.external_id,
+            resolved.currency,
+            resolved.to_amount,
+            resolved.original_currency,
+            resolved.original_amount,
+            resolved.original_rate,
         ],
     )
     .map_err(|e| e.to_string())?;
@@ -321,23 +497,23 @@ pub fn create_transaction(
     let transaction_id = conn.last_insert_rowid();
 
     // AUTO-ALLOCATION HOOK
-    if transaction.direction == "income" {
-        if let Some(target_acc_id) = to_account_id {
-            let _ = auto_allocate_if_matched(&conn, target_acc_id, transaction.category_id, transaction.amount, &transaction.date);
-        }
+    if transaction.direction == "income" && to_account_id.is_some() {
+        allocate_transaction(conn, transaction_id)?;
     }
     
     // Insert tags
-    for tag_id in tag_ids {
+    for tag_id in &tag_ids {
         conn.execute(
             "INSERT INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
             params![transaction_id, tag_id],
         )
         .map_err(|e| e.to_string())?;
     }
+
+    refresh_search_index(conn, transaction_id)?;
     
     if let Some(gid) = transaction.goal_id {
-        let _ = sync_goal_progress(&conn, gid);
+        let _ = sync_goal_progress(conn, gid);
     }
     
     Ok(transaction_id)
@@ -349,10 +525,13 @@ pub fn update_transaction(
     transaction: Transaction,
     tag_ids: Vec<i64>,
 ) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "update_transaction")?;
     let id = transaction.id.ok_or("Transaction ID is required")?;
+    ensure_not_reconciled(&tx, id)?;
     
-    let old_goal_id: Option<i64> = conn.query_row(
+    let old_goal_id: Option<i64> = tx.query_row(
         "SELECT goal_id FROM transactions WHERE id = ?1",
         [id],
         |row| row.get(0)
@@ -368,14 +547,37 @@ pub fn update_transaction(
         _ => {} // Transfers keep both
     }
 
-    conn.execute(
+    ensure_account_open(&tx, from_account_id, &transaction.date)?;
+    ensure_account_open(&tx, to_account_id, &transaction.date)?;
+
+    let resolved = resolve_transaction_currency(
+        &tx, &transaction.date, transaction.amount, from_account_id, to_account_id,
+        transaction.currency.as_deref(), transaction.to_amount,
+    )?;
+
+    // Split lines must keep adding up to the parent amount
+    validate_split_total(&tx, id, resolved.amount)?;
+
+    // An income's allocation batch is redone when anything it depends on changes
+    let reallocate = tx.query_row(
+        "SELECT 1 FROM transactions WHERE id = ?1
+         AND date IS ?2 AND amount IS ?3 AND direction IS ?4 AND to_account_id IS ?5 AND category_id IS ?6 AND to_amount IS ?7",
+        params![id, transaction.date, resolved.amount, transaction.direction, to_account_id, transaction.category_id, resolved.to_amount],
+        |_| Ok(()),
+    ).optional().map_err(|e| e.to_string())?.is_none();
+    if reallocate {
+        reverse_allocation(&tx, id)?;
+    }
+
+    tx.execute(
         "UPDATE transactions SET date = ?1, amount = ?2, direction = ?3, from_account_id = ?4, 
          to_account_id = ?5, category_id = ?6, client_id = ?7, project_id = ?8, investment_id = ?9, 
-         goal_id = ?10, notes = ?11 
-         WHERE id = ?12",
+         goal_id = ?10, notes = ?11, currency = ?12, to_amount = ?13,
+         original_currency = ?14, original_amount = ?15, original_rate = ?16
+         WHERE id = ?17",
         params![
             transaction.date,
-            transaction.amount,
+            resolved.amount,
             transaction.direction,
             from_account_id,
             to_account_id,
@@ -385,35 +587,48 @@ pub fn update_transaction(
             transaction.investment_id,
             transaction.goal_id,
             transaction.notes,
+            resolved.currency,
+            resolved.to_amount,
+            resolved.original_currency,
+            resolved.original_amount,
+            resolved.original_rate,
             id,
         ],
     )
     .map_err(|e| e.to_string())?;
+
+    if reallocate {
+        allocate_transaction(&tx, id)?;
+    }
     
     if let Some(og) = old_goal_id {
-        let _ = sync_goal_progress(&conn, og);
+        let _ = sync_goal_progress(&tx, og);
     }
     if let Some(ng) = transaction.goal_id {
         if Some(ng) != old_goal_id {
-            let _ = sync_goal_progress(&conn, ng);
+            let _ = sync_goal_progress(&tx, ng);
         }
     }
 
     // Update tags - delete and re-insert
-    conn.execute(
+    tx.execute(
         "DELETE FROM transaction_tags WHERE transaction_id = ?1",
         params![id],
     )
     .map_err(|e| e.to_string())?;
     
     for tag_id in tag_ids {
-        conn.execute(
+        tx.execute(
             "INSERT INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
             params![id, tag_id],
         )
         .map_err(|e| e.to_string())?;
     }
-    
+
+    refresh_search_index(&tx, id)?;
+
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -436,89 +651,36 @@ pub fn get_transaction_tags(
     
     Ok(tags)
 }
-fn auto_allocate_if_matched(conn: &rusqlite::Connection, account_id: i64, cat_id: i64, amount: f64, date: &str) -> Result<(), String> {
-    // 1. Fetch settings
-    let (target, trigger_cat, enabled): (f64, Option<i64>, i32) = conn.query_row(
-        "SELECT emergency_target, trigger_category_id, is_enabled FROM bucket_allocation_settings WHERE id = 1",
-        [],
-        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))
-    ).unwrap_or((100000.0, None, 0));
-
-    if enabled == 0 { return Ok(()); }
-    if let Some(triggered_cat) = trigger_cat {
-        if cat_id != triggered_cat { return Ok(()); }
-    } else {
-        return Ok(()); // No trigger set
-    }
-
-    // 2. Find bucket IDs for roles linked to this parent account
-    let mut emergency_id = None;
-    let mut asset_id = None;
-    let mut travel_id = None;
-
-    let mut stmt = conn.prepare("SELECT id, bucket_role FROM accounts WHERE parent_id = ?1").map_err(|e| e.to_string())?;
-    let rows = stmt.query_map([account_id], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?))).map_err(|e| e.to_string())?;
-    for row in rows {
-        if let Ok((id, role)) = row {
-            match role.as_str() {
-                "emergency" => emergency_id = Some(id),
-                "asset" => asset_id = Some(id),
-                "travel" => travel_id = Some(id),
-                _ => {}
-            }
-        }
-    }
-
-    if emergency_id.is_none() || asset_id.is_none() || travel_id.is_none() {
-        return Ok(()); // Need all 3 for the formula
-    }
-
-    // 3. Get current emergency balance
-    let em_id = emergency_id.unwrap();
-    let opening: f64 = conn.query_row("SELECT opening_balance FROM accounts WHERE id = ?1", [em_id], |r| r.get(0)).unwrap_or(0.0);
-    let incoming: f64 = conn.query_row("SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE to_account_id = ?1", [em_id], |r| r.get(0)).unwrap_or(0.0);
-    let outgoing: f64 = conn.query_row("SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE from_account_id = ?1", [em_id], |r| r.get(0)).unwrap_or(0.0);
-    let current_em_bal = opening + incoming - outgoing;
-
-    // 4. Get percentages from dynamic rules
-    let (per_em, per_asset, per_travel): (f64, f64, f64) = if current_em_bal < target * 0.5 {
-        conn.query_row("SELECT emergency_pc, asset_pc, travel_pc FROM allocation_rules WHERE tier = 1", [], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).map_err(|e| e.to_string())?
-    } else if current_em_bal < target {
-        conn.query_row("SELECT emergency_pc, asset_pc, travel_pc FROM allocation_rules WHERE tier = 2", [], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).map_err(|e| e.to_string())?
-    } else {
-        conn.query_row("SELECT emergency_pc, asset_pc, travel_pc FROM allocation_rules WHERE tier = 3", [], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).map_err(|e| e.to_string())?
-    };
+#[tauri::command]
+pub fn delete_transaction(db: State<DbConnection>, id: i64) -> Result<(), String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let change = ChangeScope::begin(&tx, "delete_transaction")?;
 
-    let amt_em = amount * per_em;
-    let amt_asset = amount * per_asset;
-    let amt_travel = amount * per_travel;
-
-    // 5. Create transfers
-    let splits = vec![
-        (emergency_id, amt_em, "Auto-Allocation: Emergency"),
-        (asset_id, amt_asset, "Auto-Allocation: Asset"),
-        (travel_id, amt_travel, "Auto-Allocation: Travel"),
-    ];
-
-    for (target_id, amt, note) in splits {
-        if let Some(tid) = target_id {
-            if amt > 0.0 {
-                let _ = conn.execute(
-                    "INSERT INTO transactions (date, amount, direction, from_account_id, to_account_id, category_id, notes)
-                     VALUES (?1, ?2, 'transfer', ?3, ?4, ?5, ?6)",
-                    params![date, amt, account_id, tid, cat_id, note],
-                );
-            }
-        }
+    if let Some(gid) = remove_transaction(&tx, id)? {
+        let _ = sync_goal_progress(&tx, gid);
     }
 
+    drop(change);
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(())
 }
 
-#[tauri::command]
-pub fn delete_transaction(db: State<DbConnection>, id: i64) -> Result<(), String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
+/// Deletes a transaction with its tags and split lines. Returns the goal it
+/// was linked to so the caller can re-sync progress.
+pub(crate) fn remove_transaction(conn: &rusqlite::Connection, id: i64) -> Result<Option<i64>, String> {
+    ensure_not_reconciled(conn, id)?;
+    // A loan payment's legs go together with its schedule row
+    let loan_payment: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM loan_payments WHERE principal_transaction_id = ?1 OR interest_transaction_id = ?1)",
+        [id],
+        |r| r.get(0),
+    ).map_err(|e| e.to_string())?;
+    if loan_payment {
+        return Err(format!("Transaction {} is part of a loan payment; delete the loan payment instead", id));
+    }
+    reverse_allocation(conn, id)?;
+
     let goal_id: Option<i64> = conn.query_row(
         "SELECT goal_id FROM transactions WHERE id = ?1",
         [id],
@@ -526,26 +688,24 @@ pub fn delete_transaction(db: State<DbConnection>, id: i64) -> Result<(), String
     ).unwrap_or(None);
 
     conn.execute("DELETE FROM transaction_tags WHERE transaction_id = ?1", [id]).map_err(|e| e.to_string())?;
+    delete_splits(conn, id)?;
     conn.execute("DELETE FROM transactions WHERE id = ?1", [id]).map_err(|e| e.to_string())?;
+    refresh_search_index(conn, id)?;
 
-    if let Some(gid) = goal_id {
-        let _ = sync_goal_progress(&conn, gid);
-    }
-    
-    Ok(())
+    Ok(goal_id)
 }
 
-fn sync_goal_progress(conn: &rusqlite::Connection, goal_id: i64) -> Result<(), String> {
+pub(crate) fn sync_goal_progress(conn: &rusqlite::Connection, goal_id: i64) -> Result<(), String> {
     // 1. Get goal info
-    let (target, _bucket_id): (f64, i64) = conn.query_row(
-        "SELECT target_amount, bucket_id FROM goals WHERE id = ?1",
+    let (target, status): (f64, String) = conn.query_row(
+        "SELECT target_amount, status FROM goals WHERE id = ?1",
         [goal_id],
         |r| Ok((r.get(0)?, r.get(1)?))
     ).map_err(|e| e.to_string())?;
 
     // 2. Sum net contributions linked to this goal
     let incoming: f64 = conn.query_row(
-        "SELECT COALESCE(SUM(amount), 0) FROM transactions WHERE to_account_id = (SELECT bucket_id FROM goals WHERE id = ?1) AND goal_id = ?1",
+        "SELECT COALESCE(SUM(COALESCE(to_amount, amount)), 0) FROM transactions WHERE to_account_id = (SELECT bucket_id FROM goals WHERE id = ?1) AND goal_id = ?1",
         [goal_id],
         |r| r.get(0)
     ).unwrap_or(0.0);
@@ -557,12 +717,78 @@ fn sync_goal_progress(conn: &rusqlite::Connection, goal_id: i64) -> Result<(), S
     ).unwrap_or(0.0);
 
     let current = incoming - outgoing;
-    let status = if current >= target { "completed" } else { "active" };
-
     conn.execute(
-        "UPDATE goals SET current_amount = ?1, status = ?2 WHERE id = ?3",
-        params![current, status, goal_id],
+        "UPDATE goals SET current_amount = ?1 WHERE id = ?2",
+        params![current, goal_id],
     ).map_err(|e| e.to_string())?;
 
+    // An active goal completes once the target is reached. Later withdrawals
+    // leave it completed, and paused or abandoned goals keep their status.
+    if status == "active" && current >= target {
+        record_goal_status(conn, goal_id, "completed", Some("Target reached"))?;
+        conn.execute("UPDATE goals SET status = 'completed' WHERE id = ?1", [goal_id]).map_err(|e| e.to_string())?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn grocery_payment(category_id: i64) -> Transaction {
+        Transaction {
+            id: None,
+            date: "2026-03-01".to_string(),
+            amount: 40.0,
+            direction: "expense".to_string(),
+            from_account_id: Some(1),
+            to_account_id: None,
+            category_id,
+            client_id: None,
+            project_id: None,
+            investment_id: None,
+            goal_id: None,
+            notes: Some("BIGMART store 42".to_string()),
+            external_id: None,
+            currency: None,
+            to_amount: None,
+        }
+    }
+
+    #[test]
+    fn rules_add_tags_client_and_project_but_keep_an_explicit_category() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO accounts (id, name, type, opening_balance) VALUES (1, 'Bank', 'bank', 500);
+             INSERT INTO categories (id, name, kind) VALUES (1, 'Groceries', 'expense'), (2, 'Office', 'expense');
+             INSERT INTO clients (id, name) VALUES (1, 'Acme');
+             INSERT INTO projects (id, name, client_id) VALUES (1, 'Site', 1);
+             INSERT INTO tags (id, name) VALUES (1, 'shop'), (2, 'manual');
+             INSERT INTO categorization_rules (id, name, notes_contains, set_category_id, set_client_id, set_project_id)
+                 VALUES (1, 'Bigmart', 'bigmart', 1, 1, 1);
+             INSERT INTO categorization_rule_tags (rule_id, tag_id) VALUES (1, 1);",
+        ).unwrap();
+        let db = DbConnection(Mutex::new(conn));
+
+        let saved = |id: i64| -> (i64, Option<i64>, Option<i64>, Vec<i64>) {
+            let conn = db.0.lock().unwrap();
+            let tags = conn.prepare("SELECT tag_id FROM transaction_tags WHERE transaction_id = ?1 ORDER BY tag_id").unwrap()
+                .query_map([id], |r| r.get(0)).unwrap()
+                .collect::<Result<Vec<i64>, _>>().unwrap();
+            conn.query_row(
+                "SELECT category_id, client_id, project_id FROM transactions WHERE id = ?1",
+                [id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, tags)),
+            ).unwrap()
+        };
+
+        let categorized = create_transaction(State::from_ref(&db), grocery_payment(2), vec![2]).unwrap();
+        assert_eq!(saved(categorized), (2, Some(1), Some(1), vec![1, 2]));
+
+        let uncategorized = create_transaction(State::from_ref(&db), grocery_payment(0), vec![]).unwrap();
+        assert_eq!(saved(uncategorized), (1, Some(1), Some(1), vec![1]));
+    }
+}
@@ -18,7 +18,14 @@ pub fn initialize_database() -> Result<DbConnection> {
     println!("Database location: {:?}", db_path);
     
     let conn = Connection::open(&db_path)?;
-    
+    init_schema(&conn)?;
+
+    Ok(DbConnection(Mutex::new(conn)))
+}
+
+/// Creates the schema, runs every migration and installs the per-connection
+/// triggers.
+pub(crate) fn init_schema(conn: &Connection) -> Result<()> {
     // Read and execute schema
     let schema = include_str!("../../../database/schema.sql");
     conn.execute_batch(schema)?;
@@ -405,30 +412,36 @@ pub fn initialize_database() -> Result<DbConnection> {
     let _ = conn.execute("ALTER TABLE accounts ADD COLUMN bucket_role TEXT DEFAULT 'none'", []);
     let _ = conn.execute("ALTER TABLE accounts ADD COLUMN is_investment_active INTEGER DEFAULT 1", []);
     
-    // We need a schema swap to update the CHECK constraint for 'bucket' type without losing new columns
-    let _ = conn.execute_batch(
-        "PRAGMA foreign_keys=off;
-         BEGIN TRANSACTION;
-         CREATE TABLE IF NOT EXISTS accounts_v2 (
-             id INTEGER PRIMARY KEY,
-             name TEXT NOT NULL,
-             type TEXT CHECK(type IN ('bank','cash','investment','bucket')) NOT NULL,
-             opening_balance REAL NOT NULL,
-             parent_id INTEGER REFERENCES accounts(id),
-             bucket_role TEXT DEFAULT 'none',
-             is_investment_active INTEGER DEFAULT 1,
-             notes TEXT,
-             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-         );
-         -- Copy everything including the new columns we just ensured exist
-         INSERT OR IGNORE INTO accounts_v2 (id, name, type, opening_balance, parent_id, bucket_role, is_investment_active, notes, created_at) 
-         SELECT id, name, type, opening_balance, parent_id, bucket_role, COALESCE(is_investment_active, 1), notes, created_at FROM accounts;
-         
-         DROP TABLE accounts;
-         ALTER TABLE accounts_v2 RENAME TO accounts;
-         COMMIT;
-         PRAGMA foreign_keys=on;"
-    );
+    // We need a schema swap to update the CHECK constraint for 'bucket' type without losing new columns.
+    // Only swap once: later columns aren't copied, and views over accounts block the rename.
+    let accounts_sql: String = conn
+        .query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'accounts'", [], |r| r.get(0))
+        .unwrap_or_default();
+    if !accounts_sql.contains("'bucket'") {
+        let _ = conn.execute_batch(
+            "PRAGMA foreign_keys=off;
+             BEGIN TRANSACTION;
+             CREATE TABLE IF NOT EXISTS accounts_v2 (
+                 id INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 type TEXT CHECK(type IN ('bank','cash','investment','bucket')) NOT NULL,
+                 opening_balance REAL NOT NULL,
+                 parent_id INTEGER REFERENCES accounts(id),
+                 bucket_role TEXT DEFAULT 'none',
+                 is_investment_active INTEGER DEFAULT 1,
+                 notes TEXT,
+                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+             );
+             -- Copy everything including the new columns we just ensured exist
+             INSERT OR IGNORE INTO accounts_v2 (id, name, type, opening_balance, parent_id, bucket_role, is_investment_active, notes, created_at) 
+             SELECT id, name, type, opening_balance, parent_id, bucket_role, COALESCE(is_investment_active, 1), notes, created_at FROM accounts;
+             
+             DROP TABLE accounts;
+             ALTER TABLE accounts_v2 RENAME TO accounts;
+             COMMIT;
+             PRAGMA foreign_keys=on;"
+        );
+    }
 
     // 32. Create Goals table
     let _ = conn.execute(
@@ -506,10 +519,437 @@ pub fn initialize_database() -> Result<DbConnection> {
         [],
     )?;
 
-    Ok(DbConnection(Mutex::new(conn)))
+    // 41. Add external_id to transactions (OFX FITID / QIF fingerprint) for idempotent statement imports
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN external_id TEXT", []);
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_transactions_external ON transactions(external_id)", []);
+
+    // 42. Split transactions: child lines carry their own category, amount, client, project and tags
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transaction_splits (
+            id INTEGER PRIMARY KEY,
+            transaction_id INTEGER NOT NULL,
+            category_id INTEGER NOT NULL,
+            amount REAL NOT NULL,
+            client_id INTEGER,
+            project_id INTEGER,
+            notes TEXT,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id),
+            FOREIGN KEY (client_id) REFERENCES clients(id),
+            FOREIGN KEY (project_id) REFERENCES projects(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transaction_split_tags (
+            split_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (split_id, tag_id),
+            FOREIGN KEY (split_id) REFERENCES transaction_splits(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id)
+        )",
+        [],
+    )?;
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_transaction_splits_tx ON transaction_splits(transaction_id)", []);
+
+    // 43. Full-text search index over notes, category, client, project and tag names (rowid = transaction id)
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS transaction_search USING fts5(
+            notes, category, client, project, tags,
+            tokenize = 'unicode61 remove_diacritics 2'
+        )",
+        [],
+    )?;
+    // Backfill existing ledgers (and heal drift) when the index is out of step with transactions
+    let indexed_count: i64 = conn.query_row("SELECT COUNT(*) FROM transaction_search", [], |r| r.get(0)).unwrap_or(0);
+    let transaction_count: i64 = conn.query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0)).unwrap_or(0);
+    if indexed_count != transaction_count {
+        let _ = crate::commands::search::rebuild_search_index(conn);
+    }
+
+    // 44. Auto-categorization rules (first match by priority wins)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS categorization_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            notes_contains TEXT,
+            amount_equals REAL,
+            min_amount REAL,
+            max_amount REAL,
+            account_id INTEGER,
+            direction TEXT,
+            set_category_id INTEGER,
+            set_client_id INTEGER,
+            set_project_id INTEGER,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id),
+            FOREIGN KEY (set_category_id) REFERENCES categories(id),
+            FOREIGN KEY (set_client_id) REFERENCES clients(id),
+            FOREIGN KEY (set_project_id) REFERENCES projects(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS categorization_rule_tags (
+            rule_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (rule_id, tag_id),
+            FOREIGN KEY (rule_id) REFERENCES categorization_rules(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id)
+        )",
+        [],
+    )?;
+
+    // 45. Append-only change journal (one row per command, one entry per touched row)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS change_journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            command TEXT NOT NULL,
+            reverts_change_id INTEGER,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (reverts_change_id) REFERENCES change_journal(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS change_journal_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            change_id INTEGER NOT NULL,
+            entity TEXT NOT NULL,
+            entity_id INTEGER,
+            action TEXT NOT NULL,
+            before_json TEXT,
+            after_json TEXT,
+            FOREIGN KEY (change_id) REFERENCES change_journal(id)
+        )",
+        [],
+    )?;
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_change_journal_entries_change ON change_journal_entries(change_id)", []);
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_change_journal_reverts ON change_journal(reverts_change_id)", []);
+
+    // 46. Reconciliation: bank statements and per-transaction cleared state
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_statements (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            end_date TEXT NOT NULL,
+            closing_balance REAL NOT NULL,
+            status TEXT NOT NULL DEFAULT 'open',
+            notes TEXT,
+            reconciled_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        )",
+        [],
+    )?;
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN cleared_status TEXT NOT NULL DEFAULT 'uncleared'", []);
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN statement_id INTEGER REFERENCES account_statements(id)", []);
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_transactions_statement ON transactions(statement_id)", []);
+
+    // 47. Attachments: content-addressed files (stored once) and their links
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attachment_files (
+            hash TEXT PRIMARY KEY,
+            stored_path TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            file_hash TEXT NOT NULL,
+            original_name TEXT NOT NULL,
+            transaction_id INTEGER,
+            invoice_id INTEGER,
+            investment_lot_id INTEGER,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (file_hash) REFERENCES attachment_files(hash),
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE SET NULL,
+            FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL,
+            FOREIGN KEY (investment_lot_id) REFERENCES investment_lots(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_attachments_transaction ON attachments(transaction_id)", []);
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(file_hash)", []);
+
+    // 48. Multi-currency: amounts are stored in the paying account's currency; transfers
+    // into another currency also store the credited amount. Totals convert on read.
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN currency TEXT NOT NULL DEFAULT 'INR'", []);
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN currency TEXT NOT NULL DEFAULT 'INR'", []);
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN to_amount REAL", []);
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS fx_rates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate REAL NOT NULL,
+            source TEXT NOT NULL DEFAULT 'manual',
+            UNIQUE(date, from_currency, to_currency)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS fx_settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            base_currency TEXT NOT NULL DEFAULT 'INR'
+        )",
+        [],
+    )?;
+    conn.execute("INSERT OR IGNORE INTO fx_settings (id, base_currency) VALUES (1, 'INR')", [])?;
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_fx_rates_pair ON fx_rates(from_currency, to_currency, date)", []);
+
+    // 49. Credit cards: limit, billing cycle and the statements generated from each cycle
+    allow_account_type(conn, "credit_card")?;
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN credit_limit REAL", []);
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN statement_day INTEGER", []);
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN due_day INTEGER", []);
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN min_due_percent REAL", []);
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS card_statements (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            period_start TEXT NOT NULL,
+            period_end TEXT NOT NULL,
+            due_date TEXT NOT NULL,
+            billed_amount REAL NOT NULL,
+            minimum_due REAL NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(account_id, period_end),
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        )",
+        [],
+    )?;
+
+    // 50. Loans: liability accounts with amortization terms and recorded EMI/prepayments
+    allow_account_type(conn, "loan")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS loans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL UNIQUE,
+            principal REAL NOT NULL,
+            annual_rate REAL NOT NULL,
+            tenure_months INTEGER NOT NULL,
+            start_date TEXT NOT NULL,
+            emi REAL NOT NULL,
+            category_id INTEGER NOT NULL,
+            interest_category_id INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (account_id) REFERENCES accounts(id),
+            FOREIGN KEY (category_id) REFERENCES categories(id),
+            FOREIGN KEY (interest_category_id) REFERENCES categories(id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS loan_payments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            loan_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            kind TEXT NOT NULL DEFAULT 'emi',
+            amount REAL NOT NULL,
+            interest REAL NOT NULL,
+            principal REAL NOT NULL,
+            outstanding_after REAL NOT NULL,
+            principal_transaction_id INTEGER,
+            interest_transaction_id INTEGER,
+            FOREIGN KEY (loan_id) REFERENCES loans(id) ON DELETE CASCADE,
+            FOREIGN KEY (principal_transaction_id) REFERENCES transactions(id) ON DELETE SET NULL,
+            FOREIGN KEY (interest_transaction_id) REFERENCES transactions(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+    let _ = conn.execute("CREATE INDEX IF NOT EXISTS idx_loan_payments_loan ON loan_payments(loan_id, date)", []);
+
+    // 51. Materialized per-account daily net flows, kept current by triggers
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_daily_balances (
+            account_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            net_amount REAL NOT NULL DEFAULT 0,
+            net_base_amount REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (account_id, date)
+        ) WITHOUT ROWID",
+        [],
+    )?;
+
+    // 52. Closed accounts: hidden from pickers and the dashboard, kept in reports
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN closed_on TEXT", []);
+
+    // 53. Balance assertions: expected account balances on given dates
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS balance_assertions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            expected_balance REAL NOT NULL,
+            notes TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(account_id, date),
+            FOREIGN KEY (account_id) REFERENCES accounts(id)
+        )",
+        [],
+    )?;
+
+    // 54. Allocation tiers with one share row per bucket role, replacing the fixed
+    // emergency/asset/travel columns of allocation_rules
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN target_balance REAL", []);
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS allocation_tiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tier INTEGER NOT NULL UNIQUE,
+            name TEXT,
+            key_role TEXT,
+            fill_below REAL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS allocation_tier_shares (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tier_id INTEGER NOT NULL,
+            bucket_role TEXT NOT NULL,
+            pc REAL NOT NULL,
+            UNIQUE(tier_id, bucket_role),
+            FOREIGN KEY (tier_id) REFERENCES allocation_tiers(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    let tiers_count: i64 = conn.query_row("SELECT COUNT(*) FROM allocation_tiers", [], |r| r.get(0)).unwrap_or(0);
+    if tiers_count == 0 {
+        // Old tiers were keyed on the emergency bucket: below 50%, below 100%, then filled
+        conn.execute_batch(
+            "INSERT INTO allocation_tiers (tier, name, key_role, fill_below)
+             SELECT tier,
+                    CASE tier WHEN 1 THEN 'Emergency below 50%' WHEN 2 THEN 'Emergency below 100%' ELSE 'Emergency filled' END,
+                    CASE WHEN tier IN (1, 2) THEN 'emergency' END,
+                    CASE tier WHEN 1 THEN 0.5 WHEN 2 THEN 1.0 END
+             FROM allocation_rules;
+             INSERT INTO allocation_tier_shares (tier_id, bucket_role, pc)
+             SELECT t.id, 'emergency', r.emergency_pc FROM allocation_rules r JOIN allocation_tiers t ON t.tier = r.tier WHERE r.emergency_pc > 0
+             UNION ALL
+             SELECT t.id, 'asset', r.asset_pc FROM allocation_rules r JOIN allocation_tiers t ON t.tier = r.tier WHERE r.asset_pc > 0
+             UNION ALL
+             SELECT t.id, 'travel', r.travel_pc FROM allocation_rules r JOIN allocation_tiers t ON t.tier = r.tier WHERE r.travel_pc > 0;"
+        )?;
+    }
+
+    // 55. Allocation batches: the transfers auto-allocation made for one income
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS allocation_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_transaction_id INTEGER NOT NULL UNIQUE,
+            tier_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (source_transaction_id) REFERENCES transactions(id),
+            FOREIGN KEY (tier_id) REFERENCES allocation_tiers(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN allocation_batch_id INTEGER REFERENCES allocation_batches(id)", []);
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_transactions_allocation_batch ON transactions(allocation_batch_id)", [])?;
+
+    // 56. Goal status history; existing goals start with their current status
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS goal_status_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            goal_id INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            note TEXT,
+            changed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (goal_id) REFERENCES goals(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "INSERT INTO goal_status_history (goal_id, status, changed_at)
+         SELECT id, COALESCE(status, 'active'), COALESCE(created_at, CURRENT_TIMESTAMP) FROM goals g
+         WHERE NOT EXISTS (SELECT 1 FROM goal_status_history h WHERE h.goal_id = g.id)",
+        [],
+    )?;
+
+    // 57. Envelope budgeting: per-category rollover and recorded moves between envelopes
+    let _ = conn.execute("ALTER TABLE categories ADD COLUMN rollover_mode TEXT NOT NULL DEFAULT 'none'", []);
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS budget_moves (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            month TEXT NOT NULL,
+            from_category_id INTEGER NOT NULL,
+            to_category_id INTEGER NOT NULL,
+            amount REAL NOT NULL,
+            reason TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (from_category_id) REFERENCES categories(id),
+            FOREIGN KEY (to_category_id) REFERENCES categories(id)
+        )",
+        [],
+    )?;
+
+    // 58. Budget periods: calendar months, salary-date cycles or a custom start day
+    let _ = conn.execute("ALTER TABLE budget_settings ADD COLUMN period_mode TEXT NOT NULL DEFAULT 'calendar'", []);
+    let _ = conn.execute("ALTER TABLE budget_settings ADD COLUMN period_start_day INTEGER", []);
+
+    // 59. Per-account balance floor for cash-flow forecast warnings
+    let _ = conn.execute("ALTER TABLE accounts ADD COLUMN balance_floor REAL", []);
+
+    // 60. EMI in force before a reduce_emi prepayment, restored when the payment is deleted
+    let _ = conn.execute("ALTER TABLE loan_payments ADD COLUMN previous_emi REAL", []);
+
+    // 61. Transactions entered in a foreign currency keep the amount and rate they were converted at
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN original_currency TEXT", []);
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN original_amount REAL", []);
+    let _ = conn.execute("ALTER TABLE transactions ADD COLUMN original_rate REAL", []);
+
+    // Conversion views and the split-aware transaction_lines view
+    crate::commands::currency::create_currency_views(conn)?;
+
+    // Balance triggers are TEMP like the journal's; they read account_flows so come after the views
+    crate::commands::balances::install_balance_triggers(conn)?;
+
+    // Journal triggers are TEMP and must be installed on every connection, after all migrations
+    crate::commands::journal::install_journal_triggers(conn)?;
+
+    Ok(())
+}
+
+// Adds a value to the accounts.type CHECK list. SQLite can't alter a CHECK, so the
+// table is rebuilt from its own schema (keeping every column added since).
+fn allow_account_type(conn: &Connection, account_type: &str) -> Result<()> {
+    let sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'accounts'",
+        [],
+        |r| r.get(0),
+    )?;
+    if sql.contains(&format!("'{}'", account_type)) {
+        return Ok(());
+    }
+
+    let columns = &sql[sql.find('(').unwrap_or(0)..];
+    let columns = columns.replacen("'bucket'", &format!("'bucket','{}'", account_type), 1);
+    // legacy_alter_table keeps the rename from re-validating views that read accounts
+    conn.execute_batch(&format!(
+        "PRAGMA foreign_keys=off;
+         PRAGMA legacy_alter_table=on;
+         BEGIN TRANSACTION;
+         DROP TABLE IF EXISTS accounts_v2;
+         CREATE TABLE accounts_v2 {};
+         INSERT INTO accounts_v2 SELECT * FROM accounts;
+         DROP TABLE accounts;
+         ALTER TABLE accounts_v2 RENAME TO accounts;
+         COMMIT;
+         PRAGMA legacy_alter_table=off;
+         PRAGMA foreign_keys=on;",
+        columns
+    ))
 }
 
-fn get_app_data_dir() -> PathBuf {
+pub(crate) fn get_app_data_dir() -> PathBuf {
     // For Windows: C:\Users\Username\AppData\Roaming\com.moneytracker.app
     if let Some(data_dir) = dirs::data_dir() {
         data_dir.join("com.moneytracker.app")
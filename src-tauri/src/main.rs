@@ -18,6 +18,9 @@ fn main() {
             create_account,
             update_account,
             delete_account,
+            close_account,
+            reopen_account,
+            merge_accounts,
             get_categories,
             create_category,
             update_category,
@@ -30,11 +33,16 @@ fn main() {
             get_tags,
             create_tag,
             get_transactions,
+            query_transactions,
+            search_transactions,
+            bulk_edit_transactions,
             get_transaction_balances,
             create_transaction,
             update_transaction,
             get_transaction_tags,
             delete_transaction,
+            get_transaction_splits,
+            set_transaction_splits,
             get_monthly_summary,
             get_category_summary,
             get_client_summary,
@@ -68,15 +76,39 @@ fn main() {
             delete_scheduled_transaction,
             process_pending_schedules,
             get_monthly_budget,
+            get_budget_settings,
+            update_budget_settings,
+            get_budget_period,
+            get_monthly_incomes,
+            set_monthly_income,
+            delete_monthly_income,
+            get_income_variance,
+            get_category_budgets,
+            set_category_budget,
+            copy_budgets_forward,
+            clear_category_budgets,
+            get_budget_envelopes,
+            move_budget_between_categories,
+            get_budget_moves,
+            delete_budget_move,
             // Goals & Allocation
             get_goals,
             create_goal,
             update_goal,
             delete_goal,
+            get_goal_projections,
+            get_goal_timeline,
+            set_goal_status,
             get_allocation_settings,
             update_allocation_settings,
             get_allocation_rules,
+            create_allocation_rule,
             update_allocation_rule,
+            delete_allocation_rule,
+            preview_allocation,
+            get_allocation_batch,
+            // Forecast
+            get_cash_flow_forecast,
             // Company Settings commands
             save_pdf,
             open_file_folder,
@@ -89,6 +121,63 @@ fn main() {
             get_net_worth_trend,
             get_asset_allocation,
             get_source_category_breakdown,
+            // Statement Import
+            preview_csv_import,
+            preview_statement_import,
+            commit_import,
+            // Categorization Rules
+            get_categorization_rules,
+            create_categorization_rule,
+            update_categorization_rule,
+            delete_categorization_rule,
+            reapply_categorization_rules,
+            // Change Journal
+            get_change_journal,
+            undo_last_change,
+            revert_change,
+            // Reconciliation
+            get_account_statements,
+            create_account_statement,
+            delete_account_statement,
+            get_reconciliation_summary,
+            set_transactions_cleared,
+            finish_reconciliation,
+            unreconcile_statement,
+            unreconcile_transaction,
+            // Attachments
+            add_attachment,
+            get_attachments,
+            open_attachment,
+            delete_attachment,
+            cleanup_orphan_attachments,
+            // Currency
+            get_base_currency,
+            set_base_currency,
+            get_fx_rates,
+            save_fx_rate,
+            delete_fx_rate,
+            import_fx_rates_csv,
+            get_missing_fx_currencies,
+            get_fx_gain_loss,
+            // Credit Cards
+            generate_card_statements,
+            get_card_statements,
+            get_credit_card_summaries,
+            // Loans
+            create_loan,
+            get_loans,
+            get_loan_schedule,
+            record_loan_payment,
+            record_loan_prepayment,
+            delete_loan_payment,
+            // Balances
+            rebuild_account_balances,
+            verify_account_balances,
+            // Balance Assertions
+            create_balance_assertion,
+            delete_balance_assertion,
+            get_balance_assertions,
+            locate_assertion_divergence,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
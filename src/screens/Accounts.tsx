@@ -367,16 +367,28 @@ export default function Accounts() {
                                     </div>
                                     <div>
                                         <label className={darkTheme.label}>Bucket Role</label>
-                                        <select
+                                        <input
+                                            list="bucket-roles"
                                             value={formData.bucket_role}
-                                            onChange={(e) => setFormData({ ...formData, bucket_role: e.target.value as any })}
-                                            className={darkTheme.select}
-                                        >
+                                            onChange={(e) => setFormData({ ...formData, bucket_role: e.target.value || 'none' })}
+                                            className={darkTheme.input}
+                                        />
+                                        <datalist id="bucket-roles">
                                             <option value="none">Generic</option>
                                             <option value="emergency">Emergency</option>
                                             <option value="asset">Asset</option>
                                             <option value="travel">Travel</option>
-                                        </select>
+                                        </datalist>
+                                    </div>
+                                    <div>
+                                        <label className={darkTheme.label}>Target Balance</label>
+                                        <input
+                                            type="number"
+                                            value={formData.target_balance ?? ''}
+                                            onChange={(e) => setFormData({ ...formData, target_balance: e.target.value ? parseFloat(e.target.value) : undefined })}
+                                            placeholder="Uses the emergency target when empty"
+                                            className={darkTheme.input}
+                                        />
                                     </div>
                                 </div>
                             )}
@@ -394,6 +406,18 @@ export default function Accounts() {
                                 />
                             </div>
 
+                            <div>
+                                <label className={darkTheme.label}>Balance Floor</label>
+                                <input
+                                    type="number"
+                                    step="0.01"
+                                    value={formData.balance_floor ?? ''}
+                                    onChange={(e) => setFormData({ ...formData, balance_floor: e.target.value ? parseFloat(e.target.value) : undefined })}
+                                    className={darkTheme.input}
+                                    placeholder="Forecast warns below this balance"
+                                />
+                            </div>
+
                             <div>
                                 <label className={darkTheme.label}>Notes</label>
                                 <textarea
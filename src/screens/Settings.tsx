@@ -1,6 +1,6 @@
 import React, { useEffect, useState } from 'react';
 import { useDatabase } from '../hooks/useDatabase';
-import type { Category, Account, Goal, AllocationSettings, AllocationRule } from '../types';
+import type { Category, Account, Goal, AllocationSettings, AllocationRule, AllocationShare } from '../types';
 import { formatCurrency } from '../utils/formatters';
 import { darkTheme } from '../utils/theme';
 import Swal from 'sweetalert2';
@@ -59,11 +59,11 @@ export default function Settings() {
     };
 
     const handleUpdateRule = async (rule: AllocationRule) => {
-        const total = rule.emergency_pc + rule.asset_pc + rule.travel_pc;
-        if (Math.abs(total - 1) > 0.001) {
+        const total = rule.shares.reduce((sum, share) => sum + share.pc, 0);
+        if (total > 1.001) {
             Swal.fire({
                 title: 'Invalid Percentage',
-                text: `Total must be 100%. Current sum: ${Math.round(total * 100)}%`,
+                text: `Total must not exceed 100%; the rest stays in the account. Current sum: ${Math.round(total * 100)}%`,
                 icon: 'error',
                 background: '#0f172a',
                 color: '#f1f5f9'
@@ -72,7 +72,7 @@ export default function Settings() {
         }
 
         try {
-            await execute('update_allocation_rule', { rule });
+            await execute(rule.id ? 'update_allocation_rule' : 'create_allocation_rule', { rule });
             Swal.fire({ title: 'Rule Updated', icon: 'success', background: '#0f172a', color: '#f1f5f9', timer: 1000, showConfirmButton: false });
             loadData();
         } catch (error) {
@@ -80,13 +80,41 @@ export default function Settings() {
         }
     };
 
-    const handleRuleChange = (index: number, field: string, value: string) => {
+    const handleRuleChange = (index: number, changes: Partial<AllocationRule>) => {
         const newRules = [...rules];
-        const numValue = parseFloat(value) / 100;
-        newRules[index] = { ...newRules[index], [field]: isNaN(numValue) ? 0 : numValue };
+        newRules[index] = { ...newRules[index], ...changes };
         setRules(newRules);
     };
 
+    const handleShareChange = (index: number, shareIndex: number, changes: Partial<AllocationShare>) => {
+        const shares = [...rules[index].shares];
+        shares[shareIndex] = { ...shares[shareIndex], ...changes };
+        handleRuleChange(index, { shares });
+    };
+
+    const handleAddRule = () => {
+        const nextTier = rules.reduce((max, r) => Math.max(max, r.tier), 0) + 1;
+        setRules([...rules, { tier: nextTier, shares: [] }]);
+    };
+
+    const handleDeleteRule = async (rule: AllocationRule) => {
+        if (!rule.id) {
+            setRules(rules.filter(r => r !== rule));
+            return;
+        }
+        try {
+            await execute('delete_allocation_rule', { id: rule.id });
+            loadData();
+        } catch (error) {
+            console.error('Failed to delete rule:', error);
+        }
+    };
+
+    const percentValue = (value: string) => {
+        const numValue = parseFloat(value) / 100;
+        return isNaN(numValue) ? 0 : numValue;
+    };
+
     const handleCreateGoal = async (e: React.FormEvent) => {
         e.preventDefault();
         try {
@@ -121,13 +149,10 @@ export default function Settings() {
         }
     };
 
-    const getTierTitle = (tier: number) => {
-        switch (tier) {
-            case 1: return 'Tier 1: Emergency Fund < 50%';
-            case 2: return 'Tier 2: Emergency Fund 50% - 100%';
-            case 3: return 'Tier 3: Emergency Fund Completed (> 100%)';
-            default: return `Tier ${tier}`;
-        }
+    const getTierTitle = (rule: AllocationRule) => {
+        if (rule.name) return `Tier ${rule.tier}: ${rule.name}`;
+        if (rule.key_role) return `Tier ${rule.tier}: ${rule.key_role} < ${Math.round((rule.fill_below || 0) * 100)}%`;
+        return `Tier ${rule.tier}: Otherwise`;
     };
 
     if (loading && categories.length === 0) {
@@ -183,48 +208,96 @@ export default function Settings() {
                 </div>
 
                 <div className="mb-6">
-                    <h3 className="text-sm font-bold text-slate-500 uppercase tracking-widest mb-4">Allocation Percentages by Tier</h3>
+                    <div className="flex justify-between items-center mb-4">
+                        <h3 className="text-sm font-bold text-slate-500 uppercase tracking-widest">Allocation Percentages by Tier</h3>
+                        <button
+                            onClick={handleAddRule}
+                            className="text-[10px] bg-blue-600/20 text-blue-400 px-2 py-1 rounded border border-blue-500/20 hover:bg-blue-600/30 font-bold"
+                        >
+                            + Add Tier
+                        </button>
+                    </div>
                     <div className="space-y-4">
                         {rules.map((rule, idx) => (
-                            <div key={rule.id} className="p-4 bg-slate-900/50 rounded-xl border border-slate-700/50">
+                            <div key={rule.id ?? `new-${idx}`} className="p-4 bg-slate-900/50 rounded-xl border border-slate-700/50">
                                 <div className="flex justify-between items-center mb-4">
-                                    <span className="text-xs font-bold text-blue-400 uppercase tracking-wide">{getTierTitle(rule.tier)}</span>
-                                    <button 
-                                        onClick={() => handleUpdateRule(rule)}
-                                        className="text-[10px] bg-blue-600/20 text-blue-400 px-2 py-1 rounded border border-blue-500/20 hover:bg-blue-600/30 font-bold"
-                                    >
-                                        Save Rule
-                                    </button>
+                                    <span className="text-xs font-bold text-blue-400 uppercase tracking-wide">{getTierTitle(rule)}</span>
+                                    <div className="flex gap-2">
+                                        <button 
+                                            onClick={() => handleDeleteRule(rule)}
+                                            className="text-[10px] bg-red-600/20 text-red-400 px-2 py-1 rounded border border-red-500/20 hover:bg-red-600/30 font-bold"
+                                        >
+                                            Delete
+                                        </button>
+                                        <button 
+                                            onClick={() => handleUpdateRule(rule)}
+                                            className="text-[10px] bg-blue-600/20 text-blue-400 px-2 py-1 rounded border border-blue-500/20 hover:bg-blue-600/30 font-bold"
+                                        >
+                                            Save Rule
+                                        </button>
+                                    </div>
                                 </div>
-                                <div className="grid grid-cols-3 gap-4">
+                                <div className="grid grid-cols-3 gap-4 mb-4">
                                     <div>
-                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">Emergency (%)</label>
+                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">Order</label>
                                         <input 
                                             type="number"
-                                            value={Math.round(rule.emergency_pc * 100)}
-                                            onChange={(e) => handleRuleChange(idx, 'emergency_pc', e.target.value)}
+                                            value={rule.tier}
+                                            onChange={(e) => handleRuleChange(idx, { tier: parseInt(e.target.value) || 0 })}
                                             className={`${darkTheme.input} !py-1 text-center`}
                                         />
                                     </div>
                                     <div>
-                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">Asset (%)</label>
+                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">While Bucket Role</label>
                                         <input 
-                                            type="number"
-                                            value={Math.round(rule.asset_pc * 100)}
-                                            onChange={(e) => handleRuleChange(idx, 'asset_pc', e.target.value)}
+                                            value={rule.key_role || ''}
+                                            onChange={(e) => handleRuleChange(idx, { key_role: e.target.value || undefined })}
+                                            placeholder="Always"
                                             className={`${darkTheme.input} !py-1 text-center`}
                                         />
                                     </div>
                                     <div>
-                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">Travel (%)</label>
+                                        <label className="text-[10px] text-slate-500 block mb-1 uppercase font-bold">Is Below (% of target)</label>
                                         <input 
                                             type="number"
-                                            value={Math.round(rule.travel_pc * 100)}
-                                            onChange={(e) => handleRuleChange(idx, 'travel_pc', e.target.value)}
+                                            value={rule.fill_below !== undefined && rule.fill_below !== null ? Math.round(rule.fill_below * 100) : ''}
+                                            onChange={(e) => handleRuleChange(idx, { fill_below: e.target.value ? percentValue(e.target.value) : undefined })}
                                             className={`${darkTheme.input} !py-1 text-center`}
                                         />
                                     </div>
                                 </div>
+                                <div className="grid grid-cols-3 gap-4">
+                                    {rule.shares.map((share, shareIdx) => (
+                                        <div key={shareIdx}>
+                                            <div className="flex justify-between items-center mb-1">
+                                                <input 
+                                                    value={share.bucket_role}
+                                                    onChange={(e) => handleShareChange(idx, shareIdx, { bucket_role: e.target.value })}
+                                                    placeholder="Bucket role"
+                                                    className="text-[10px] text-slate-500 uppercase font-bold bg-transparent w-full"
+                                                />
+                                                <button
+                                                    onClick={() => handleRuleChange(idx, { shares: rule.shares.filter((_, i) => i !== shareIdx) })}
+                                                    className="text-[10px] text-slate-500 hover:text-red-400 ml-2"
+                                                >
+                                                    ✕
+                                                </button>
+                                            </div>
+                                            <input 
+                                                type="number"
+                                                value={Math.round(share.pc * 100)}
+                                                onChange={(e) => handleShareChange(idx, shareIdx, { pc: percentValue(e.target.value) })}
+                                                className={`${darkTheme.input} !py-1 text-center`}
+                                            />
+                                        </div>
+                                    ))}
+                                    <button
+                                        onClick={() => handleRuleChange(idx, { shares: [...rule.shares, { bucket_role: '', pc: 0 }] })}
+                                        className="text-[10px] text-blue-400 border border-dashed border-slate-700 rounded-lg hover:bg-slate-800/50 font-bold"
+                                    >
+                                        + Bucket
+                                    </button>
+                                </div>
                             </div>
                         ))}
                     </div>
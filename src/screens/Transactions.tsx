@@ -621,14 +621,14 @@ export default function Transactions() {
 
                             {/* Category */}
                             <div>
-                                <label className={darkTheme.label}>Category *</label>
+                                <label className={darkTheme.label}>Category {formData.id ? '*' : ''}</label>
                                 <select
-                                    required
+                                    required={!!formData.id}
                                     value={formData.category_id || ''}
-                                    onChange={(e) => setFormData({ ...formData, category_id: parseInt(e.target.value) })}
+                                    onChange={(e) => setFormData({ ...formData, category_id: e.target.value ? parseInt(e.target.value) : 0 })}
                                     className={darkTheme.select}
                                 >
-                                    <option value="">Select Category</option>
+                                    <option value="">{formData.id ? 'Select Category' : 'Auto (categorization rules)'}</option>
                                     {filteredCategories.map((cat) => (
                                         <option key={cat.id} value={cat.id}>{cat.name}</option>
                                     ))}
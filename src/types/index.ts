@@ -5,9 +5,11 @@ export interface Account {
     opening_balance: number;
     current_balance?: number;
     parent_id?: number;
-    bucket_role?: 'emergency' | 'asset' | 'travel' | 'none';
+    bucket_role?: string; // 'none' or any role referenced by allocation tiers
     is_investment_active?: boolean;
     notes?: string;
+    target_balance?: number; // Buckets: fill target for allocation tiers
+    balance_floor?: number; // Forecast warns below this balance
 }
 
 export interface Goal {
@@ -16,7 +18,7 @@ export interface Goal {
     name: string;
     target_amount: number;
     current_amount: number;
-    status: 'active' | 'completed';
+    status: 'active' | 'completed' | 'paused' | 'abandoned';
     deadline?: string;
 }
 
@@ -26,12 +28,18 @@ export interface AllocationSettings {
     is_enabled: boolean;
 }
 
+export interface AllocationShare {
+    bucket_role: string;
+    pc: number; // Fraction of the income, 0.25 = 25%
+}
+
 export interface AllocationRule {
     id?: number;
     tier: number;
-    emergency_pc: number;
-    asset_pc: number;
-    travel_pc: number;
+    name?: string;
+    key_role?: string;   // Tier applies while this bucket is below fill_below of its target
+    fill_below?: number; // Fraction, 0.5 = 50%
+    shares: AllocationShare[];
 }
 
 
@@ -108,6 +116,7 @@ export interface Category {
     is_investment: boolean;
     include_in_budget?: boolean;
     include_in_income_breakdown?: boolean;
+    rollover_mode?: 'none' | 'carry_positive' | 'carry_both';
 }
 
 export interface Client {
@@ -344,6 +353,8 @@ export interface InvestmentRate {
 
 export interface BudgetSettings {
     salary_date: number;
+    period_mode: 'calendar' | 'salary' | 'custom';
+    period_start_day?: number;
 }
 
 export interface MonthlyIncome {